@@ -40,6 +40,18 @@ typedef struct {
 
 } cxs_status_t;
 
+/**
+ * Bulk status
+ *
+ * Every object type has a pair of list_state functions. The first fills a caller supplied
+ * status_array with room for *count entries, sets *count to the number of objects and
+ * returns an error when they did not all fit. The _alloc variant allocates the array, which
+ * must be released with cxs_status_list_free. The msg of each status is owned by the SDK.
+ */
+
+/** Frees an array from a list_state_alloc call. count must be exactly the count that call returned. */
+cxs_error_t cxs_status_list_free(cxs_status_t *status_array, unsigned int count);
+
 
 /**
  * Initialize the SDK
@@ -54,17 +66,32 @@ cxs_error_t cxs_init(const char *config_path);
  * For creating, validating and committing a schema to the sovrin ledger.
  */
 
-/** Creates a schema from a json string. Populates a handle to the new schema. */
-cxs_error_t cxs_schema_create(const char *schema_data, cxs_schema_handle_t *schema_handle);
+/** Creates a schema from a json string {"name":"","version":"","attr_names":[]}. Populates a handle to the new schema. */
+cxs_error_t cxs_schema_create(cxs_command_handle_t command_handle, const char *source_id, const char *schema_data, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_schema_handle_t schema_handle));
 
 /** Asynchronously commits the schema to the ledger. */
-cxs_error_t cxs_schema_commit(cxs_schema_handle_t schema_handle);
+cxs_error_t cxs_schema_commit(cxs_command_handle_t command_handle, cxs_schema_handle_t schema_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
 
 /** Populates data with the contents of the schema handle. */
-cxs_error_t cxs_schema_get_data(cxs_schema_handle_t schema_handle, char *data);
+cxs_error_t cxs_schema_get_data(cxs_command_handle_t command_handle, cxs_schema_handle_t schema_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *data));
 
 /** Populates sequence_no with the actual sequence number of the schema on the sovrin ledger. */
-cxs_error_t cxs_schema_get_sequence_no(cxs_schema_handle_t schema_handle, int *sequence_no);
+cxs_error_t cxs_schema_get_sequence_no(cxs_command_handle_t command_handle, cxs_schema_handle_t schema_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, unsigned int sequence_no));
+
+/** Asynchronously populates state with the json of the schema object, which cxs_schema_deserialize reads back. */
+cxs_error_t cxs_schema_serialize(cxs_command_handle_t command_handle, cxs_schema_handle_t schema_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *state));
+
+/** Re-creates a schema object from the specified serialization. */
+cxs_error_t cxs_schema_deserialize(cxs_command_handle_t command_handle, const char *serialized_schema, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_schema_handle_t schema_handle));
+
+/** Releases the schema from memory. */
+cxs_error_t cxs_schema_release(cxs_schema_handle_t schema_handle);
+
+/** Populates status_array with the state of each schema handle. */
+cxs_error_t cxs_schema_list_state(cxs_status_t *status_array, unsigned int *count);
+
+/** Allocates status_array with the state of each schema handle. */
+cxs_error_t cxs_schema_list_state_alloc(cxs_status_t **status_array, unsigned int *count);
 
 
 /**
@@ -73,17 +100,26 @@ cxs_error_t cxs_schema_get_sequence_no(cxs_schema_handle_t schema_handle, int *s
  * For creating, validating and committing a claim definition to the sovrin ledger.
  */
 
-/** Creates a claim definition from the given schema.  Populates a handle to the new claimdef. */
-cxs_error_t cxs_claimdef_create(cxs_schema_handle_t schema_handle, cxs_claimdef_handle_t *claimdef_handle);
+/** Creates a claim definition from the given schema, along with the revocation registry its issued claims are revoked through.  Populates a handle to the new claimdef. */
+cxs_error_t cxs_claimdef_create(cxs_command_handle_t command_handle, const char *source_id, cxs_schema_handle_t schema_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_claimdef_handle_t claimdef_handle));
 
 /** Asynchronously commits the claimdef to the ledger.  */
-cxs_error_t cxs_claimdef_commit(cxs_claimdef_handle_t claimdef_handle);
+cxs_error_t cxs_claimdef_commit(cxs_command_handle_t command_handle, cxs_claimdef_handle_t claimdef_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
 
 /** Populates sequence_no with the actual sequence number of the claimdef on the sovrin ledger. */
-cxs_error_t cxs_claimdef_get_sequence_no(cxs_claimdef_handle_t claimdef_handle, int *sequence_no);
+cxs_error_t cxs_claimdef_get_sequence_no(cxs_command_handle_t command_handle, cxs_claimdef_handle_t claimdef_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, unsigned int sequence_no));
 
 /** Populates data with the contents of the claimdef handle. */
-cxs_error_t cxs_claimdef_get(cxs_claimdef_handle_t claimdef_handle, char *data);
+cxs_error_t cxs_claimdef_get(cxs_command_handle_t command_handle, cxs_claimdef_handle_t claimdef_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *data));
+
+/** Releases the claimdef from memory. */
+cxs_error_t cxs_claimdef_release(cxs_claimdef_handle_t claimdef_handle);
+
+/** Populates status_array with the state of each claimdef handle. */
+cxs_error_t cxs_claimdef_list_state(cxs_status_t *status_array, unsigned int *count);
+
+/** Allocates status_array with the state of each claimdef handle. */
+cxs_error_t cxs_claimdef_list_state_alloc(cxs_status_t **status_array, unsigned int *count);
 
 
 /**
@@ -96,41 +132,105 @@ cxs_error_t cxs_claimdef_get(cxs_claimdef_handle_t claimdef_handle, char *data);
 /** Creates a connection object to a specific identity owner. Populates a handle to the new connection. */
 cxs_error_t cxs_connection_create(const char *recipient_info, cxs_connection_handle_t *connection_handle);
 
+/** Asynchronously accepts an invitation sent by another enterprise. invite_details is the inviter's invite detail json or invite link, source_id may be null. Populates connection_handle with a connection already in the accepted state. */
+cxs_error_t cxs_connection_accept_invitation(cxs_command_handle_t command_handle, const char *source_id, const char *invite_details, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_connection_handle_t connection_handle));
+
 /** Asynchronously request a connection be made. */
 cxs_error_t cxs_connection_connect(cxs_connection_handle_t connection_handle, const char *connection_type);
 
-/** Returns the contents of the connection handle or null if the connection does not exist. */
+/** Sends a fresh invitation on the same connection handle when its invitation expired or is still unanswered. connection_options may be null. */
+cxs_error_t cxs_connection_reinvite(cxs_connection_handle_t connection_handle, const char *connection_options);
+
+/** Returns the contents of the connection handle or null if the connection does not exist.
+ * state_history lists every state change of the connection with its timestamp, oldest first. */
 char *cxs_connection_get_data(cxs_connection_handle_t connection_handle);
 
+/** Asynchronously serializes the connection, including its keys, endpoint and invite details, in a versioned format. */
+cxs_error_t cxs_connection_serialize(cxs_connection_handle_t connection_handle, void (*cb)(cxs_connection_handle_t connection_handle, cxs_error_t err, const char *connection_data));
+
+/** Asynchronously re-creates a connection from the output of cxs_connection_serialize. Populates connection_handle with a new handle. */
+cxs_error_t cxs_connection_deserialize(cxs_command_handle_t command_handle, const char *connection_data, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_connection_handle_t connection_handle));
+
 /** Populates status with the current state of the asynchronous connection request. */
 cxs_error_t cxs_connection_get_state(cxs_connection_handle_t connection_handle, cxs_claim_state_t *status);
 
+/** Populates connection_handle with the newest connection created with source_id. */
+cxs_error_t cxs_connection_get_handle_by_source_id(const char *source_id, cxs_connection_handle_t *connection_handle);
+
+/** Populates connection_handle with the connection whose own pairwise did is did. */
+cxs_error_t cxs_connection_get_handle_by_did(const char *did, cxs_connection_handle_t *connection_handle);
+
+/** Populates connection_handle with the newest connection to the party with the given did. */
+cxs_error_t cxs_connection_get_handle_by_their_did(const char *their_did, cxs_connection_handle_t *connection_handle);
+
+/**
+ * Populates connection_handle with the newest connection to the party with the given verkey.
+ * Only connections made with cxs_connection_accept_invitation know the other party's verkey;
+ * connections that sent the invitation are not found.
+ */
+cxs_error_t cxs_connection_get_handle_by_their_verkey(const char *their_verkey, cxs_connection_handle_t *connection_handle);
+
 /** Releases the connection from memory. */
 cxs_error_t cxs_connection_release(cxs_connection_handle_t connection_handle);
 
+/**
+ * Asynchronously deletes the pairwise relationship at the agent, marks the pairwise did retired in the wallet and releases the connection.
+ * A nonzero notify_remote first sends an accepted connection's other party a "connDeleted" message with the payload
+ * {"msg_type":"CONN_DELETED","version":"0.1","from_did":<our pairwise did>}; the connection is deleted even when that message can't be sent.
+ * Connections without a pairwise did yet are released without contacting the agent.
+ */
+cxs_error_t cxs_connection_delete_connection(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, unsigned int notify_remote, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
+
+/** Populates status_array with the state of each connection handle. */
+cxs_error_t cxs_connection_list_state(cxs_status_t *status_array, unsigned int *count);
+
+/** Allocates status_array with the state of each connection handle. */
+cxs_error_t cxs_connection_list_state_alloc(cxs_status_t **status_array, unsigned int *count);
+
+/** Asynchronously populates invite_details with the json of the invitation the connection sent or accepted. */
+cxs_error_t cxs_connection_get_invite_details(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *invite_details));
+
+/** Asynchronously populates link with a deep link that carries the connection's invitation. The link can be passed to cxs_connection_accept_invitation. */
+cxs_error_t cxs_connection_get_invite_link(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *link));
+
+/** Asynchronously renders the invite link as a QR code. image_format is "svg" or "png"; image is only valid during the callback. */
+cxs_error_t cxs_connection_get_invite_qr_code(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, const char *image_format, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const unsigned char *image, unsigned int image_len));
+
+/** Asynchronously lists the messages of the connection, oldest first. filter is a json object with optional msg_type, status_code, sender_did, offset and limit. Populates messages with {"total":n,"msgs":[{"uid","type","status","sender","timestamp"}]}. */
+cxs_error_t cxs_connection_get_messages(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, const char *filter, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *messages));
+
+/** Asynchronously fetches the message with the given uid from the connection. Populates message with the json of the message, including its payload. */
+cxs_error_t cxs_connection_get_message(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, const char *uid, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *message));
+
+/** Asynchronously sets the status of messages stored at the connection's agent. uids is a json array of message uids, status_code the new status, e.g. MS-106 for reviewed or MS-105 for rejected. */
+cxs_error_t cxs_connection_update_message_status(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, const char *uids, const char *status_code, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
+
 /**
  * claim issuer object
  *
  * Used for offering and managing a claim with an identity owner.
  */
 
-/** Creates a claim object from the specified claimdef handle. Populates a handle the new claim. */
-cxs_error_t cxs_issuer_create_claim(cxs_command_handle_t, const char *source_id, cxs_claimdef_handle_t claimdef_handle, const char * claim_data, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_claim_handle_t *claim_handle));
+/** Creates a claim object from the specified claimdef handle. Populates a handle the new claim. On failure message says what is wrong, for bad claim_data it lists the missing, unknown and malformed attributes. */
+cxs_error_t cxs_issuer_create_claim(cxs_command_handle_t, const char *source_id, cxs_claimdef_handle_t claimdef_handle, const char * claim_data, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_claim_handle_t *claim_handle, const char *message));
+
+/** Checks claim_data against the schema of the claimdef. On failure message lists the missing, unknown and malformed attributes. */
+cxs_error_t cxs_issuer_claim_validate_data(cxs_command_handle_t command_handle, cxs_claimdef_handle_t claimdef_handle, const char *claim_data, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *message));
 
 /** Asynchronously sends the claim offer to the connection. */
-cxs_error_t cxs_issuer_send_claim_offer(cxs_claim_handle_t claim_handle, cxs_connection_handle_t connection_handle);
+cxs_error_t cxs_issuer_send_claim_offer(cxs_command_handle_t command_handle, cxs_claim_handle_t claim_handle, cxs_connection_handle_t connection_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
 
 /** Populates claim_request with the latest claim request received. */
-cxs_error_t cxs_issuer_get_claim_request(cxs_claim_handle_t claim_handle, char *claim_request);
+cxs_error_t cxs_issuer_get_claim_request(cxs_command_handle_t command_handle, cxs_claim_handle_t claim_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *claim_request));
 
-/** Sets the claim request in an accepted state. */
-cxs_error_t cxs_issuer_accept_claim(cxs_claim_handle_t claim_handle);
+/** Accepts the received claim request and signs the claim. */
+cxs_error_t cxs_issuer_accept_claim(cxs_command_handle_t command_handle, cxs_claim_handle_t claim_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
 
-/** Asynchronously send the claim to the connection. Populates a handle to the new transaction. */
-cxs_error_t cxs_issuer_send_claim(cxs_claim_handle_t claim_handle, cxs_connection_handle_t connection_handle);
+/** Asynchronously sends the signed claim to the connection the offer was sent to, accepting the claim request first if needed. Any other connection handle is rejected. */
+cxs_error_t cxs_issuer_send_claim(cxs_command_handle_t command_handle, cxs_claim_handle_t claim_handle, cxs_connection_handle_t connection_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
 
-/** Terminates a claim for the specified reason. */
-cxs_error_t cxs_issuer_terminate_claim(cxs_claim_handle_t claim_handle, cxs_claim_state_t state_type, const char *msg);
+/** Terminates a claim for the specified reason. Issued claims are revoked through the claim definition's revocation registry, claims that were not issued yet are left unfulfilled and the holder is sent msg. */
+cxs_error_t cxs_issuer_terminate_claim(cxs_command_handle_t command_handle, cxs_claim_handle_t claim_handle, cxs_claim_state_t state_type, const char *msg, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
 
 /** Populates status with the current state of this claim. */
 cxs_error_t cxs_issuer_claim_serialize(cxs_claim_handle_t claim_handle, void (*cb)(cxs_claim_handle_t claim_handle, cxs_error_t err, const char *state));
@@ -138,32 +238,66 @@ cxs_error_t cxs_issuer_claim_serialize(cxs_claim_handle_t claim_handle, void (*c
 /** Re-creates a claim object from the specified serialization. */
 cxs_error_t cxs_issuer_claim_deserialize(cxs_command_handle_t, const char *serialized_claim, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_claim_handle_t *claim_handle));
 
+/** Populates status_array with the state of each claim handle. */
+cxs_error_t cxs_issuer_claim_list_state(cxs_status_t *status_array, unsigned int *count);
+
+/** Allocates status_array with the state of each claim handle. */
+cxs_error_t cxs_issuer_claim_list_state_alloc(cxs_status_t **status_array, unsigned int *count);
+
 /**
  * proof object
  *
  * Used for requesting and managing a proof request with an identity owner.
  */
 
-/** Creates a proof object.  Populates a handle to the new proof. */
-cxs_error_t cxs_proof_create(const char *proof_request_data, cxs_proof_handle_t *proof_handle);
+/** Creates a proof object from the proof request json.  Populates a handle to the new proof. */
+cxs_error_t cxs_proof_create(cxs_command_handle_t command_handle, const char *source_id, const char *proof_request_data, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_proof_handle_t proof_handle));
 
 /** Sets the specific connection for this proof request. */
 cxs_error_t cxs_proof_set_connection(cxs_proof_handle_t proof_handle, cxs_connection_handle_t connection_handle);
 
 /** Asynchronously send a proof request to the connection. */
-cxs_error_t cxs_proof_send_request(cxs_proof_handle_t proof_handle);
+cxs_error_t cxs_proof_send_request(cxs_command_handle_t command_handle, cxs_proof_handle_t proof_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err));
 
 /** Populate response_data with the latest proof offer received. */
-cxs_error_t cxs_proof_get_proof_offer(cxs_proof_handle_t proof_handle, char *proof_offer);
+cxs_error_t cxs_proof_get_proof_offer(cxs_command_handle_t command_handle, cxs_proof_handle_t proof_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *proof_offer));
+
+/** Verifies the proof offer, retrieving it from the agency when response_data is null. The proof must carry the nonce of the proof request. Populates proof_result with the revealed attributes and predicates of a valid proof. An invalid proof offer is discarded and the proof waits for another one. Only proofs whose request was sent can be validated, and a validated proof keeps its result. */
+cxs_error_t cxs_proof_validate_response(cxs_command_handle_t command_handle, cxs_proof_handle_t proof_handle, const char *response_data, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *proof_result));
 
-/** Set proof offer as accepted. */
+/** Set proof offer as accepted. Only a validated proof can be accepted. */
 cxs_error_t cxs_proof_accepted(cxs_proof_handle_t proof_handle);
 
-/** Populate status_array with the state of each proof handle. */
-cxs_error_t cxs_proof_list_state(cxs_status_t *status_array);
+/** Populates status_array with the state of each proof handle. */
+cxs_error_t cxs_proof_list_state(cxs_status_t *status_array, unsigned int *count);
+
+/** Allocates status_array with the state of each proof handle. */
+cxs_error_t cxs_proof_list_state_alloc(cxs_status_t **status_array, unsigned int *count);
 
 /** Populates status with the current state of this proof request. */
-cxs_error_t cxs_proof_get_state(cxs_proof_handle_t proof_handle, char *status);
+cxs_error_t cxs_proof_get_state(cxs_command_handle_t command_handle, cxs_proof_handle_t proof_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_claim_state_t state));
+
+/** Populates proof_state with the serialized proof object. */
+cxs_error_t cxs_proof_serialize(cxs_command_handle_t command_handle, cxs_proof_handle_t proof_handle, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *proof_state));
+
+/** Re-creates a proof object from the specified serialization. */
+cxs_error_t cxs_proof_deserialize(cxs_command_handle_t command_handle, const char *serialized_proof, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, cxs_proof_handle_t proof_handle));
+
+/** Releases the proof from memory. */
+cxs_error_t cxs_proof_release(cxs_proof_handle_t proof_handle);
+
+/**
+ * custom messages
+ *
+ * Lets the application send its own message types to the agency alongside the SDK's. Payloads of
+ * custom messages are validated, encrypted and serialized the same way as the built in messages.
+ */
+
+/** Registers msg_type for cxs_message_send. required_fields is a json array of the field names each payload must have, or null. Built in message types can't be registered. */
+cxs_error_t cxs_message_type_register(const char *msg_type, const char *required_fields);
+
+/** Asynchronously sends a message of a registered type to the agency for the connection. payload is a json object with the fields of the message. Populates response with the agency's json response. */
+cxs_error_t cxs_message_send(cxs_command_handle_t command_handle, cxs_connection_handle_t connection_handle, const char *msg_type, const char *payload, void (*cb)(cxs_command_handle_t command_handle, cxs_error_t err, const char *response));
 
 
 #ifdef __cplusplus
@@ -0,0 +1,205 @@
+extern crate libc;
+
+use self::libc::c_char;
+use utils::cstring::CStringUtils;
+use utils::error;
+use api::CxsStatus;
+use api::cxs::{fill_status_array, alloc_status_array};
+use claimdef;
+use std::thread;
+
+/**
+ * claimdef object
+ */
+
+#[no_mangle]
+pub extern fn cxs_claimdef_create(command_handle: u32,
+                                  source_id: *const c_char,
+                                  schema_handle: u32,
+                                  cb: Option<extern fn(xcommand_handle: u32, err: u32, claimdef_handle: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    let source_id_opt = if !source_id.is_null() {
+        check_useful_c_str!(source_id, error::INVALID_OPTION.code_num);
+        let val = source_id.to_owned();
+        Some(val)
+    } else { None };
+
+    thread::spawn(move|| {
+        let (rc, handle) = match claimdef::create(source_id_opt, schema_handle) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, handle);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_claimdef_commit(command_handle: u32,
+                                  claimdef_handle: u32,
+                                  cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    if !claimdef::is_valid_handle(claimdef_handle) {
+        return error::INVALID_CLAIM_DEF_HANDLE.code_num;
+    }
+
+    thread::spawn(move|| {
+        let rc = match claimdef::commit(claimdef_handle) {
+            Ok(_) => error::SUCCESS.code_num,
+            Err(x) => x,
+        };
+
+        cb(command_handle, rc);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_claimdef_get_sequence_no(command_handle: u32,
+                                           claimdef_handle: u32,
+                                           cb: Option<extern fn(xcommand_handle: u32, err: u32, sequence_no: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    if !claimdef::is_valid_handle(claimdef_handle) {
+        return error::INVALID_CLAIM_DEF_HANDLE.code_num;
+    }
+
+    thread::spawn(move|| {
+        let (rc, sequence_no) = match claimdef::get_sequence_no(claimdef_handle) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, sequence_no);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_claimdef_get(command_handle: u32,
+                               claimdef_handle: u32,
+                               cb: Option<extern fn(xcommand_handle: u32, err: u32, claimdef_data: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (data, rc) = match claimdef::get_data(claimdef_handle) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let data = CStringUtils::string_to_cstring(data);
+
+        cb(command_handle, rc, data.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_claimdef_release(claimdef_handle: u32) -> u32 { claimdef::release(claimdef_handle) }
+
+/// # Safety
+/// Same contract as fill_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_claimdef_list_state(status_array: *mut CxsStatus, count: *mut u32) -> u32 {
+    fill_status_array(claimdef::list_state(), status_array, count)
+}
+
+/// # Safety
+/// Same contract as alloc_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_claimdef_list_state_alloc(status_array: *mut *mut CxsStatus, count: *mut u32) -> u32 {
+    alloc_status_array(claimdef::list_state(), status_array, count)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use settings;
+    use schema;
+    use claimdef::tests::SCHEMA_DATA;
+    use std::ptr;
+    use std::time::Duration;
+
+    extern "C" fn create_cb(command_handle: u32, err: u32, claimdef_handle: u32) {
+        assert_eq!(err, 0);
+        assert!(claimdef_handle > 0);
+        println!("successfully called create_cb")
+    }
+
+    extern "C" fn create_fails_cb(command_handle: u32, err: u32, claimdef_handle: u32) {
+        assert_eq!(err, error::INVALID_SCHEMA_HANDLE.code_num);
+        assert_eq!(claimdef_handle, 0);
+    }
+
+    extern "C" fn commit_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, 0);
+    }
+
+    extern "C" fn sequence_no_cb(command_handle: u32, err: u32, sequence_no: u32) {
+        assert_eq!(err, 0);
+        assert!(sequence_no > 0);
+    }
+
+    extern "C" fn get_cb(command_handle: u32, err: u32, claimdef_data: *const c_char) {
+        assert_eq!(err, 0);
+        check_useful_c_str!(claimdef_data, ());
+        println!("successfully called get_cb: {}", claimdef_data);
+    }
+
+    extern "C" fn create_and_commit_cb(command_handle: u32, err: u32, claimdef_handle: u32) {
+        assert_eq!(err, 0);
+        assert_eq!(cxs_claimdef_commit(command_handle, claimdef_handle, Some(commit_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(cxs_claimdef_get_sequence_no(command_handle, claimdef_handle, Some(sequence_no_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_claimdef_get(command_handle, claimdef_handle, Some(get_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    fn create_committed_schema() -> u32 {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let schema_handle = schema::create(None, SCHEMA_DATA.to_owned()).unwrap();
+        schema::commit(schema_handle).unwrap();
+        schema_handle
+    }
+
+    #[test]
+    fn test_cxs_claimdef_create_success() {
+        let schema_handle = create_committed_schema();
+        assert_eq!(cxs_claimdef_create(0, ptr::null(), schema_handle, Some(create_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_claimdef_create_fails() {
+        assert_eq!(cxs_claimdef_create(0, ptr::null(), 0, None), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_claimdef_create(0, ptr::null(), 0, Some(create_fails_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_claimdef_commit() {
+        let schema_handle = create_committed_schema();
+        assert_eq!(cxs_claimdef_create(0, ptr::null(), schema_handle, Some(create_and_commit_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(800));
+    }
+
+    #[test]
+    fn test_cxs_claimdef_commit_fails() {
+        assert_eq!(cxs_claimdef_commit(0, 0, Some(commit_cb)), error::INVALID_CLAIM_DEF_HANDLE.code_num);
+        assert_eq!(cxs_claimdef_release(0), error::INVALID_CLAIM_DEF_HANDLE.code_num);
+    }
+}
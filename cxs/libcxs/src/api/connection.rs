@@ -1,10 +1,17 @@
 extern crate libc;
+extern crate serde_json;
 
 use self::libc::c_char;
 use utils::cstring::CStringUtils;
 use utils::error;
+use api::CxsStatus;
+use api::cxs::{fill_status_array, alloc_status_array};
 use std::ptr;
-use connection::{build_connection, connect, to_string, get_state, release};
+use std::thread;
+use messages::inbox;
+use connection::{build_connection, connect, reinvite, delete_connection, to_string, serialize, deserialize, get_state, list_state, release, update_message_status,
+                 accept_invitation, get_invite_detail, get_invite_link, get_invite_qr_code, get_handle_by_source_id,
+                 get_handle_by_pw_did, get_handle_by_their_did, get_handle_by_their_verkey};
 
 /**
  * connection object
@@ -36,6 +43,36 @@ pub extern fn cxs_connection_create(source_id: *const c_char,
     error::SUCCESS.code_num
 }
 
+/// Accepts an invitation from another enterprise.  invite_details is the json the inviter's
+/// connection reports as its invite detail, source_id may be null.
+#[no_mangle]
+pub extern fn cxs_connection_accept_invitation(command_handle: u32,
+                                               source_id: *const c_char,
+                                               invite_details: *const c_char,
+                                               cb: Option<extern fn(xcommand_handle: u32, err: u32, connection_handle: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(invite_details, error::INVALID_INVITE_DETAILS.code_num);
+
+    let source_id = if !source_id.is_null() {
+        check_useful_c_str!(source_id, error::UNKNOWN_ERROR.code_num);
+        Some(source_id)
+    } else {
+        None
+    };
+
+    thread::spawn(move|| {
+        let (handle, err) = match accept_invitation(source_id, &invite_details) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (0, x),
+        };
+
+        cb(command_handle, err, handle);
+    });
+
+    error::SUCCESS.code_num
+}
+
 #[no_mangle]
 pub extern fn cxs_connection_connect(connection_handle: u32, connection_options: *const c_char) -> u32 {
     let options = if !connection_options.is_null() {
@@ -49,6 +86,20 @@ pub extern fn cxs_connection_connect(connection_handle: u32, connection_options:
     connect(connection_handle, options)
 }
 
+/// Sends a fresh invitation for a connection whose invitation expired or is still unanswered.
+#[no_mangle]
+pub extern fn cxs_connection_reinvite(connection_handle: u32, connection_options: *const c_char) -> u32 {
+    let options = if !connection_options.is_null() {
+        check_useful_c_str!(connection_options, error::UNKNOWN_ERROR.code_num);
+        connection_options.to_owned()
+    }
+    else {
+        "{}".to_string()
+    };
+
+    reinvite(connection_handle, options)
+}
+
 #[no_mangle]
 pub extern fn cxs_connection_get_data(connection_handle: u32) -> *mut c_char {
     let json_string = to_string(connection_handle);
@@ -63,6 +114,50 @@ pub extern fn cxs_connection_get_data(connection_handle: u32) -> *mut c_char {
     }
 }
 
+/// Serializes the connection in a versioned format that cxs_connection_deserialize reads back.
+#[no_mangle]
+pub extern fn cxs_connection_serialize(connection_handle: u32, cb: Option<extern fn(xconnection_handle: u32, err: u32, connection_data: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (connection_data, err) = match serialize(connection_handle) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => {
+                warn!("could not serialize connection {}", connection_handle);
+                (String::new(), x)
+            },
+        };
+
+        let connection_data = CStringUtils::string_to_cstring(connection_data);
+
+        cb(connection_handle, err, connection_data.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+/// Rebuilds a serialized connection, populating a new handle for it.
+#[no_mangle]
+pub extern fn cxs_connection_deserialize(command_handle: u32,
+                                         connection_data: *const c_char,
+                                         cb: Option<extern fn(xcommand_handle: u32, err: u32, connection_handle: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(connection_data, error::INVALID_SERIALIZED_CONNECTION.code_num);
+
+    thread::spawn(move|| {
+        let (err, handle) = match deserialize(&connection_data) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, err, handle);
+    });
+
+    error::SUCCESS.code_num
+}
+
 #[no_mangle]
 pub extern fn cxs_connection_get_state(connection_handle: u32, status: *mut u32) -> u32 {
 
@@ -75,11 +170,232 @@ pub extern fn cxs_connection_get_state(connection_handle: u32, status: *mut u32)
     error::SUCCESS.code_num
 }
 
+fn populate_handle(found: Result<u32, u32>, connection_handle: *mut u32) -> u32 {
+    match found {
+        Ok(x) => {
+            unsafe { *connection_handle = x }
+            error::SUCCESS.code_num
+        },
+        Err(x) => x,
+    }
+}
+
+/// Looks up the newest connection created with source_id.
+#[no_mangle]
+pub extern fn cxs_connection_get_handle_by_source_id(source_id: *const c_char, connection_handle: *mut u32) -> u32 {
+    if connection_handle.is_null() {return error::UNKNOWN_ERROR.code_num}
+    check_useful_c_str!(source_id, error::CONNECTION_NOT_FOUND.code_num);
+
+    populate_handle(get_handle_by_source_id(&source_id), connection_handle)
+}
+
+/// Looks up the connection by its own pairwise did.
+#[no_mangle]
+pub extern fn cxs_connection_get_handle_by_did(did: *const c_char, connection_handle: *mut u32) -> u32 {
+    if connection_handle.is_null() {return error::UNKNOWN_ERROR.code_num}
+    check_useful_c_str!(did, error::CONNECTION_NOT_FOUND.code_num);
+
+    populate_handle(get_handle_by_pw_did(&did), connection_handle)
+}
+
+/// Looks up the newest connection to the party with the given did.
+#[no_mangle]
+pub extern fn cxs_connection_get_handle_by_their_did(their_did: *const c_char, connection_handle: *mut u32) -> u32 {
+    if connection_handle.is_null() {return error::UNKNOWN_ERROR.code_num}
+    check_useful_c_str!(their_did, error::CONNECTION_NOT_FOUND.code_num);
+
+    populate_handle(get_handle_by_their_did(&their_did), connection_handle)
+}
+
+/// Looks up the newest connection to the party with the given verkey.  Only connections made
+/// by accepting an invitation know the other party's verkey, connections that sent the
+/// invitation are not found.
+#[no_mangle]
+pub extern fn cxs_connection_get_handle_by_their_verkey(their_verkey: *const c_char, connection_handle: *mut u32) -> u32 {
+    if connection_handle.is_null() {return error::UNKNOWN_ERROR.code_num}
+    check_useful_c_str!(their_verkey, error::CONNECTION_NOT_FOUND.code_num);
+
+    populate_handle(get_handle_by_their_verkey(&their_verkey), connection_handle)
+}
+
 #[no_mangle]
 pub extern fn cxs_connection_release(connection_handle: u32) -> u32 {
     release(connection_handle)
 }
 
+/// Deletes the connection at the agent, retires its pairwise did and releases the handle.
+/// A nonzero notify_remote tells the other party about it first, when they can be reached.
+#[no_mangle]
+pub extern fn cxs_connection_delete_connection(command_handle: u32,
+                                               connection_handle: u32,
+                                               notify_remote: u32,
+                                               cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let err = match delete_connection(connection_handle, notify_remote != 0) {
+            Ok(x) | Err(x) => x,
+        };
+
+        cb(command_handle, err);
+    });
+
+    error::SUCCESS.code_num
+}
+
+/// # Safety
+/// Same contract as fill_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_connection_list_state(status_array: *mut CxsStatus, count: *mut u32) -> u32 {
+    fill_status_array(list_state(), status_array, count)
+}
+
+/// # Safety
+/// Same contract as alloc_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_connection_list_state_alloc(status_array: *mut *mut CxsStatus, count: *mut u32) -> u32 {
+    alloc_status_array(list_state(), status_array, count)
+}
+
+#[no_mangle]
+pub extern fn cxs_connection_get_invite_details(command_handle: u32,
+                                                connection_handle: u32,
+                                                cb: Option<extern fn(xcommand_handle: u32, err: u32, invite_details: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (invite_details, err) = match get_invite_detail(connection_handle) {
+            Ok(x) => (serde_json::to_string(&x).unwrap_or_default(), error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let invite_details = CStringUtils::string_to_cstring(invite_details);
+        cb(command_handle, err, invite_details.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_connection_get_invite_link(command_handle: u32,
+                                             connection_handle: u32,
+                                             cb: Option<extern fn(xcommand_handle: u32, err: u32, link: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (link, err) = match get_invite_link(connection_handle) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let link = CStringUtils::string_to_cstring(link);
+        cb(command_handle, err, link.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+/// image_format is "svg" or "png".  The image is only valid for the duration of the callback.
+#[no_mangle]
+pub extern fn cxs_connection_get_invite_qr_code(command_handle: u32,
+                                                connection_handle: u32,
+                                                image_format: *const c_char,
+                                                cb: Option<extern fn(xcommand_handle: u32, err: u32, image: *const u8, image_len: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(image_format, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (image, err) = match get_invite_qr_code(connection_handle, &image_format) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (Vec::new(), x),
+        };
+
+        cb(command_handle, err, image.as_ptr(), image.len() as u32);
+    });
+
+    error::SUCCESS.code_num
+}
+
+/// filter is a json object with any of msg_type, status_code, sender_did, offset and limit.
+#[no_mangle]
+pub extern fn cxs_connection_get_messages(command_handle: u32,
+                                          connection_handle: u32,
+                                          filter: *const c_char,
+                                          cb: Option<extern fn(xcommand_handle: u32, err: u32, messages: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(filter, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (messages, err) = match inbox::list(connection_handle, &filter) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let messages = CStringUtils::string_to_cstring(messages);
+        cb(command_handle, err, messages.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_connection_get_message(command_handle: u32,
+                                         connection_handle: u32,
+                                         uid: *const c_char,
+                                         cb: Option<extern fn(xcommand_handle: u32, err: u32, message: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(uid, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (message, err) = match inbox::get_message(connection_handle, &uid) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let message = CStringUtils::string_to_cstring(message);
+        cb(command_handle, err, message.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+
+/// uids is a json array of the uids of the messages, status_code the status they are given,
+/// e.g. MS-106 for reviewed or MS-105 for rejected.
+#[no_mangle]
+pub extern fn cxs_connection_update_message_status(command_handle: u32,
+                                                   connection_handle: u32,
+                                                   uids: *const c_char,
+                                                   status_code: *const c_char,
+                                                   cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(uids, error::INVALID_MSG_STATUS.code_num);
+    check_useful_c_str!(status_code, error::INVALID_MSG_STATUS.code_num);
+
+    let uids: Vec<String> = match serde_json::from_str(&uids) {
+        Ok(x) => x,
+        Err(_) => return error::INVALID_MSG_STATUS.code_num,
+    };
+
+    thread::spawn(move|| {
+        let err = match update_message_status(connection_handle, &uids, &status_code) {
+            Ok(x) => x,
+            Err(x) => x,
+        };
+
+        cb(command_handle, err);
+    });
+
+    error::SUCCESS.code_num
+}
+
 #[cfg(test)]
 mod tests {
     extern crate mockito;
@@ -90,9 +406,9 @@ mod tests {
     use std::ptr;
     use utils::error;
     use utils::wallet;
-    use std::thread;
     use std::time::Duration;
     use api::CxsStateType;
+    use messages::invite::KeyDlgProof;
 
     #[test]
     fn test_cxs_connection_create() {
@@ -158,6 +474,41 @@ mod tests {
         assert_eq!(rc, error::INVALID_CONNECTION_HANDLE.code_num);
     }
 
+    #[test]
+    fn test_cxs_connection_reinvite() {
+        let handle = ::connection::tests::build_test_connection();
+        assert_eq!(cxs_connection_reinvite(handle, ptr::null()), error::NOT_READY.code_num);
+        assert_eq!(cxs_connection_connect(handle, CString::new("{}").unwrap().into_raw()), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_reinvite(handle, ptr::null()), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_reinvite(0, ptr::null()), error::INVALID_CONNECTION_HANDLE.code_num);
+        cxs_connection_release(handle);
+    }
+
+    #[test]
+    fn test_cxs_connection_get_handle_by_source_id() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let mut handle: u32 = 0;
+        let rc = cxs_connection_create(CString::new("test_get_handle_by_source_id").unwrap().into_raw(),
+                                       ptr::null_mut(),
+                                       ptr::null(),
+                                       &mut handle);
+        assert_eq!(rc, error::SUCCESS.code_num);
+
+        let mut found: u32 = 0;
+        let rc = cxs_connection_get_handle_by_source_id(CString::new("test_get_handle_by_source_id").unwrap().as_ptr(), &mut found);
+        assert_eq!(rc, error::SUCCESS.code_num);
+        assert_eq!(found, handle);
+        let rc = cxs_connection_get_handle_by_source_id(CString::new("test_get_handle_by_source_id").unwrap().as_ptr(), ptr::null_mut());
+        assert_eq!(rc, error::UNKNOWN_ERROR.code_num);
+
+        cxs_connection_release(handle);
+        let rc = cxs_connection_get_handle_by_source_id(CString::new("test_get_handle_by_source_id").unwrap().as_ptr(), &mut found);
+        assert_eq!(rc, error::CONNECTION_NOT_FOUND.code_num);
+        let rc = cxs_connection_get_handle_by_their_did(ptr::null(), &mut found);
+        assert_eq!(rc, error::CONNECTION_NOT_FOUND.code_num);
+    }
+
     #[test]
     fn test_cxs_connection_get_state() {
         settings::set_defaults();
@@ -278,4 +629,130 @@ mod tests {
         let rc = cxs_connection_connect(handle, CString::new("{}").unwrap().into_raw());
         assert_eq!(rc, 0);
     }
+
+    extern "C" fn get_messages_cb(command_handle: u32, err: u32, messages: *const c_char) {
+        assert_eq!(err, 0);
+        check_useful_c_str!(messages, ());
+        assert_eq!(messages, "{\"msgs\":[],\"total\":0}");
+    }
+
+    extern "C" fn get_message_cb(command_handle: u32, err: u32, message: *const c_char) {
+        assert_eq!(err, error::UNKNOWN_MSG_UID.code_num);
+    }
+
+    #[test]
+    fn test_cxs_connection_get_messages() {
+        let handle = ::connection::tests::build_test_connection();
+        assert_eq!(cxs_connection_get_messages(0,
+                                               handle,
+                                               CString::new("{\"status_code\":\"MS-103\"}").unwrap().as_ptr(),
+                                               Some(get_messages_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_get_message(0,
+                                              handle,
+                                              CString::new("6gmsuWZ").unwrap().as_ptr(),
+                                              Some(get_message_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_get_messages(0, handle, ptr::null(), Some(get_messages_cb)),
+                   error::INVALID_OPTION.code_num);
+        thread::sleep(Duration::from_millis(200));
+        release(handle);
+    }
+
+    extern "C" fn update_message_status_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, 0);
+    }
+
+    #[test]
+    fn test_cxs_connection_update_message_status() {
+        let handle = ::connection::tests::build_test_connection();
+        assert_eq!(cxs_connection_update_message_status(0,
+                                                        handle,
+                                                        CString::new("[\"6gmsuWZ\"]").unwrap().as_ptr(),
+                                                        CString::new("MS-106").unwrap().as_ptr(),
+                                                        Some(update_message_status_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_update_message_status(0,
+                                                        handle,
+                                                        CString::new("6gmsuWZ").unwrap().as_ptr(),
+                                                        CString::new("MS-106").unwrap().as_ptr(),
+                                                        Some(update_message_status_cb)), error::INVALID_MSG_STATUS.code_num);
+        thread::sleep(Duration::from_millis(200));
+        release(handle);
+    }
+
+    extern "C" fn accept_invitation_cb(command_handle: u32, err: u32, connection_handle: u32) {
+        assert_eq!(err, 0);
+        assert_eq!(::connection::get_state(connection_handle), CxsStateType::CxsStateAccepted as u32);
+        release(connection_handle);
+    }
+
+    #[test]
+    fn test_cxs_connection_accept_invitation() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let proof = KeyDlgProof::create("JiLBHundRhwYaMbPWno8Vg", "KkTVEE7RGg7z2d2hrfM2Hj", "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A").unwrap();
+        let invite = json!({
+            "connReqId": "CXqcDCE",
+            "senderDID": "JiLBHundRhwYaMbPWno8Vg",
+            "senderDIDVerKey": "AevwvcQBLv5CERRJShzUncV7ubapSgbDZxus42zS8fk1",
+            "senderAgentKeyDlgProof": proof
+        }).to_string();
+        assert_eq!(cxs_connection_accept_invitation(0,
+                                                    ptr::null(),
+                                                    CString::new(invite).unwrap().as_ptr(),
+                                                    Some(accept_invitation_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn delete_connection_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, 0);
+    }
+
+    #[test]
+    fn test_cxs_connection_delete_connection() {
+        let handle = ::connection::tests::build_accepted_test_connection();
+        assert_eq!(cxs_connection_delete_connection(0, handle, 1, Some(delete_connection_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_delete_connection(0, handle, 1, None), error::INVALID_OPTION.code_num);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(release(handle), error::INVALID_CONNECTION_HANDLE.code_num);
+    }
+
+    extern "C" fn deserialize_cb(command_handle: u32, err: u32, connection_handle: u32) {
+        assert_eq!(err, 0);
+        assert!(connection_handle > 0);
+        release(connection_handle);
+    }
+
+    extern "C" fn serialize_cb(connection_handle: u32, err: u32, connection_data: *const c_char) {
+        assert_eq!(err, 0);
+        check_useful_c_str!(connection_data, ());
+        assert_eq!(cxs_connection_deserialize(0, CString::new(connection_data).unwrap().as_ptr(), Some(deserialize_cb)),
+                   error::SUCCESS.code_num);
+    }
+
+    #[test]
+    fn test_cxs_connection_serialize_deserialize() {
+        let handle = ::connection::tests::build_test_connection();
+        assert_eq!(cxs_connection_serialize(handle, Some(serialize_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_serialize(handle, None), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_connection_deserialize(0, ptr::null(), Some(deserialize_cb)), error::INVALID_SERIALIZED_CONNECTION.code_num);
+        thread::sleep(Duration::from_millis(200));
+        release(handle);
+    }
+
+    extern "C" fn invite_qr_code_cb(command_handle: u32, err: u32, image: *const u8, image_len: u32) {
+        assert_eq!(err, error::NOT_READY.code_num);
+        assert_eq!(image_len, 0);
+    }
+
+    #[test]
+    fn test_cxs_connection_get_invite_qr_code_before_connect() {
+        let handle = ::connection::tests::build_test_connection();
+        assert_eq!(cxs_connection_get_invite_qr_code(0,
+                                                     handle,
+                                                     CString::new("png").unwrap().as_ptr(),
+                                                     Some(invite_qr_code_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_connection_get_invite_qr_code(0, handle, ptr::null(), Some(invite_qr_code_cb)),
+                   error::INVALID_OPTION.code_num);
+        thread::sleep(Duration::from_millis(200));
+        release(handle);
+    }
 }
@@ -1,6 +1,9 @@
 extern crate libc;
 
 use self::libc::c_char;
+use std::ptr;
+use std::slice;
+use std::thread;
 use api::CxsStatus;
 use utils::cstring::CStringUtils;
 use utils::{pool, wallet};
@@ -22,6 +25,8 @@ use settings;
 /// agent_pairwise_verkey: verkey for the agent pairwise relationship with an enterprise
 /// enterprise_name: enterprise's name
 /// logo_url: url for enterprise's logo
+/// enable_encryption: "true" encrypts agent payloads to the agency and agent (defaults to "false")
+/// message_format: "json" (default) or "msgpack" for the messages posted to the agency
 /// A example file is at libcxs/sample_config/config.json
 #[no_mangle]
 pub extern fn cxs_init (config_path:*const c_char) -> u32 {
@@ -119,58 +124,107 @@ pub extern fn cxs_init (config_path:*const c_char) -> u32 {
         Ok(v) => v,
     };
 
+    // older agencies don't answer GET_VERSIONS, messages go out without a version until
+    // one does, so init doesn't wait for the agency
+    thread::spawn(|| {
+        match ::messages::version::negotiate() {
+            Ok(Some(x)) => info!("using agent message protocol version {}", x),
+            Ok(None) => info!("sending agent messages without a protocol version"),
+            Err(x) => warn!("could not negotiate a protocol version with the agency: {}", x),
+        };
+    });
+
     return error::SUCCESS.code_num
 }
 
 
-
-
 /**
- * Schema object
+ * bulk status
  */
 
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_schema_create(schema_data: *const c_char, schema_handle: *mut u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_schema_commit(schema_handle: u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables)]
-pub extern fn cxs_schema_get_data(schema_handle: u32, data: *mut c_char) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_schema_get_sequence_no(schema_handle: u32, sequence_no: *mut u32) -> u32 { error::SUCCESS.code_num }
+// Indexed by CxsStateType; these are static so callers never free the msg of a CxsStatus.
+static STATE_MESSAGES: [&'static [u8]; 8] = [b"None\0",
+                                             b"Initialized\0",
+                                             b"Offer Sent\0",
+                                             b"Request Received\0",
+                                             b"Accepted\0",
+                                             b"Unfulfilled\0",
+                                             b"Expired\0",
+                                             b"Revoked\0"];
+
+fn to_status_list(mut states: Vec<(u32, u32)>) -> Vec<CxsStatus> {
+    states.sort();
+    states.into_iter().map(|(handle, state)| {
+        let msg = STATE_MESSAGES.get(state as usize).unwrap_or(&STATE_MESSAGES[0]);
+        CxsStatus {
+            handle: handle as ::std::os::raw::c_int,
+            status: state as ::std::os::raw::c_int,
+            msg: msg.as_ptr() as *mut c_char,
+        }
+    }).collect()
+}
 
+/// Fills the caller's status_array, which has room for *count entries, and sets *count to
+/// the number of objects.  Returns INSUFFICIENT_STATUS_ARRAY when they didn't all fit.
+///
+/// # Safety
+/// count must be null or point to a valid u32, and status_array must be null or point to
+/// writable memory for at least *count CxsStatus entries.
+pub unsafe fn fill_status_array(states: Vec<(u32, u32)>, status_array: *mut CxsStatus, count: *mut u32) -> u32 {
+    if status_array.is_null() || count.is_null() { return error::INVALID_OPTION.code_num; }
+
+    let statuses = to_status_list(states);
+    let total = statuses.len();
+    let capacity = *count as usize;
+
+    for (i, status) in statuses.into_iter().take(capacity).enumerate() {
+        ptr::write(status_array.offset(i as isize), status);
+    }
+    *count = total as u32;
 
-/**
- * claimdef object
- */
+    if total > capacity {
+        warn!("status array has room for {} of {} objects", capacity, total);
+        return error::INSUFFICIENT_STATUS_ARRAY.code_num;
+    }
 
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_claimdef_create(schema_handle: u32, claimdef_handle: *mut u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_claimdef_commit(claimdef_handle: u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_claimdef_get_sequence_no(claimdef_handle: u32, sequence_no: *mut u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_claimdef_get(claimdef_handle: u32, data: *mut c_char) -> u32 { error::SUCCESS.code_num }
+    error::SUCCESS.code_num
+}
 
+/// Allocates an array with the status of every object.  It must be released with
+/// cxs_status_list_free, passing back the count set here.
+///
+/// # Safety
+/// status_array and count must each be null or point to memory the array pointer and its
+/// length can be written to.
+pub unsafe fn alloc_status_array(states: Vec<(u32, u32)>, status_array: *mut *mut CxsStatus, count: *mut u32) -> u32 {
+    if status_array.is_null() || count.is_null() { return error::INVALID_OPTION.code_num; }
+
+    let statuses = to_status_list(states);
+
+    *count = statuses.len() as u32;
+    *status_array = if statuses.is_empty() {
+        ptr::null_mut()
+    } else {
+        Box::into_raw(statuses.into_boxed_slice()) as *mut CxsStatus
+    };
 
-/**
- * proof object
- */
+    error::SUCCESS.code_num
+}
+
+/// Releases an array allocated by one of the list_state_alloc functions.
+///
+/// # Safety
+/// status_array must be null or an array returned by a list_state_alloc call that wasn't
+/// released yet, and count must be exactly the count that call returned: the array is
+/// freed as a slice of that length.
+#[no_mangle]
+pub unsafe extern fn cxs_status_list_free(status_array: *mut CxsStatus, count: u32) -> u32 {
+    if !status_array.is_null() {
+        drop(Box::from_raw(slice::from_raw_parts_mut(status_array, count as usize)));
+    }
 
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_proof_create(proof_request_data: *mut c_char, proof_handle: *mut u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables)]
-pub extern fn cxs_proof_set_connection(proof_handle: u32, connection_handle: u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_proof_send_request(proof_handle: u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_proof_get_proof_offer(proof_handle: u32, response_data: *mut c_char) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables)]
-pub extern fn cxs_proof_validate_response(proof_handle: u32, response_data: *const c_char) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_proof_list_state(status_array: *mut CxsStatus) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_proof_get_state(proof_handle: u32, status: *mut c_char) -> u32 { error::SUCCESS.code_num }
+    error::SUCCESS.code_num
+}
 
 
 
@@ -222,6 +276,42 @@ mod tests {
         assert_eq!(error::UNKNOWN_ERROR.code_num,cxs_init(empty_str));
     }
 
+    #[test]
+    fn test_fill_status_array() {
+        let mut statuses: Vec<CxsStatus> = (0..2).map(|_| CxsStatus { handle: 0, status: 0, msg: ptr::null_mut() }).collect();
+        let mut count = 2;
+        assert_eq!(unsafe { fill_status_array(vec![(22, 4), (11, 1)], statuses.as_mut_ptr(), &mut count) }, error::SUCCESS.code_num);
+        assert_eq!(count, 2);
+        assert_eq!((statuses[0].handle, statuses[0].status), (11, 1));
+        assert_eq!((statuses[1].handle, statuses[1].status), (22, 4));
+        let msg = statuses[1].msg as *const c_char;
+        check_useful_c_str!(msg, ());
+        assert_eq!(msg, "Accepted");
+
+        let mut count = 1;
+        assert_eq!(unsafe { fill_status_array(vec![(11, 1), (22, 4), (33, 7)], statuses.as_mut_ptr(), &mut count) }, error::INSUFFICIENT_STATUS_ARRAY.code_num);
+        assert_eq!(count, 3);
+        assert_eq!(unsafe { fill_status_array(vec![], ptr::null_mut(), &mut count) }, error::INVALID_OPTION.code_num);
+    }
+
+    #[test]
+    fn test_alloc_status_array() {
+        let mut statuses: *mut CxsStatus = ptr::null_mut();
+        let mut count = 0;
+        assert_eq!(unsafe { alloc_status_array(vec![(22, 7), (11, 1)], &mut statuses, &mut count) }, error::SUCCESS.code_num);
+        assert_eq!(count, 2);
+        unsafe {
+            assert_eq!((*statuses).handle, 11);
+            assert_eq!((*statuses.offset(1)).status, 7);
+        }
+        assert_eq!(unsafe { cxs_status_list_free(statuses, count) }, error::SUCCESS.code_num);
+
+        assert_eq!(unsafe { alloc_status_array(vec![], &mut statuses, &mut count) }, error::SUCCESS.code_num);
+        assert_eq!(count, 0);
+        assert!(statuses.is_null());
+        assert_eq!(unsafe { cxs_status_list_free(statuses, count) }, error::SUCCESS.code_num);
+    }
+
     #[test]
     fn test_init_no_config_path() {
         settings::set_defaults();
@@ -3,20 +3,25 @@ extern crate libc;
 use self::libc::c_char;
 use utils::cstring::CStringUtils;
 use utils::error;
-use issuer_claim::{issuer_claim_create, to_string, from_string, send_claim_offer, release};
+use api::CxsStatus;
+use api::cxs::{fill_status_array, alloc_status_array};
+use issuer_claim::{issuer_claim_create, to_string, from_string, send_claim_offer, get_claim_request, accept_claim, send_claim, terminate, validate_claim_data, list_state, release};
 use std::thread;
 
 /**
  * claim object
  */
 
+/// Creates a claim for claim_data.  On failure message says what is wrong, for bad claim
+/// data it lists every missing, unknown or malformed attribute like
+/// cxs_issuer_claim_validate_data.
 #[no_mangle]
 #[allow(unused_variables, unused_mut)]
 pub extern fn cxs_issuer_create_claim(command_handle: u32,
                                       source_id: *const c_char,
                                       claimdef_handle: u32,
                                       claim_data: *const c_char,
-                                      cb: Option<extern fn(xcommand_handle: u32, err: u32, claim_handle: u32)>) -> u32 {
+                                      cb: Option<extern fn(xcommand_handle: u32, err: u32, claim_handle: u32, message: *const c_char)>) -> u32 {
 
     check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
     check_useful_c_str!(claim_data, error::INVALID_OPTION.code_num);
@@ -28,12 +33,39 @@ pub extern fn cxs_issuer_create_claim(command_handle: u32,
     } else { None };
 
     thread::spawn(move|| {
-        let (rc, handle) = match issuer_claim_create(claimdef_handle, source_id_opt, claim_data) {
-            Ok(x) => (error::SUCCESS.code_num, x),
-            Err(_) => (error::UNKNOWN_ERROR.code_num, 0),
+        let (rc, handle, message) = match issuer_claim_create(claimdef_handle, source_id_opt, claim_data) {
+            Ok(x) => (error::SUCCESS.code_num, x, String::new()),
+            Err(x) => (x.code_num, 0, x.message),
         };
 
-        cb(command_handle, rc, handle);
+        let message = CStringUtils::string_to_cstring(message);
+
+        cb(command_handle, rc, handle, message.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+/// Checks claim_data against the schema of the claimdef.  On failure err is the code for the
+/// first kind of problem found and message lists every missing, unknown or malformed attribute.
+#[no_mangle]
+pub extern fn cxs_issuer_claim_validate_data(command_handle: u32,
+                                             claimdef_handle: u32,
+                                             claim_data: *const c_char,
+                                             cb: Option<extern fn(xcommand_handle: u32, err: u32, message: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(claim_data, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (err, message) = match validate_claim_data(claimdef_handle, &claim_data) {
+            Ok(_) => (error::SUCCESS.code_num, String::new()),
+            Err(x) => (x.code_num, x.message),
+        };
+
+        let message = CStringUtils::string_to_cstring(message);
+
+        cb(command_handle, err, message.as_ptr());
     });
 
     error::SUCCESS.code_num
@@ -60,14 +92,92 @@ pub extern fn cxs_issuer_send_claim_offer(command_handle: u32,
     error::SUCCESS.code_num
 }
 
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_issuer_get_claim_request(claim_handle: u32, claim_request: *mut c_char) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_issuer_accept_claim(claim_handle: u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables, unused_mut)]
-pub extern fn cxs_issuer_send_claim(claim_handle: u32, connection_handle: u32) -> u32 { error::SUCCESS.code_num }
-#[allow(unused_variables)]
-pub extern fn cxs_issuer_terminate_claim(claim_handle: u32, termination_type: u32, msg: *const c_char) -> u32 { error::SUCCESS.code_num }
+#[no_mangle]
+pub extern fn cxs_issuer_get_claim_request(command_handle: u32,
+                                           claim_handle: u32,
+                                           cb: Option<extern fn(xcommand_handle: u32, err: u32, claim_request: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (claim_request, err) = match get_claim_request(claim_handle) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let claim_request = CStringUtils::string_to_cstring(claim_request);
+
+        cb(command_handle, err, claim_request.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_issuer_accept_claim(command_handle: u32,
+                                      claim_handle: u32,
+                                      cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let err = match accept_claim(claim_handle) {
+            Ok(x) => x,
+            Err(x) => x,
+        };
+
+        cb(command_handle, err);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_issuer_send_claim(command_handle: u32,
+                                    claim_handle: u32,
+                                    connection_handle: u32,
+                                    cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let err = match send_claim(claim_handle, connection_handle) {
+            Ok(x) => x,
+            Err(x) => x,
+        };
+
+        cb(command_handle, err);
+    });
+
+    error::SUCCESS.code_num
+}
+/// Terminates the claim: issued claims are revoked (CxsStateRevoked), claims that weren't
+/// issued yet are left unfulfilled (CxsStateUnfulfilled) and the holder is sent msg.
+#[no_mangle]
+pub extern fn cxs_issuer_terminate_claim(command_handle: u32,
+                                         claim_handle: u32,
+                                         termination_type: u32,
+                                         msg: *const c_char,
+                                         cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    let reason = if !msg.is_null() {
+        check_useful_c_str!(msg, error::INVALID_OPTION.code_num);
+        msg.to_owned()
+    } else { String::new() };
+
+    thread::spawn(move|| {
+        let err = match terminate(claim_handle, termination_type, &reason) {
+            Ok(x) => x,
+            Err(x) => x,
+        };
+
+        cb(command_handle, err);
+    });
+
+    error::SUCCESS.code_num
+}
 
 #[no_mangle]
 #[allow(unused_variables, unused_mut)]
@@ -119,16 +229,34 @@ pub extern fn cxs_issuer_claim_deserialize(command_handle: u32,
 #[no_mangle]
 pub extern fn cxs_claim_issuer_release(claim_handle: u32) -> u32 { release(claim_handle) }
 
+/// # Safety
+/// Same contract as fill_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_issuer_claim_list_state(status_array: *mut CxsStatus, count: *mut u32) -> u32 {
+    fill_status_array(list_state(), status_array, count)
+}
+
+/// # Safety
+/// Same contract as alloc_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_issuer_claim_list_state_alloc(status_array: *mut *mut CxsStatus, count: *mut u32) -> u32 {
+    alloc_status_array(list_state(), status_array, count)
+}
+
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use claimdef::tests::create_test_claimdef;
+    use connection::tests::build_accepted_test_connection;
+    use issuer_claim::tests::CLAIM_DATA;
+    use api::CxsStateType;
     use std::ffi::CString;
     use std::ptr;
     use std::time::Duration;
 
-    extern "C" fn create_cb(command_handle: u32, err: u32, claim_handle: u32) {
+    extern "C" fn create_cb(command_handle: u32, err: u32, claim_handle: u32, message: *const c_char) {
         assert_eq!(err, 0);
         assert!(claim_handle > 0);
         println!("successfully called create_cb")
@@ -143,7 +271,7 @@ mod tests {
         println!("successfully called serialize_cb: {}", claim_string);
     }
 
-    extern "C" fn create_and_serialize_cb(command_handle: u32, err: u32, claim_handle: u32) {
+    extern "C" fn create_and_serialize_cb(command_handle: u32, err: u32, claim_handle: u32, message: *const c_char) {
         assert_eq!(err, 0);
         assert!(claim_handle > 0);
         println!("successfully called create_and_serialize_cb");
@@ -151,44 +279,114 @@ mod tests {
         thread::sleep(Duration::from_millis(200));
     }
 
+    extern "C" fn create_fails_cb(command_handle: u32, err: u32, claim_handle: u32, message: *const c_char) {
+        assert_eq!(err, error::INVALID_CLAIM_DEF_HANDLE.code_num);
+        assert_eq!(claim_handle, 0);
+    }
+
+    extern "C" fn create_bad_data_cb(command_handle: u32, err: u32, claim_handle: u32, message: *const c_char) {
+        assert_eq!(err, error::MISSING_CLAIM_ATTRIBUTES.code_num);
+        assert_eq!(claim_handle, 0);
+        check_useful_c_str!(message, ());
+        assert_eq!(message, "missing: age, height, sex");
+    }
+
     extern "C" fn send_offer_cb(command_handle: u32, err: u32) {
         if err != 0 {panic!("failed to send claim offer")}
     }
 
-    extern "C" fn create_and_send_offer_cb(command_handle: u32, err: u32, claim_handle: u32) {
-        if err != 0 {panic!("failed to create claim handle in create_and_send_offer_cb!")}
+    extern "C" fn send_offer_fails_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, error::INVALID_CONNECTION_HANDLE.code_num);
+    }
 
-        if cxs_issuer_send_claim_offer(command_handle, claim_handle, 32, Some(send_offer_cb)) != error::SUCCESS.code_num {
-            panic!("failed to send claim offer");
-        }
+    #[test]
+    fn test_cxs_issuer_create_claim_success() {
+        assert_eq!(cxs_issuer_create_claim(0, ptr::null(), create_test_claimdef(), CString::new(CLAIM_DATA).unwrap().into_raw(),Some(create_cb)), error::SUCCESS.code_num);
         thread::sleep(Duration::from_millis(200));
     }
 
+    extern "C" fn validate_data_fails_cb(command_handle: u32, err: u32, message: *const c_char) {
+        assert_eq!(err, error::MISSING_CLAIM_ATTRIBUTES.code_num);
+        check_useful_c_str!(message, ());
+        assert_eq!(message, "missing: age, height, sex");
+    }
+
     #[test]
-    fn test_cxs_issuer_create_claim_success() {
-        assert_eq!(cxs_issuer_create_claim(0, ptr::null(), 32, CString::new("{\"attr\":\"value\"}").unwrap().into_raw(),Some(create_cb)), error::SUCCESS.code_num);
+    fn test_cxs_issuer_claim_validate_data() {
+        let claimdef_handle = create_test_claimdef();
+        assert_eq!(cxs_issuer_claim_validate_data(0, claimdef_handle, ptr::null(), Some(validate_data_fails_cb)), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_issuer_claim_validate_data(0, claimdef_handle, CString::new("{\"name\":\"Alex\"}").unwrap().into_raw(), Some(validate_data_fails_cb)), error::SUCCESS.code_num);
         thread::sleep(Duration::from_millis(200));
     }
 
     #[test]
     fn test_cxs_issuer_create_claim_fails() {
         assert_eq!(cxs_issuer_create_claim(0, ptr::null(),32,ptr::null(),Some(create_cb)), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_issuer_create_claim(0, ptr::null(), 0, CString::new(CLAIM_DATA).unwrap().into_raw(),Some(create_fails_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_issuer_create_claim(0, ptr::null(), create_test_claimdef(), CString::new("{\"name\":\"Alex\"}").unwrap().into_raw(),Some(create_bad_data_cb)), error::SUCCESS.code_num);
         thread::sleep(Duration::from_millis(200));
     }
 
     #[test]
     fn test_cxs_issuer_claim_serialize() {
-        assert_eq!(cxs_issuer_create_claim(0, ptr::null(),32, CString::new("{\"attr\":\"value\"}").unwrap().into_raw(),Some(create_and_serialize_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_issuer_create_claim(0, ptr::null(), create_test_claimdef(), CString::new(CLAIM_DATA).unwrap().into_raw(),Some(create_and_serialize_cb)), error::SUCCESS.code_num);
         thread::sleep(Duration::from_millis(500));
     }
 
     #[test]
     fn test_cxs_issuer_send_claim_offer() {
-        assert_eq!(cxs_issuer_create_claim(0, ptr::null(),32, CString::new("{\"attr\":\"value\"}").unwrap().into_raw(),Some(create_and_send_offer_cb)), error::SUCCESS.code_num);
+        let connection_handle = build_accepted_test_connection();
+        let claim_handle = issuer_claim_create(create_test_claimdef(), None, CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(cxs_issuer_send_claim_offer(0, claim_handle, connection_handle, Some(send_offer_cb)), error::SUCCESS.code_num);
         thread::sleep(Duration::from_millis(200));
     }
 
-    extern "C" fn create_and_deserialize_cb(command_handle: u32, err: u32, claim_handle: u32) {
+    extern "C" fn get_claim_request_cb(command_handle: u32, err: u32, claim_request: *const c_char) {
+        assert_eq!(err, error::NOT_READY.code_num);
+    }
+
+    #[test]
+    fn test_cxs_issuer_get_claim_request() {
+        let claim_handle = issuer_claim_create(create_test_claimdef(), None, CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(cxs_issuer_get_claim_request(0, claim_handle, None), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_issuer_get_claim_request(0, claim_handle, Some(get_claim_request_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn send_claim_not_ready_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, error::NOT_READY.code_num);
+    }
+
+    #[test]
+    fn test_cxs_issuer_send_claim_before_claim_request() {
+        let connection_handle = build_accepted_test_connection();
+        let claim_handle = issuer_claim_create(create_test_claimdef(), None, CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(cxs_issuer_accept_claim(0, claim_handle, None), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_issuer_accept_claim(0, claim_handle, Some(send_claim_not_ready_cb)), error::SUCCESS.code_num);
+        assert_eq!(cxs_issuer_send_claim(0, claim_handle, connection_handle, Some(send_claim_not_ready_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn terminate_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, 0);
+    }
+
+    #[test]
+    fn test_cxs_issuer_terminate_claim() {
+        let claim_handle = issuer_claim_create(create_test_claimdef(), None, CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(cxs_issuer_terminate_claim(0, claim_handle, CxsStateType::CxsStateUnfulfilled as u32, ptr::null(), None), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_issuer_terminate_claim(0, claim_handle, CxsStateType::CxsStateUnfulfilled as u32, CString::new("cancelled").unwrap().into_raw(), Some(terminate_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_issuer_send_claim_offer_fails() {
+        let claim_handle = issuer_claim_create(create_test_claimdef(), None, CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(cxs_issuer_send_claim_offer(0, claim_handle, 0, Some(send_offer_fails_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn create_and_deserialize_cb(command_handle: u32, err: u32, claim_handle: u32, message: *const c_char) {
         assert_eq!(err, 0);
         assert!(claim_handle > 0);
         println!("successfully called create_and_deserialize_cb");
@@ -206,13 +404,17 @@ mod tests {
         println!("successfully called serialize_and_deserialize_cb: {}",string);
 
         release(handle);
-        cxs_issuer_claim_deserialize(0,claim_string, Some(create_and_serialize_cb));
+        cxs_issuer_claim_deserialize(0,claim_string, Some(deserialize_and_serialize_cb));
+    }
+
+    extern "C" fn deserialize_and_serialize_cb(command_handle: u32, err: u32, claim_handle: u32) {
+        create_and_serialize_cb(command_handle, err, claim_handle, ptr::null());
     }
 
     #[test]
     fn test_cxs_issuer_claim_deserialize_succeeds() {
-        //this is a nasty thread of callbacks -> create_and_deserialize_cb -> serialize_and_deserialize_cb -> create_and_serialize_cb -> serialize_cb
-        assert_eq!(cxs_issuer_create_claim(0, ptr::null(), 32, CString::new("{\"attr\":\"value\"}").unwrap().into_raw(),Some(create_and_deserialize_cb)), error::SUCCESS.code_num);
+        //this is a nasty thread of callbacks -> create_and_deserialize_cb -> serialize_and_deserialize_cb -> deserialize_and_serialize_cb -> serialize_cb
+        assert_eq!(cxs_issuer_create_claim(0, ptr::null(), create_test_claimdef(), CString::new(CLAIM_DATA).unwrap().into_raw(),Some(create_and_deserialize_cb)), error::SUCCESS.code_num);
         thread::sleep(Duration::from_millis(1000));
     }
 }
\ No newline at end of file
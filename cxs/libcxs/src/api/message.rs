@@ -0,0 +1,90 @@
+extern crate libc;
+extern crate serde_json;
+
+use self::libc::c_char;
+use utils::cstring::CStringUtils;
+use utils::error;
+use messages::custom::{register_msg_type, send};
+use std::thread;
+
+/**
+ * custom messages
+ */
+
+/// required_fields is a json array of the field names every payload of msg_type must have,
+/// null when the payload is free form.
+#[no_mangle]
+pub extern fn cxs_message_type_register(msg_type: *const c_char, required_fields: *const c_char) -> u32 {
+    check_useful_c_str!(msg_type, error::INVALID_MSG_TYPE.code_num);
+
+    let required_fields: Vec<String> = if !required_fields.is_null() {
+        check_useful_c_str!(required_fields, error::INVALID_OPTION.code_num);
+        match serde_json::from_str(&required_fields) {
+            Ok(x) => x,
+            Err(_) => return error::INVALID_OPTION.code_num,
+        }
+    } else {
+        Vec::new()
+    };
+
+    match register_msg_type(&msg_type, required_fields) {
+        Ok(x) => x,
+        Err(x) => x,
+    }
+}
+
+#[no_mangle]
+pub extern fn cxs_message_send(command_handle: u32,
+                               connection_handle: u32,
+                               msg_type: *const c_char,
+                               payload: *const c_char,
+                               cb: Option<extern fn(xcommand_handle: u32, err: u32, response: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(msg_type, error::INVALID_MSG_TYPE.code_num);
+    check_useful_c_str!(payload, error::INVALID_MSG_PAYLOAD.code_num);
+
+    thread::spawn(move|| {
+        let (response, err) = match send(connection_handle, &msg_type, &payload) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let response = CStringUtils::string_to_cstring(response);
+        cb(command_handle, err, response.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connection;
+    use connection::tests::build_test_connection;
+    use std::ffi::CString;
+    use std::ptr;
+    use std::time::Duration;
+
+    extern "C" fn send_cb(command_handle: u32, err: u32, response: *const c_char) {
+        assert_eq!(err, 0);
+        check_useful_c_str!(response, ());
+        assert_eq!(response, "{}");
+    }
+
+    #[test]
+    fn test_cxs_message_send() {
+        let handle = build_test_connection();
+        assert_eq!(cxs_message_type_register(CString::new("ORDER_SHIPPED").unwrap().as_ptr(),
+                                             CString::new("[\"order_id\"]").unwrap().as_ptr()), error::SUCCESS.code_num);
+        assert_eq!(cxs_message_type_register(CString::new("SEND_MSG").unwrap().as_ptr(), ptr::null()),
+                   error::INVALID_MSG_TYPE.code_num);
+        assert_eq!(cxs_message_send(0,
+                                    handle,
+                                    CString::new("ORDER_SHIPPED").unwrap().as_ptr(),
+                                    CString::new("{\"order_id\":\"42\"}").unwrap().as_ptr(),
+                                    Some(send_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+        connection::release(handle);
+    }
+}
@@ -3,6 +3,10 @@ extern crate libc;
 pub mod cxs;
 pub mod connection;
 pub mod issuer_claim;
+pub mod schema;
+pub mod claimdef;
+pub mod proof;
+pub mod message;
 
 use std::fmt;
 
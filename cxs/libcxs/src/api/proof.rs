@@ -0,0 +1,348 @@
+extern crate libc;
+
+use self::libc::c_char;
+use utils::cstring::CStringUtils;
+use utils::error;
+use api::CxsStatus;
+use api::cxs::{fill_status_array, alloc_status_array};
+use proof;
+use std::thread;
+
+/**
+ * proof object
+ */
+
+#[no_mangle]
+pub extern fn cxs_proof_create(command_handle: u32,
+                               source_id: *const c_char,
+                               proof_request_data: *const c_char,
+                               cb: Option<extern fn(xcommand_handle: u32, err: u32, proof_handle: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(proof_request_data, error::INVALID_OPTION.code_num);
+
+    let source_id_opt = if !source_id.is_null() {
+        check_useful_c_str!(source_id, error::INVALID_OPTION.code_num);
+        let val = source_id.to_owned();
+        Some(val)
+    } else { None };
+
+    thread::spawn(move|| {
+        let (rc, handle) = match proof::create(source_id_opt, proof_request_data) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, handle);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_proof_set_connection(proof_handle: u32, connection_handle: u32) -> u32 {
+    match proof::set_connection(proof_handle, connection_handle) {
+        Ok(x) => x,
+        Err(x) => x,
+    }
+}
+
+#[no_mangle]
+pub extern fn cxs_proof_send_request(command_handle: u32,
+                                     proof_handle: u32,
+                                     cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    if !proof::is_valid_handle(proof_handle) {
+        return error::INVALID_PROOF_HANDLE.code_num;
+    }
+
+    thread::spawn(move|| {
+        let rc = match proof::send_request(proof_handle) {
+            Ok(x) => x,
+            Err(x) => x,
+        };
+
+        cb(command_handle, rc);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_proof_get_proof_offer(command_handle: u32,
+                                        proof_handle: u32,
+                                        cb: Option<extern fn(xcommand_handle: u32, err: u32, proof_offer: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    if !proof::is_valid_handle(proof_handle) {
+        return error::INVALID_PROOF_HANDLE.code_num;
+    }
+
+    thread::spawn(move|| {
+        let (offer, rc) = match proof::get_proof_offer(proof_handle) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let offer = CStringUtils::string_to_cstring(offer);
+
+        cb(command_handle, rc, offer.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+/// Verifies the proof offer.  When response_data is null the latest proof offer is
+/// retrieved from the agency first.  A valid proof's revealed attributes and predicates
+/// are passed to the callback as json.  A proof without the nonce of the request is invalid.
+/// An invalid proof offer is discarded and the next call retrieves a new one.  Proofs whose
+/// request wasn't sent fail, validated proofs pass their result again without re-validating.
+#[no_mangle]
+pub extern fn cxs_proof_validate_response(command_handle: u32,
+                                          proof_handle: u32,
+                                          response_data: *const c_char,
+                                          cb: Option<extern fn(xcommand_handle: u32, err: u32, proof_result: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    let response_opt = if !response_data.is_null() {
+        check_useful_c_str!(response_data, error::INVALID_OPTION.code_num);
+        let val = response_data.to_owned();
+        Some(val)
+    } else { None };
+
+    if !proof::is_valid_handle(proof_handle) {
+        return error::INVALID_PROOF_HANDLE.code_num;
+    }
+
+    thread::spawn(move|| {
+        let (result, rc) = match proof::validate_response(proof_handle, response_opt) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let result = CStringUtils::string_to_cstring(result);
+
+        cb(command_handle, rc, result.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+/// Marks the proof offer accepted.  Fails with NOT_READY until the proof is validated.
+#[no_mangle]
+pub extern fn cxs_proof_accepted(proof_handle: u32) -> u32 {
+    match proof::accepted(proof_handle) {
+        Ok(x) => x,
+        Err(x) => x,
+    }
+}
+
+#[no_mangle]
+pub extern fn cxs_proof_get_state(command_handle: u32,
+                                  proof_handle: u32,
+                                  cb: Option<extern fn(xcommand_handle: u32, err: u32, state: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (rc, state) = match proof::get_state(proof_handle) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, state);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_proof_serialize(command_handle: u32,
+                                  proof_handle: u32,
+                                  cb: Option<extern fn(xcommand_handle: u32, err: u32, proof_state: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (proof_string, rc) = match proof::to_string(proof_handle) {
+            Ok(x) => {
+                info!("serializing proof handle: {} with data: {}", proof_handle, x);
+                (x, error::SUCCESS.code_num)
+            },
+            Err(x) => {
+                warn!("could not serialize proof handle {}", proof_handle);
+                (String::new(), x)
+            },
+        };
+
+        let proof_string = CStringUtils::string_to_cstring(proof_string);
+
+        cb(command_handle, rc, proof_string.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_proof_deserialize(command_handle: u32,
+                                    proof_data: *const c_char,
+                                    cb: Option<extern fn(xcommand_handle: u32, err: u32, proof_handle: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(proof_data, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (rc, handle) = match proof::from_string(&proof_data) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, handle);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_proof_release(proof_handle: u32) -> u32 { proof::release(proof_handle) }
+
+/// # Safety
+/// Same contract as fill_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_proof_list_state(status_array: *mut CxsStatus, count: *mut u32) -> u32 {
+    fill_status_array(proof::list_state(), status_array, count)
+}
+
+/// # Safety
+/// Same contract as alloc_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_proof_list_state_alloc(status_array: *mut *mut CxsStatus, count: *mut u32) -> u32 {
+    alloc_status_array(proof::list_state(), status_array, count)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use proof::tests::{PROOF_REQUEST, PROOF_OFFER};
+    use connection::tests::build_test_connection;
+    use api::CxsStateType;
+    use api::cxs::cxs_status_list_free;
+    use settings;
+    use std::ffi::CString;
+    use std::ptr;
+    use std::time::Duration;
+
+    extern "C" fn create_cb(command_handle: u32, err: u32, proof_handle: u32) {
+        assert_eq!(err, 0);
+        assert!(proof_handle > 0);
+        println!("successfully called create_cb")
+    }
+
+    extern "C" fn create_fails_cb(command_handle: u32, err: u32, proof_handle: u32) {
+        assert_eq!(err, error::INVALID_PROOF_REQUEST.code_num);
+        assert_eq!(proof_handle, 0);
+    }
+
+    extern "C" fn send_request_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, 0);
+    }
+
+    extern "C" fn validate_cb(command_handle: u32, err: u32, proof_result: *const c_char) {
+        assert_eq!(err, 0);
+        check_useful_c_str!(proof_result, ());
+        assert!(proof_result.contains("revealed_attrs"));
+    }
+
+    extern "C" fn accepted_state_cb(command_handle: u32, err: u32, state: u32) {
+        assert_eq!(err, 0);
+        assert_eq!(state, CxsStateType::CxsStateAccepted as u32);
+    }
+
+    extern "C" fn create_and_validate_cb(command_handle: u32, err: u32, proof_handle: u32) {
+        assert_eq!(err, 0);
+        ::proof::tests::set_request_sent(proof_handle);
+        assert_eq!(cxs_proof_validate_response(command_handle, proof_handle, CString::new(PROOF_OFFER).unwrap().into_raw(), Some(validate_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(cxs_proof_accepted(proof_handle), error::SUCCESS.code_num);
+        assert_eq!(cxs_proof_get_state(command_handle, proof_handle, Some(accepted_state_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn serialize_and_deserialize_cb(command_handle: u32, err: u32, proof_string: *const c_char) {
+        assert_eq!(err, 0);
+        let string = proof_string;
+        check_useful_c_str!(string, ());
+        println!("successfully called serialize_and_deserialize_cb: {}", string);
+        cxs_proof_deserialize(command_handle, proof_string, Some(create_cb));
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn create_and_serialize_cb(command_handle: u32, err: u32, proof_handle: u32) {
+        assert_eq!(err, 0);
+        assert_eq!(cxs_proof_serialize(command_handle, proof_handle, Some(serialize_and_deserialize_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_proof_create_success() {
+        assert_eq!(cxs_proof_create(0, ptr::null(), CString::new(PROOF_REQUEST).unwrap().into_raw(), Some(create_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_proof_create_fails() {
+        assert_eq!(cxs_proof_create(0, ptr::null(), ptr::null(), Some(create_cb)), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_proof_create(0, ptr::null(), CString::new("{}").unwrap().into_raw(), Some(create_fails_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_proof_send_request() {
+        let connection_handle = build_test_connection();
+        let handle = proof::create(None, PROOF_REQUEST.to_owned()).unwrap();
+        assert_eq!(cxs_proof_set_connection(handle, 0), error::INVALID_CONNECTION_HANDLE.code_num);
+        assert_eq!(cxs_proof_set_connection(handle, connection_handle), error::SUCCESS.code_num);
+        assert_eq!(cxs_proof_send_request(0, handle, Some(send_request_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(cxs_proof_send_request(0, 0, Some(send_request_cb)), error::INVALID_PROOF_HANDLE.code_num);
+    }
+
+    #[test]
+    fn test_cxs_proof_validate_response() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        assert_eq!(cxs_proof_create(0, ptr::null(), CString::new(PROOF_REQUEST).unwrap().into_raw(), Some(create_and_validate_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(800));
+    }
+
+    #[test]
+    fn test_cxs_proof_list_state() {
+        let handle = proof::create(None, PROOF_REQUEST.to_owned()).unwrap();
+        let mut statuses: *mut CxsStatus = ptr::null_mut();
+        let mut count = 0;
+        assert_eq!(unsafe { cxs_proof_list_state_alloc(&mut statuses, &mut count) }, error::SUCCESS.code_num);
+        assert!(count > 0);
+        let found = unsafe { ::std::slice::from_raw_parts(statuses, count as usize) }.iter()
+            .any(|x| x.handle as u32 == handle && x.status == CxsStateType::CxsStateInitialized as i32);
+        assert!(found);
+        assert_eq!(unsafe { cxs_status_list_free(statuses, count) }, error::SUCCESS.code_num);
+
+        let mut count = 0;
+        let mut empty: Vec<CxsStatus> = Vec::new();
+        assert_eq!(unsafe { cxs_proof_list_state(empty.as_mut_ptr(), &mut count) }, error::INSUFFICIENT_STATUS_ARRAY.code_num);
+        assert!(count > 0);
+        proof::release(handle);
+    }
+
+    #[test]
+    fn test_cxs_proof_serialize_and_deserialize() {
+        assert_eq!(cxs_proof_create(0, ptr::null(), CString::new(PROOF_REQUEST).unwrap().into_raw(), Some(create_and_serialize_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(800));
+    }
+}
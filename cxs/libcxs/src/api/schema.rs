@@ -0,0 +1,269 @@
+extern crate libc;
+
+use self::libc::c_char;
+use utils::cstring::CStringUtils;
+use utils::error;
+use api::CxsStatus;
+use api::cxs::{fill_status_array, alloc_status_array};
+use schema;
+use std::thread;
+
+/**
+ * schema object
+ */
+
+#[no_mangle]
+pub extern fn cxs_schema_create(command_handle: u32,
+                                source_id: *const c_char,
+                                schema_data: *const c_char,
+                                cb: Option<extern fn(xcommand_handle: u32, err: u32, schema_handle: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(schema_data, error::INVALID_OPTION.code_num);
+
+    let source_id_opt = if !source_id.is_null() {
+        check_useful_c_str!(source_id, error::INVALID_OPTION.code_num);
+        let val = source_id.to_owned();
+        Some(val)
+    } else { None };
+
+    thread::spawn(move|| {
+        let (rc, handle) = match schema::create(source_id_opt, schema_data) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, handle);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_schema_commit(command_handle: u32,
+                                schema_handle: u32,
+                                cb: Option<extern fn(xcommand_handle: u32, err: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    if !schema::is_valid_handle(schema_handle) {
+        return error::INVALID_SCHEMA_HANDLE.code_num;
+    }
+
+    thread::spawn(move|| {
+        let rc = match schema::commit(schema_handle) {
+            Ok(_) => error::SUCCESS.code_num,
+            Err(x) => x,
+        };
+
+        cb(command_handle, rc);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_schema_get_sequence_no(command_handle: u32,
+                                         schema_handle: u32,
+                                         cb: Option<extern fn(xcommand_handle: u32, err: u32, sequence_no: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    if !schema::is_valid_handle(schema_handle) {
+        return error::INVALID_SCHEMA_HANDLE.code_num;
+    }
+
+    thread::spawn(move|| {
+        let (rc, sequence_no) = match schema::get_sequence_no(schema_handle) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, sequence_no);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_schema_get_data(command_handle: u32,
+                                  schema_handle: u32,
+                                  cb: Option<extern fn(xcommand_handle: u32, err: u32, schema_data: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (data, rc) = match schema::get_data(schema_handle) {
+            Ok(x) => (x, error::SUCCESS.code_num),
+            Err(x) => (String::new(), x),
+        };
+
+        let data = CStringUtils::string_to_cstring(data);
+
+        cb(command_handle, rc, data.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_schema_serialize(command_handle: u32,
+                                   schema_handle: u32,
+                                   cb: Option<extern fn(xcommand_handle: u32, err: u32, schema_state: *const c_char)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (schema_string, rc) = match schema::to_string(schema_handle) {
+            Ok(x) => {
+                info!("serializing schema handle: {} with data: {}", schema_handle, x);
+                (x, error::SUCCESS.code_num)
+            },
+            Err(x) => {
+                warn!("could not serialize schema handle {}", schema_handle);
+                (String::new(), x)
+            },
+        };
+
+        let schema_string = CStringUtils::string_to_cstring(schema_string);
+
+        cb(command_handle, rc, schema_string.as_ptr());
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_schema_deserialize(command_handle: u32,
+                                     schema_data: *const c_char,
+                                     cb: Option<extern fn(xcommand_handle: u32, err: u32, schema_handle: u32)>) -> u32 {
+
+    check_useful_c_callback!(cb, error::INVALID_OPTION.code_num);
+    check_useful_c_str!(schema_data, error::INVALID_OPTION.code_num);
+
+    thread::spawn(move|| {
+        let (rc, handle) = match schema::from_string(&schema_data) {
+            Ok(x) => (error::SUCCESS.code_num, x),
+            Err(x) => (x, 0),
+        };
+
+        cb(command_handle, rc, handle);
+    });
+
+    error::SUCCESS.code_num
+}
+
+#[no_mangle]
+pub extern fn cxs_schema_release(schema_handle: u32) -> u32 { schema::release(schema_handle) }
+
+/// # Safety
+/// Same contract as fill_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_schema_list_state(status_array: *mut CxsStatus, count: *mut u32) -> u32 {
+    fill_status_array(schema::list_state(), status_array, count)
+}
+
+/// # Safety
+/// Same contract as alloc_status_array.
+#[no_mangle]
+pub unsafe extern fn cxs_schema_list_state_alloc(status_array: *mut *mut CxsStatus, count: *mut u32) -> u32 {
+    alloc_status_array(schema::list_state(), status_array, count)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use settings;
+    use std::ffi::CString;
+    use std::ptr;
+    use std::time::Duration;
+
+    static SCHEMA_DATA: &'static str = "{\"name\":\"test-schema\",\"version\":\"1.0\",\"attr_names\":[\"name\",\"age\"]}";
+
+    extern "C" fn create_cb(command_handle: u32, err: u32, schema_handle: u32) {
+        assert_eq!(err, 0);
+        assert!(schema_handle > 0);
+        println!("successfully called create_cb")
+    }
+
+    extern "C" fn create_fails_cb(command_handle: u32, err: u32, schema_handle: u32) {
+        assert_eq!(err, error::INVALID_SCHEMA.code_num);
+        assert_eq!(schema_handle, 0);
+    }
+
+    extern "C" fn commit_cb(command_handle: u32, err: u32) {
+        assert_eq!(err, 0);
+        println!("successfully called commit_cb")
+    }
+
+    extern "C" fn create_and_commit_cb(command_handle: u32, err: u32, schema_handle: u32) {
+        assert_eq!(err, 0);
+        assert_eq!(cxs_schema_commit(command_handle, schema_handle, Some(commit_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(cxs_schema_get_sequence_no(command_handle, schema_handle, Some(sequence_no_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn sequence_no_cb(command_handle: u32, err: u32, sequence_no: u32) {
+        assert_eq!(err, 0);
+        assert!(sequence_no > 0);
+    }
+
+    extern "C" fn serialize_cb(command_handle: u32, err: u32, schema_string: *const c_char) {
+        assert_eq!(err, 0);
+        if schema_string.is_null() {
+            panic!("schema_string is null");
+        }
+        check_useful_c_str!(schema_string, ());
+        println!("successfully called serialize_cb: {}", schema_string);
+    }
+
+    extern "C" fn create_and_serialize_cb(command_handle: u32, err: u32, schema_handle: u32) {
+        assert_eq!(err, 0);
+        assert_eq!(cxs_schema_serialize(command_handle, schema_handle, Some(serialize_and_deserialize_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    extern "C" fn serialize_and_deserialize_cb(command_handle: u32, err: u32, schema_string: *const c_char) {
+        assert_eq!(err, 0);
+        let string = schema_string;
+        check_useful_c_str!(string, ());
+        println!("successfully called serialize_and_deserialize_cb: {}", string);
+        cxs_schema_deserialize(command_handle, schema_string, Some(create_cb));
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_schema_create_success() {
+        assert_eq!(cxs_schema_create(0, ptr::null(), CString::new(SCHEMA_DATA).unwrap().into_raw(), Some(create_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_schema_create_fails() {
+        assert_eq!(cxs_schema_create(0, ptr::null(), ptr::null(), Some(create_cb)), error::INVALID_OPTION.code_num);
+        assert_eq!(cxs_schema_create(0, ptr::null(), CString::new("{}").unwrap().into_raw(), Some(create_fails_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_cxs_schema_commit() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        assert_eq!(cxs_schema_create(0, ptr::null(), CString::new(SCHEMA_DATA).unwrap().into_raw(), Some(create_and_commit_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(800));
+    }
+
+    #[test]
+    fn test_cxs_schema_commit_fails() {
+        assert_eq!(cxs_schema_commit(0, 0, Some(commit_cb)), error::INVALID_SCHEMA_HANDLE.code_num);
+    }
+
+    #[test]
+    fn test_cxs_schema_serialize_and_deserialize() {
+        assert_eq!(cxs_schema_create(0, ptr::null(), CString::new(SCHEMA_DATA).unwrap().into_raw(), Some(create_and_serialize_cb)), error::SUCCESS.code_num);
+        thread::sleep(Duration::from_millis(800));
+    }
+}
@@ -0,0 +1,244 @@
+extern crate rand;
+extern crate serde_json;
+
+use std::sync::Mutex;
+use std::collections::HashMap;
+use rand::Rng;
+use api::CxsStateType;
+use settings;
+use schema;
+use schema::SchemaData;
+use utils::error;
+use utils::ledger;
+use utils::anoncreds;
+
+pub static MAX_CLAIM_NUM: u32 = 100;
+
+lazy_static! {
+    static ref CLAIMDEF_MAP: Mutex<HashMap<u32, Box<ClaimDef>>> = Default::default();
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClaimDef {
+    source_id: String,
+    handle: u32,
+    schema_seq_no: u32,
+    schema_data: SchemaData,
+    issuer_did: String,
+    claim_def: String,
+    sequence_num: u32,
+    state: CxsStateType,
+    revoc_reg_uuid: String,
+    revoc_index: i32,
+}
+
+impl ClaimDef {
+    fn commit(&mut self) -> Result<u32, u32> {
+        if self.sequence_num > 0 {
+            info!("claimdef {} already committed with sequence number {}", self.handle, self.sequence_num);
+            return Ok(self.sequence_num);
+        }
+
+        let claim_def: serde_json::Value = match serde_json::from_str(&self.claim_def) {
+            Ok(x) => x,
+            Err(_) => return Err(error::UNKNOWN_ERROR.code_num),
+        };
+
+        let request = ledger::build_claim_def_txn(&self.issuer_did,
+                                                  self.schema_seq_no,
+                                                  &claim_def["data"].to_string())?;
+        let response = ledger::sign_and_submit_request(&self.issuer_did, &request)?;
+
+        self.sequence_num = ledger::get_seq_no(&response)?;
+        self.state = CxsStateType::CxsStateAccepted;
+        info!("committed claimdef {} with sequence number {}", self.handle, self.sequence_num);
+        Ok(self.sequence_num)
+    }
+
+    /// Reserves the next index in the claimdef's revocation registry.
+    fn next_revoc_index(&mut self) -> Result<i32, u32> {
+        if self.revoc_index >= MAX_CLAIM_NUM as i32 {
+            warn!("revocation registry {} for claimdef {} is full", self.revoc_reg_uuid, self.handle);
+            return Err(error::REVOCATION_REGISTRY_FULL.code_num);
+        }
+
+        self.revoc_index += 1;
+        Ok(self.revoc_index)
+    }
+}
+
+pub fn create(source_id: Option<String>, schema_handle: u32) -> Result<u32, u32> {
+    if !schema::is_valid_handle(schema_handle) {
+        return Err(error::INVALID_SCHEMA_HANDLE.code_num);
+    }
+
+    let schema_seq_no = schema::get_sequence_no(schema_handle)?;
+    let schema_data: SchemaData = serde_json::from_str(&schema::get_data(schema_handle)?).unwrap();
+    let issuer_did = settings::get_config_value(settings::CONFIG_ENTERPRISE_DID)?;
+
+    let schema_json = json!({
+        "seqNo": schema_seq_no,
+        "data": {
+            "name": schema_data.name,
+            "version": schema_data.version,
+            "keys": schema_data.attr_names
+        }
+    }).to_string();
+
+    let claim_def = anoncreds::issuer_create_and_store_claim_def(&issuer_did, &schema_json, true)?;
+    let (_, revoc_reg_uuid) = anoncreds::issuer_create_and_store_revoc_reg(&issuer_did, schema_seq_no, MAX_CLAIM_NUM)?;
+
+    let new_handle = rand::thread_rng().gen::<u32>();
+
+    let new_claimdef = Box::new(ClaimDef {
+        source_id: source_id.unwrap_or("".to_string()),
+        handle: new_handle,
+        schema_seq_no: schema_seq_no,
+        schema_data: schema_data,
+        issuer_did: issuer_did,
+        claim_def: claim_def,
+        sequence_num: 0,
+        state: CxsStateType::CxsStateInitialized,
+        revoc_reg_uuid: revoc_reg_uuid,
+        revoc_index: 0,
+    });
+
+    {
+        let mut m = CLAIMDEF_MAP.lock().unwrap();
+        info!("inserting handle {} into claimdef table", new_handle);
+        m.insert(new_handle, new_claimdef);
+    }
+
+    Ok(new_handle)
+}
+
+pub fn is_valid_handle(handle: u32) -> bool {
+    CLAIMDEF_MAP.lock().unwrap().contains_key(&handle)
+}
+
+pub fn commit(handle: u32) -> Result<u32, u32> {
+    let mut m = CLAIMDEF_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(c) => c.commit(),
+        None => Err(error::INVALID_CLAIM_DEF_HANDLE.code_num),
+    }
+}
+
+pub fn get_sequence_no(handle: u32) -> Result<u32, u32> {
+    let m = CLAIMDEF_MAP.lock().unwrap();
+    match m.get(&handle) {
+        Some(c) if c.sequence_num > 0 => Ok(c.sequence_num),
+        Some(_) => {
+            warn!("claimdef {} has not been committed to the ledger", handle);
+            Err(error::NOT_READY.code_num)
+        },
+        None => Err(error::INVALID_CLAIM_DEF_HANDLE.code_num),
+    }
+}
+
+pub fn get_schema_seq_no(handle: u32) -> Result<u32, u32> {
+    match CLAIMDEF_MAP.lock().unwrap().get(&handle) {
+        Some(c) => Ok(c.schema_seq_no),
+        None => Err(error::INVALID_CLAIM_DEF_HANDLE.code_num),
+    }
+}
+
+pub fn get_issuer_did(handle: u32) -> Result<String, u32> {
+    match CLAIMDEF_MAP.lock().unwrap().get(&handle) {
+        Some(c) => Ok(c.issuer_did.clone()),
+        None => Err(error::INVALID_CLAIM_DEF_HANDLE.code_num),
+    }
+}
+
+pub fn get_attr_names(handle: u32) -> Result<Vec<String>, u32> {
+    match CLAIMDEF_MAP.lock().unwrap().get(&handle) {
+        Some(c) => Ok(c.schema_data.attr_names.clone()),
+        None => Err(error::INVALID_CLAIM_DEF_HANDLE.code_num),
+    }
+}
+
+pub fn get_data(handle: u32) -> Result<String, u32> {
+    match CLAIMDEF_MAP.lock().unwrap().get(&handle) {
+        Some(c) => Ok(c.claim_def.clone()),
+        None => Err(error::INVALID_CLAIM_DEF_HANDLE.code_num),
+    }
+}
+
+pub fn next_revoc_index(handle: u32) -> Result<i32, u32> {
+    match CLAIMDEF_MAP.lock().unwrap().get_mut(&handle) {
+        Some(c) => c.next_revoc_index(),
+        None => Err(error::INVALID_CLAIM_DEF_HANDLE.code_num),
+    }
+}
+
+/// Returns the handle and current state of every claimdef object.
+pub fn list_state() -> Vec<(u32, u32)> {
+    CLAIMDEF_MAP.lock().unwrap().iter().map(|(handle, x)| (*handle, x.state as u32)).collect()
+}
+
+pub fn release(handle: u32) -> u32 {
+    match CLAIMDEF_MAP.lock().unwrap().remove(&handle) {
+        Some(_) => error::SUCCESS.code_num,
+        None => error::INVALID_CLAIM_DEF_HANDLE.code_num,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub static SCHEMA_DATA: &'static str = "{\"name\":\"test-schema\",\"version\":\"1.0\",\"attr_names\":[\"name\",\"age\",\"height\",\"sex\"]}";
+
+    pub fn create_test_claimdef() -> u32 {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let schema_handle = schema::create(None, SCHEMA_DATA.to_owned()).unwrap();
+        schema::commit(schema_handle).unwrap();
+        create(Some("test_claimdef".to_owned()), schema_handle).unwrap()
+    }
+
+    #[test]
+    fn test_create_claimdef_succeeds() {
+        let handle = create_test_claimdef();
+        assert!(handle > 0);
+        assert!(is_valid_handle(handle));
+        assert_eq!(get_schema_seq_no(handle).unwrap(), ledger::TEST_MODE_SEQ_NO);
+        assert_eq!(get_attr_names(handle).unwrap(), vec!["name", "age", "height", "sex"]);
+        assert_eq!(get_data(handle).unwrap(), anoncreds::CLAIM_DEF_TEST_MODE);
+        release(handle);
+    }
+
+    #[test]
+    fn test_create_claimdef_with_invalid_schema_fails() {
+        assert_eq!(create(None, 0), Err(error::INVALID_SCHEMA_HANDLE.code_num));
+    }
+
+    #[test]
+    fn test_commit_claimdef() {
+        let handle = create_test_claimdef();
+        assert_eq!(get_sequence_no(handle), Err(error::NOT_READY.code_num));
+        assert_eq!(commit(handle).unwrap(), ledger::TEST_MODE_SEQ_NO);
+        assert_eq!(get_sequence_no(handle).unwrap(), ledger::TEST_MODE_SEQ_NO);
+        release(handle);
+    }
+
+    #[test]
+    fn test_next_revoc_index() {
+        let handle = create_test_claimdef();
+        assert_eq!(CLAIMDEF_MAP.lock().unwrap().get(&handle).unwrap().revoc_reg_uuid, anoncreds::REVOC_REG_UUID_TEST_MODE);
+        assert_eq!(next_revoc_index(handle).unwrap(), 1);
+        assert_eq!(next_revoc_index(handle).unwrap(), 2);
+        CLAIMDEF_MAP.lock().unwrap().get_mut(&handle).unwrap().revoc_index = MAX_CLAIM_NUM as i32;
+        assert_eq!(next_revoc_index(handle), Err(error::REVOCATION_REGISTRY_FULL.code_num));
+        assert_eq!(next_revoc_index(0), Err(error::INVALID_CLAIM_DEF_HANDLE.code_num));
+        release(handle);
+    }
+
+    #[test]
+    fn test_invalid_handle_fails() {
+        assert!(!is_valid_handle(0));
+        assert_eq!(commit(0), Err(error::INVALID_CLAIM_DEF_HANDLE.code_num));
+        assert_eq!(get_data(0), Err(error::INVALID_CLAIM_DEF_HANDLE.code_num));
+        assert_eq!(release(0), error::INVALID_CLAIM_DEF_HANDLE.code_num);
+    }
+}
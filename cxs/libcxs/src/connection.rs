@@ -4,17 +4,78 @@ extern crate libc;
 
 use utils::wallet;
 use utils::error;
-use utils::httpclient;
+use utils::crypto;
+use utils::qr;
+use utils;
 use api::CxsStateType;
 use rand::Rng;
+use url::Url;
 use std::sync::Mutex;
 use std::collections::HashMap;
 use settings;
-use messages::GeneralMessage;
+use messages::{GeneralMessage, Recipient};
+use messages::invite::{CreateKeyMsg, SendInvite, UpdateProfileData, AcceptInvitation, InviteDetail, KeyDlgProof};
+use messages::validation;
+use messages::message::{GetMessages, MSG_STATUS_ACCEPTED, MSG_STATUS_REVIEWED, MSG_STATUS_EXPIRED};
+use messages::update_message::UpdateMessageStatus;
+use messages::update_connection::{UpdateConnectionStatus, CONN_STATUS_DELETED};
+use messages::send_message::SendMessage;
+use messages::format::MessageFormat;
 use messages;
 
+pub static CONN_REQ_MSG_TYPE: &'static str = "connReq";
+/// Type of the message telling the other party a connection was deleted, its payload is a
+/// ConnectionDeleted.
+pub static CONN_DELETED_MSG_TYPE: &'static str = "connDeleted";
+/// Version of the format serialize writes, which deserialize reads.
+pub static SERIALIZE_VERSION: &'static str = "1.0";
+
 lazy_static! {
     static ref CONNECTION_MAP: Mutex<HashMap<u32, Box<Connection>>> = Default::default();
+    // locked after CONNECTION_MAP when both are needed
+    static ref CONNECTION_INDEX: Mutex<ConnectionIndex> = Default::default();
+}
+
+// handles of the connections in CONNECTION_MAP by their other keys.  Keys need not be unique,
+// so each maps to every handle that has it, oldest first.
+#[derive(Default)]
+struct ConnectionIndex {
+    pw_did: HashMap<String, Vec<u32>>,
+    source_id: HashMap<String, Vec<u32>>,
+    their_did: HashMap<String, Vec<u32>>,
+    their_verkey: HashMap<String, Vec<u32>>,
+}
+
+fn add_key(index: &mut HashMap<String, Vec<u32>>, key: &str, handle: u32) {
+    if key.is_empty() { return; }
+    index.entry(key.to_string()).or_insert_with(Vec::new).push(handle);
+}
+
+fn remove_key(index: &mut HashMap<String, Vec<u32>>, key: &str, handle: u32) {
+    let now_empty = match index.get_mut(key) {
+        Some(handles) => {
+            handles.retain(|x| *x != handle);
+            handles.is_empty()
+        },
+        None => false,
+    };
+    if now_empty { index.remove(key); }
+}
+
+impl ConnectionIndex {
+    fn add(&mut self, cxn: &Connection) {
+        add_key(&mut self.pw_did, &cxn.pw_did, cxn.handle);
+        add_key(&mut self.source_id, &cxn.source_id, cxn.handle);
+        add_key(&mut self.their_did, &cxn.their_did, cxn.handle);
+        add_key(&mut self.their_verkey, &cxn.their_verkey, cxn.handle);
+    }
+
+    fn remove(&mut self, cxn: &Connection) {
+        remove_key(&mut self.pw_did, &cxn.pw_did, cxn.handle);
+        remove_key(&mut self.source_id, &cxn.source_id, cxn.handle);
+        remove_key(&mut self.their_did, &cxn.their_did, cxn.handle);
+        remove_key(&mut self.their_verkey, &cxn.their_verkey, cxn.handle);
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -25,19 +86,65 @@ struct ConnectionOptions {
     phone: String,
 }
 
+/// A change of a connection's state, timestamp being in seconds since the epoch.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct StateTransition {
+    pub from: CxsStateType,
+    pub to: CxsStateType,
+    pub timestamp: u64,
+}
+
+// the states a connection may move to from each state
+fn is_valid_transition(from: CxsStateType, to: CxsStateType) -> bool {
+    match (from, to) {
+        // the pairwise did was stored
+        (CxsStateType::CxsStateNone, CxsStateType::CxsStateInitialized) |
+        // an invitation from someone else was answered
+        (CxsStateType::CxsStateNone, CxsStateType::CxsStateAccepted) |
+        (CxsStateType::CxsStateInitialized, CxsStateType::CxsStateOfferSent) |
+        (CxsStateType::CxsStateOfferSent, CxsStateType::CxsStateAccepted) |
+        (CxsStateType::CxsStateOfferSent, CxsStateType::CxsStateExpired) |
+        // it was invited again
+        (CxsStateType::CxsStateExpired, CxsStateType::CxsStateOfferSent) => true,
+        _ => false,
+    }
+}
+
+/// Payload of a CONN_DELETED_MSG_TYPE message, from_did is the deleting side's pairwise did.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ConnectionDeleted {
+    pub msg_type: String,
+    pub version: String,
+    pub from_did: String,
+}
+
 #[derive(Serialize, Deserialize)]
 struct Connection {
     source_id: String,
     handle: u32,
     pw_did: String,
     pw_verkey: String,
+    // the other party of the connection, once known
+    #[serde(default)]
+    their_did: String,
+    // only an accepted invitation tells the verkey, the agency leaves it out of connReq messages
+    #[serde(default)]
+    their_verkey: String,
+    // the agent's side of the pairwise relationship, which the key delegation proof vouches for
+    agent_did: String,
+    agent_verkey: String,
     did_endpoint: String,
     wallet: String,
     state: CxsStateType,
     uuid: String,
     endpoint: String,
     // For QR code invitation
-    invite_detail: String,
+    invite_detail: Option<InviteDetail>,
+    // seconds since the epoch, 0 when the invitation doesn't expire
+    #[serde(default)]
+    invite_expires_at: u64,
+    #[serde(default)]
+    state_history: Vec<StateTransition>,
 }
 
 impl Connection {
@@ -47,6 +154,19 @@ impl Connection {
             return error::NOT_READY.code_num;
         }
 
+        self.send_invite(options)
+    }
+
+    fn reinvite(&mut self, options: String) -> u32 {
+        if self.state != CxsStateType::CxsStateOfferSent && self.state != CxsStateType::CxsStateExpired {
+            info!("connection {} in state {} has no invitation to renew",self.handle,self.state as u32);
+            return error::NOT_READY.code_num;
+        }
+
+        self.send_invite(options)
+    }
+
+    fn send_invite(&mut self, options: String) -> u32 {
         let options_obj: ConnectionOptions = match serde_json::from_str(options.trim()) {
             Ok(val) => val,
             Err(_) => return error::INVALID_OPTION.code_num
@@ -54,37 +174,76 @@ impl Connection {
 
         let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT).unwrap());
 
-        let json_msg = match messages::send_invite()
+        let proof = match KeyDlgProof::create(&self.pw_did, &self.agent_did, &self.agent_verkey) {
+            Ok(x) => x,
+            Err(x) => return x,
+        };
+
+        let recipient = self.get_recipient();
+        let format = MessageFormat::from_settings();
+        let msg = match messages::send_invite()
             .to(&self.pw_did)
-            .key_delegate("key")
+            .over(&recipient)
+            .key_delegate(&proof)
             .phone_number(&options_obj.phone)
-            .serialize_message(){
+            .pack(format){
             Ok(x) => x,
             Err(x) => return x
         };
 
-        match httpclient::post(&json_msg,&url) {
+        match messages::post_message(&msg, &url, format) {
             Err(_) => {
                 println!("better message");
                 return error::POST_MSG_FAILURE.code_num
             },
-            Ok(response) => {
-                self.state = CxsStateType::CxsStateOfferSent;
-                self.invite_detail = get_invite_detail(&response);
-                return error::SUCCESS.code_num;
+            Ok((response, format)) => {
+                self.invite_detail = parse_invite_detail(&response, &recipient, format);
+                self.invite_expires_at = invite_expiry(&self.invite_detail);
+                match self.set_state(CxsStateType::CxsStateOfferSent) {
+                    Ok(x) | Err(x) => x,
+                }
             }
         }
     }
 
+    // only an open invitation can expire, reported_by_agency being whether the agency said it did
+    fn is_invite_expired(&self, reported_by_agency: bool) -> bool {
+        if self.state != CxsStateType::CxsStateOfferSent { return false; }
+
+        reported_by_agency || (self.invite_expires_at > 0 && utils::now() >= self.invite_expires_at)
+    }
+
     fn get_state(&self) -> u32 {
         let state = self.state as u32;
         state
     }
     fn set_pw_did(&mut self, did: &str) { self.pw_did = did.to_string(); }
-    fn set_state(&mut self, state: CxsStateType) { self.state = state; }
+    fn set_state(&mut self, state: CxsStateType) -> Result<u32, u32> {
+        if state == self.state { return Ok(error::SUCCESS.code_num); }
+        if !is_valid_transition(self.state, state) {
+            warn!("connection {} can't move from state {} to {}", self.handle, self.state as u32, state as u32);
+            return Err(error::INVALID_STATE_TRANSITION.code_num);
+        }
+
+        info!("connection {} moved from state {} to {}", self.handle, self.state as u32, state as u32);
+        self.state_history.push(StateTransition { from: self.state, to: state, timestamp: utils::now() });
+        self.state = state;
+        Ok(error::SUCCESS.code_num)
+    }
     fn get_pw_did(&self) -> String { self.pw_did.clone() }
     fn get_pw_verkey(&self) -> String { self.pw_verkey.clone() }
     fn set_pw_verkey(&mut self, verkey: &str) { self.pw_verkey = verkey.to_string(); }
+    fn set_agent_keys(&mut self, did: &str, verkey: &str) {
+        self.agent_did = did.to_string();
+        self.agent_verkey = verkey.to_string();
+    }
+    fn get_recipient(&self) -> Recipient {
+        Recipient::Connection {
+            pw_did: self.pw_did.clone(),
+            agent_did: self.agent_did.clone(),
+            agent_verkey: self.agent_verkey.clone(),
+        }
+    }
 
     fn get_uuid(&self) -> String { self.uuid.clone() }
     fn get_endpoint(&self) -> String { self.endpoint.clone() }
@@ -93,16 +252,68 @@ impl Connection {
     fn set_endpoint(&mut self, endpoint: &str) { self.endpoint = endpoint.to_string(); }
 }
 
+// the earlier of the configured time to live and the expiry the agency gave the invitation
+fn invite_expiry(invite: &Option<InviteDetail>) -> u64 {
+    let ttl = settings::get_config_value(settings::CONFIG_INVITE_TTL).ok()
+        .and_then(|x| x.parse::<u64>().ok())
+        .unwrap_or(0);
+    let configured = if ttl > 0 { utils::now() + ttl } else { 0 };
+    let reported = invite.as_ref().map(|x| x.expires_at).unwrap_or(0);
+
+    match (configured, reported) {
+        (0, x) | (x, 0) => x,
+        (x, y) => ::std::cmp::min(x, y),
+    }
+}
+
+fn add_connection(cxn: Box<Connection>) {
+    let mut m = CONNECTION_MAP.lock().unwrap();
+    CONNECTION_INDEX.lock().unwrap().add(&cxn);
+    m.insert(cxn.handle, cxn);
+}
+
+// changes keys of a connection, keeping the index in step
+fn update_keys<F: FnOnce(&mut Connection)>(handle: u32, update: F) {
+    let mut m = CONNECTION_MAP.lock().unwrap();
+
+    if let Some(cxn) = m.get_mut(&handle) {
+        let mut index = CONNECTION_INDEX.lock().unwrap();
+        index.remove(cxn);
+        update(cxn);
+        index.add(cxn);
+    }
+}
+
+// the newest connection with key in index
+fn find_handle(index: &HashMap<String, Vec<u32>>, key: &str) -> Result<u32, u32> {
+    match index.get(key).and_then(|x| x.last()) {
+        Some(handle) => Ok(*handle),
+        None => Err(error::CONNECTION_NOT_FOUND.code_num),
+    }
+}
+
 fn find_connection(did: &str) -> u32 {
-    let connection_table = CONNECTION_MAP.lock().unwrap();
+    get_handle_by_pw_did(did).unwrap_or(0)
+}
 
-    for (handle, connection) in connection_table.iter() { //TODO this could be very slow with lots of objects
-        if connection.pw_did == did {
-            return *handle;
-        }
-    };
+/// The newest connection created with source_id.
+pub fn get_handle_by_source_id(source_id: &str) -> Result<u32, u32> {
+    find_handle(&CONNECTION_INDEX.lock().unwrap().source_id, source_id)
+}
+
+/// The newest connection whose pairwise did is did.
+pub fn get_handle_by_pw_did(did: &str) -> Result<u32, u32> {
+    find_handle(&CONNECTION_INDEX.lock().unwrap().pw_did, did)
+}
+
+/// The newest connection to the party with the given did.
+pub fn get_handle_by_their_did(did: &str) -> Result<u32, u32> {
+    find_handle(&CONNECTION_INDEX.lock().unwrap().their_did, did)
+}
 
-    return 0;
+/// The newest connection to the party with the given verkey.
+pub fn get_handle_by_their_verkey(verkey: &str) -> Result<u32, u32> {
+    find_handle(&CONNECTION_INDEX.lock().unwrap().their_verkey, verkey)
 }
 
 pub fn is_valid_connection_handle(handle: u32) -> bool {
@@ -116,18 +327,25 @@ pub fn is_valid_connection_handle(handle: u32) -> bool {
 }
 
 pub fn set_pw_did(handle: u32, did: &str) {
+    update_keys(handle, |cxn| cxn.set_pw_did(did));
+}
+
+/// Moves the connection to state, failing with INVALID_STATE_TRANSITION when the connection
+/// can't get there from its current state.
+pub fn set_state(handle: u32, state: CxsStateType) -> Result<u32, u32> {
     let mut connection_table = CONNECTION_MAP.lock().unwrap();
 
-    if let Some(cxn) = connection_table.get_mut(&handle) {
-        cxn.set_pw_did(did);
+    match connection_table.get_mut(&handle) {
+        Some(cxn) => cxn.set_state(state),
+        None => Err(error::INVALID_CONNECTION_HANDLE.code_num),
     }
 }
 
-pub fn set_state(handle: u32, state: CxsStateType) {
-    let mut connection_table = CONNECTION_MAP.lock().unwrap();
-
-    if let Some(cxn) = connection_table.get_mut(&handle) {
-        cxn.set_state(state);
+/// Every state change of the connection so far, oldest first.
+pub fn get_state_history(handle: u32) -> Result<Vec<StateTransition>, u32> {
+    match CONNECTION_MAP.lock().unwrap().get(&handle) {
+        Some(cxn) => Ok(cxn.state_history.clone()),
+        None => Err(error::INVALID_CONNECTION_HANDLE.code_num),
     }
 }
 
@@ -171,6 +389,14 @@ pub fn set_pw_verkey(handle: u32, verkey: &str) {
     }
 }
 
+pub fn set_agent_keys(handle: u32, did: &str, verkey: &str) {
+    let mut connection_table = CONNECTION_MAP.lock().unwrap();
+
+    if let Some(cxn) = connection_table.get_mut(&handle) {
+        cxn.set_agent_keys(did, verkey);
+    }
+}
+
 pub fn get_endpoint(handle: u32) -> Result<String, u32> {
     let connection_table = CONNECTION_MAP.lock().unwrap();
     match connection_table.get(&handle) {
@@ -188,6 +414,17 @@ pub fn get_pw_verkey(handle: u32) -> Result<String, u32> {
     }
 }
 
+/// The relationship messages about the connection travel over, which they are encrypted
+/// with.  It is read before a message is built, as packing it must not lock the connection.
+pub fn get_recipient(handle: u32) -> Result<Recipient, u32> {
+    let connection_table = CONNECTION_MAP.lock().unwrap();
+
+    match connection_table.get(&handle) {
+        Some(cxn) => Ok(cxn.get_recipient()),
+        None => Err(error::INVALID_CONNECTION_HANDLE.code_num),
+    }
+}
+
 pub fn create_agent_pairwise(handle: u32) -> Result<u32, u32> {
     let enterprise_did = settings::get_config_value(settings::CONFIG_ENTERPRISE_DID_AGENCY).unwrap();
     let pw_did = match get_pw_did(handle) {
@@ -197,18 +434,29 @@ pub fn create_agent_pairwise(handle: u32) -> Result<u32, u32> {
     let pw_verkey = get_pw_verkey(handle).unwrap();
     let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT).unwrap());
 
-    let json_msg = match messages::create_keys()
+    let format = MessageFormat::from_settings();
+    let msg = match messages::create_keys()
         .to(&pw_did)
         .for_did(&enterprise_did)
         .for_verkey(&pw_verkey)
         .nonce("anything")
-        .serialize_message(){
+        .pack(format){
         Ok(x) => x,
         Err(x) => return Err(x),
     };
 
-    match httpclient::post(&json_msg, &url) {
-        Ok(_) => return Ok(error::SUCCESS.code_num),
+    match messages::post_message(&msg, &url, format) {
+        Ok((response, format)) => {
+            let mut key = CreateKeyMsg::unpack_response(&response, format)?;
+            if settings::test_mode_enabled() && key.for_did.is_empty() {
+                //TEST MODE: the canned response carries no keys, hand out fixed ones
+                key.for_did = "KkTVEE7RGg7z2d2hrfM2Hj".to_string();
+                key.for_verkey = "AevwvcQBLv5CERRJShzUncV7ubapSgbDZxus42zS8fk1".to_string();
+            }
+            info!("agent created pairwise did {} for connection {}", key.for_did, handle);
+            set_agent_keys(handle, &key.for_did, &key.for_verkey);
+            Ok(error::SUCCESS.code_num)
+        },
         Err(_) => return Err(error::POST_MSG_FAILURE.code_num),
     }
 }
@@ -221,21 +469,238 @@ pub fn update_agent_profile(handle: u32) -> Result<u32, u32> {
     };
     let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT).unwrap());
 
-    let json_msg = match messages::update_data()
+    let recipient = get_recipient(handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = match messages::update_data()
         .to(&pw_did)
+        .over(&recipient)
         .name(&settings::get_config_value(settings::CONFIG_ENTERPRISE_NAME).unwrap())
         .logo_url(&settings::get_config_value(settings::CONFIG_LOGO_URL).unwrap())
-        .serialize_message(){
+        .pack(format){
         Ok(x) => x,
         Err(x) => return Err(x)
     };
 
-    match httpclient::post(&json_msg, &url) {
-        Ok(_) => return Ok(error::SUCCESS.code_num),
+    match messages::post_message(&msg, &url, format) {
+        Ok((response, format)) => {
+            UpdateProfileData::unpack_response_over(&response, &recipient, format)?;
+            Ok(error::SUCCESS.code_num)
+        },
         Err(_) => return Err(error::POST_MSG_FAILURE.code_num),
     }
 }
 
+/// Sets the status of messages stored at the connection's agent.
+pub fn update_message_status(handle: u32, uids: &[String], status_code: &str) -> Result<u32, u32> {
+    if !is_valid_connection_handle(handle) {
+        return Err(error::INVALID_CONNECTION_HANDLE.code_num);
+    }
+
+    let pw_did = get_pw_did(handle)?;
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+    let recipient = get_recipient(handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = messages::update_message_status()
+        .to(&pw_did)
+        .over(&recipient)
+        .uids(uids)
+        .status_code(status_code)
+        .pack(format)?;
+
+    match messages::post_message(&msg, &url, format) {
+        Ok((response, format)) => {
+            UpdateMessageStatus::unpack_response_over(&response, &recipient, format)?;
+            info!("set status of messages {:?} of connection {} to {}", uids, handle, status_code);
+            Ok(error::SUCCESS.code_num)
+        },
+        Err(x) => {
+            warn!("could not update status of messages for connection {}: {}", handle, x);
+            Err(error::POST_MSG_FAILURE.code_num)
+        },
+    }
+}
+
+/// Marks messages the SDK has processed as reviewed.  Failing to do so only means they are
+/// seen again on the next poll, so errors are logged rather than returned.
+pub fn acknowledge_messages(handle: u32, uids: &[String]) {
+    if uids.is_empty() { return; }
+
+    if let Err(x) = update_message_status(handle, uids, MSG_STATUS_REVIEWED) {
+        warn!("could not acknowledge messages {:?} of connection {}: {}", uids, handle, x);
+    }
+}
+
+// tells the other party the connection is going away, while the agent can still reach them
+fn notify_deletion(handle: u32, pw_did: &str) -> Result<u32, u32> {
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+    let payload = ConnectionDeleted {
+        msg_type: "CONN_DELETED".to_string(),
+        version: "0.1".to_string(),
+        from_did: pw_did.to_string(),
+    };
+
+    let recipient = get_recipient(handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = messages::send_message()
+        .to(pw_did)
+        .over(&recipient)
+        .msg_type(CONN_DELETED_MSG_TYPE)
+        .edge_agent_payload(&serde_json::to_string(&payload).unwrap())
+        .pack(format)?;
+
+    match messages::post_message(&msg, &url, format) {
+        Ok((response, format)) => {
+            SendMessage::unpack_response_over(&response, &recipient, format)?;
+            Ok(error::SUCCESS.code_num)
+        },
+        Err(x) => {
+            warn!("could not tell the other party connection {} is deleted: {}", handle, x);
+            Err(error::POST_MSG_FAILURE.code_num)
+        },
+    }
+}
+
+/// Deletes the pairwise relationship at the agent, telling the other party first when notify
+/// is set and the connection was accepted, then retires the pairwise did in the wallet and
+/// releases the handle.  Nothing is deleted when the agent can't be reached.  Connections
+/// that never got a pairwise did have nothing at the agent and are only released.
+pub fn delete_connection(handle: u32, notify: bool) -> Result<u32, u32> {
+    let state = match CONNECTION_MAP.lock().unwrap().get(&handle) {
+        Some(cxn) => cxn.state,
+        None => return Err(error::INVALID_CONNECTION_HANDLE.code_num),
+    };
+
+    let pw_did = get_pw_did(handle)?;
+    if pw_did.is_empty() || state == CxsStateType::CxsStateNone {
+        info!("connection {} has no pairwise relationship at the agent, releasing it", handle);
+        return Ok(release(handle));
+    }
+
+    // deleting doesn't depend on the other party hearing about it
+    if notify && state == CxsStateType::CxsStateAccepted {
+        if let Err(x) = notify_deletion(handle, &pw_did) {
+            warn!("deleting connection {} without telling the other party: {}", handle, x);
+        }
+    }
+
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+    let recipient = get_recipient(handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = messages::update_connection_status()
+        .to(&pw_did)
+        .over(&recipient)
+        .status_code(CONN_STATUS_DELETED)
+        .pack(format)?;
+
+    match messages::post_message(&msg, &url, format) {
+        Ok((response, format)) => { UpdateConnectionStatus::unpack_response_over(&response, &recipient, format)?; },
+        Err(x) => {
+            warn!("could not delete connection {} at the agent: {}", handle, x);
+            return Err(error::POST_MSG_FAILURE.code_num);
+        },
+    };
+    info!("deleted pairwise did {} of connection {} at the agent", pw_did, handle);
+
+    // the relationship is gone at the agent either way, so the wallet only gets a warning
+    if let Err(x) = wallet::retire_my_did(&pw_did) {
+        warn!("could not retire did {} of connection {}: {}", pw_did, handle, x);
+    }
+
+    Ok(release(handle))
+}
+
+fn answer_invitation(handle: u32, invite: &InviteDetail) -> Result<u32, u32> {
+    create_agent_pairwise(handle)?;
+    update_agent_profile(handle)?;
+
+    let pw_did = get_pw_did(handle)?;
+    let proof = {
+        let m = CONNECTION_MAP.lock().unwrap();
+        let cxn = m.get(&handle).ok_or(error::INVALID_CONNECTION_HANDLE.code_num)?;
+        KeyDlgProof::create(&pw_did, &cxn.agent_did, &cxn.agent_verkey)?
+    };
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+    let recipient = get_recipient(handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = messages::accept_invitation()
+        .to(&pw_did)
+        .over(&recipient)
+        .msg_uid(&invite.conn_req_id)
+        .enterprise_name(&settings::get_config_value(settings::CONFIG_ENTERPRISE_NAME)?)
+        .logo_url(&settings::get_config_value(settings::CONFIG_LOGO_URL)?)
+        .sender_did(&invite.sender_did)
+        .sender_verkey(&invite.sender_verkey)
+        .key_delegate(&proof)
+        .remote_endpoint(&invite.sender_endpoint)
+        .pack(format)?;
+
+    match messages::post_message(&msg, &url, format) {
+        Ok((response, format)) => {
+            AcceptInvitation::unpack_response_over(&response, &recipient, format)?;
+            Ok(error::SUCCESS.code_num)
+        },
+        Err(x) => {
+            warn!("could not accept invitation {} for connection {}: {}", invite.conn_req_id, handle, x);
+            Err(error::POST_MSG_FAILURE.code_num)
+        },
+    }
+}
+
+/// Accepts an invitation that another enterprise sent, given the json of its invite details
+/// or a deep link to it.  Creates the pairwise did and its keys at the agent, answers the
+/// invitation and returns a connection in the accepted state.  Nothing is kept when any of
+/// those steps fail.
+pub fn accept_invitation(source_id: Option<String>, invite_details: &str) -> Result<u32, u32> {
+    let invite = if Url::parse(invite_details).is_ok() {
+        InviteDetail::from_deep_link(invite_details)?
+    } else {
+        InviteDetail::from_str(invite_details)?
+    };
+    if validation::validate_did(&invite.sender_did).is_err() || validation::validate_verkey(&invite.sender_verkey).is_err() {
+        warn!("invite {} has an invalid sender did or verkey", invite.conn_req_id);
+        return Err(error::INVALID_INVITE_DETAILS.code_num);
+    }
+    crypto::store_their_did(&invite.sender_did, &invite.sender_verkey)?;
+    validation::validate_key_delegate(&invite.sender_agent_key_dlg_proof, &invite.sender_did)?;
+
+    let (pw_did, pw_verkey) = wallet::create_my_did("{}")?;
+    let handle = rand::thread_rng().gen::<u32>();
+    info!("accepting invitation {} from {} with connection {}", invite.conn_req_id, invite.sender_name, handle);
+
+    add_connection(Box::new(Connection {
+        source_id: source_id.unwrap_or_default(),
+        handle: handle,
+        pw_did: pw_did.clone(),
+        pw_verkey: pw_verkey,
+        their_did: invite.sender_did.clone(),
+        their_verkey: invite.sender_verkey.clone(),
+        agent_did: String::new(),
+        agent_verkey: String::new(),
+        did_endpoint: String::new(),
+        wallet: String::new(),
+        state: CxsStateType::CxsStateNone,
+        uuid: String::new(),
+        endpoint: invite.sender_endpoint.clone(),
+        invite_detail: Some(invite.clone()),
+        invite_expires_at: 0,
+        state_history: Vec::new(),
+    }));
+
+    match answer_invitation(handle, &invite).and_then(|_| set_state(handle, CxsStateType::CxsStateAccepted)) {
+        Ok(_) => Ok(handle),
+        Err(x) => {
+            // the did never made it into a relationship, so it mustn't look like one in use
+            if let Err(e) = wallet::retire_my_did(&pw_did) {
+                warn!("could not retire did {} of failed connection {}: {}", pw_did, handle, e);
+            }
+            release(handle);
+            Err(x)
+        },
+    }
+}
+
 //TODO may want to split between the code path where did is pass and is not passed
 pub fn build_connection (source_id: Option<String>,
                          did: Option<String>,
@@ -259,19 +724,22 @@ pub fn build_connection (source_id: Option<String>,
         handle: new_handle,
         pw_did: String::new(),
         pw_verkey: String::new(),
+        their_did: their_did.unwrap_or_default(),
+        their_verkey: String::new(),
+        agent_did: String::new(),
+        agent_verkey: String::new(),
         did_endpoint: String::new(),
         wallet: String::new(),
         state: CxsStateType::CxsStateNone,
         uuid: String::new(),
         endpoint: String::new(),
-        invite_detail: String::new(),
+        invite_detail: None,
+        invite_expires_at: 0,
+        state_history: Vec::new(),
     });
 
-    {
-        let mut m = CONNECTION_MAP.lock().unwrap();
-        info!("inserting handle {} into connection table", new_handle);
-        m.insert(new_handle, c);
-    }
+    info!("inserting handle {} into connection table", new_handle);
+    add_connection(c);
 
 
     if did.is_none() { //TODO need better input validation
@@ -309,22 +777,74 @@ pub fn update_state(handle: u32) -> u32{
 
     let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT).unwrap());
 
-    let json_msg = match messages::get_messages()
+    let recipient = match get_recipient(handle) {
+        Ok(x) => x,
+        Err(x) => return x,
+    };
+
+    let format = MessageFormat::from_settings();
+    let msg = match messages::get_messages()
         .to(&pw_did)
-        .serialize_message(){
+        .over(&recipient)
+        .pack(format){
         Ok(x) => x,
         Err(x) => return x,
     };
-    match httpclient::post(&json_msg, &url) {
-        Err(_) => {error::POST_MSG_FAILURE.code_num}
-        Ok(response) => {
-            if response.contains("message accepted") { set_state(handle, CxsStateType::CxsStateAccepted); }
-            error::SUCCESS.code_num
-            //TODO: add expiration handling
+    match messages::post_message(&msg, &url, format) {
+        Err(_) => {
+            // the invite's own expiry still counts while the agency can't be reached
+            expire_invite(handle, false);
+            error::POST_MSG_FAILURE.code_num
+        }
+        Ok((response, format)) => {
+            let msgs = match GetMessages::unpack_response_over(&response, &recipient, format) {
+                Ok(x) => x.msgs,
+                Err(x) => return x,
+            };
+
+            let mut accepted = Vec::new();
+            let mut expired = Vec::new();
+            let mut their_did = String::new();
+            for msg in msgs.into_iter().filter(|x| x.msg_type == CONN_REQ_MSG_TYPE) {
+                if msg.status_code == MSG_STATUS_ACCEPTED {
+                    their_did = msg.sender_did;
+                    accepted.push(msg.uid);
+                } else if msg.status_code == MSG_STATUS_EXPIRED {
+                    expired.push(msg.uid);
+                }
+            }
+            // after a re-invite only the expiry of the latest request counts
+            let reported_expired = match get_invite_detail(handle) {
+                Ok(invite) => expired.contains(&invite.conn_req_id),
+                Err(_) => !expired.is_empty(),
+            };
+
+            if !accepted.is_empty() {
+                if let Err(x) = set_state(handle, CxsStateType::CxsStateAccepted) {
+                    return x;
+                }
+                if !their_did.is_empty() {
+                    update_keys(handle, |cxn| cxn.their_did = their_did);
+                }
+                // the state keeps the acceptance, so the request needn't be processed again
+                acknowledge_messages(handle, &accepted);
+                error::SUCCESS.code_num
+            } else {
+                expire_invite(handle, reported_expired)
+            }
         }
     }
 }
 
+fn expire_invite(handle: u32, reported_by_agency: bool) -> u32 {
+    if !is_invite_expired(handle, reported_by_agency) { return error::SUCCESS.code_num; }
+
+    match set_state(handle, CxsStateType::CxsStateExpired) {
+        Ok(x) => x,
+        Err(x) => x,
+    }
+}
+
 
 pub fn get_state(handle: u32) -> u32 {
     // Try to update state from agent first
@@ -352,6 +872,24 @@ pub fn connect(handle: u32, options: String) -> u32 {
     rc
 }
 
+/// Sends a new invitation for a connection whose invitation expired or wasn't answered yet,
+/// moving it back to the offer sent state.
+pub fn reinvite(handle: u32, options: String) -> u32 {
+    let mut m = CONNECTION_MAP.lock().unwrap();
+
+    match m.get_mut(&handle) {
+        Some(t) => t.reinvite(options),
+        None => error::INVALID_CONNECTION_HANDLE.code_num,
+    }
+}
+
+fn is_invite_expired(handle: u32, reported_by_agency: bool) -> bool {
+    match CONNECTION_MAP.lock().unwrap().get(&handle) {
+        Some(cxn) => cxn.is_invite_expired(reported_by_agency),
+        None => false,
+    }
+}
+
 pub fn to_string(handle: u32) -> String {
     let m = CONNECTION_MAP.lock().unwrap();
     let result = m.get(&handle);
@@ -364,40 +902,139 @@ pub fn to_string(handle: u32) -> String {
     connection_json.to_owned()
 }
 
+#[derive(Deserialize)]
+struct SerializedConnection {
+    version: String,
+    data: serde_json::Value,
+}
+
+/// Serializes everything needed to rebuild the connection in another process, tagged with
+/// SERIALIZE_VERSION.
+pub fn serialize(handle: u32) -> Result<String, u32> {
+    match CONNECTION_MAP.lock().unwrap().get(&handle) {
+        Some(cxn) => Ok(json!({"version": SERIALIZE_VERSION, "data": cxn}).to_string()),
+        None => Err(error::INVALID_CONNECTION_HANDLE.code_num),
+    }
+}
+
+/// Rebuilds a connection from the output of serialize, under a new handle.
+pub fn deserialize(connection_data: &str) -> Result<u32, u32> {
+    let serialized: SerializedConnection = match serde_json::from_str(connection_data) {
+        Ok(x) => x,
+        Err(x) => {
+            warn!("invalid serialized connection: {}", x);
+            return Err(error::INVALID_SERIALIZED_CONNECTION.code_num);
+        },
+    };
+    if serialized.version != SERIALIZE_VERSION {
+        warn!("unsupported serialized connection version {}", serialized.version);
+        return Err(error::INVALID_SERIALIZED_CONNECTION.code_num);
+    }
+
+    let mut cxn: Connection = match serde_json::from_value(serialized.data) {
+        Ok(x) => x,
+        Err(x) => {
+            warn!("invalid serialized connection: {}", x);
+            return Err(error::INVALID_SERIALIZED_CONNECTION.code_num);
+        },
+    };
+    let handle = rand::thread_rng().gen::<u32>();
+    info!("restoring connection {} as {}", cxn.handle, handle);
+    cxn.handle = handle;
+    add_connection(Box::new(cxn));
+
+    Ok(handle)
+}
+
+/// Returns the handle and current state of every connection object, without asking the agent for updates.
+pub fn list_state() -> Vec<(u32, u32)> {
+    CONNECTION_MAP.lock().unwrap().iter().map(|(handle, x)| (*handle, x.state as u32)).collect()
+}
+
 pub fn release(handle: u32) -> u32 {
     let mut m = CONNECTION_MAP.lock().unwrap();
     let result = m.remove(&handle);
 
     let rc = match result {
-        Some(t) => error::SUCCESS.code_num,
+        Some(t) => {
+            CONNECTION_INDEX.lock().unwrap().remove(&t);
+            error::SUCCESS.code_num
+        },
         None => error::INVALID_CONNECTION_HANDLE.code_num,
     };
 
     rc
 }
 
-fn get_invite_detail(response: &str) -> String {
-    match serde_json::from_str(response) {
-        Ok(json) => {
-            let json: serde_json::Value = json;
-            let detail = &json["inviteDetail"];
-            detail.to_string()
-        }
+fn parse_invite_detail(response: &[u8], recipient: &Recipient, format: MessageFormat) -> Option<InviteDetail> {
+    match SendInvite::unpack_response_over(response, recipient, format) {
+        Ok(x) => x.invite_detail,
         Err(_) => {
             info!("Connect called without a valid response from server");
-            String::from("")
+            None
         }
     }
 }
 
+/// The invitation the connection sent or accepted.
+pub fn get_invite_detail(handle: u32) -> Result<InviteDetail, u32> {
+    match CONNECTION_MAP.lock().unwrap().get(&handle) {
+        Some(cxn) => match cxn.invite_detail {
+            Some(ref x) => Ok(x.clone()),
+            None => {
+                info!("connection {} has no invitation yet", handle);
+                Err(error::NOT_READY.code_num)
+            },
+        },
+        None => Err(error::INVALID_CONNECTION_HANDLE.code_num),
+    }
+}
+
+/// A deep link that carries the connection's invitation, under the agency's invite path.
+pub fn get_invite_link(handle: u32) -> Result<String, u32> {
+    let invite = get_invite_detail(handle)?;
+    let base_url = format!("{}/agency/invite", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+    invite.deep_link(&base_url)
+}
+
+/// Renders the connection's invite link as a qr code, image_format being "svg" or "png".
+pub fn get_invite_qr_code(handle: u32, image_format: &str) -> Result<Vec<u8>, u32> {
+    let link = get_invite_link(handle)?;
+    match image_format {
+        "svg" => Ok(qr::svg(&link)?.into_bytes()),
+        "png" => qr::png(&link),
+        _ => {
+            warn!("unsupported qr code format {}", image_format);
+            Err(error::INVALID_OPTION.code_num)
+        },
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub mod tests {
     extern crate mockito;
     use super::*;
     use utils::wallet;
     use std::thread;
     use std::time::Duration;
 
+    pub fn build_test_connection() -> u32 {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let handle = build_connection(Some("test_connection".to_owned()), None, None);
+        // wait for the test mode did to be stored
+        thread::sleep(Duration::from_millis(500));
+        handle
+    }
+
+    // a test connection that went through the offer to the accepted state
+    pub fn build_accepted_test_connection() -> u32 {
+        let handle = build_test_connection();
+        set_state(handle, CxsStateType::CxsStateOfferSent).unwrap();
+        set_state(handle, CxsStateType::CxsStateAccepted).unwrap();
+        handle
+    }
+
     #[test]
     fn test_create_connection() {
         settings::set_defaults();
@@ -425,8 +1062,8 @@ mod tests {
         let _m = mockito::mock("POST", "/agency/route")
             .with_status(202)
             .with_header("content-type", "text/plain")
-            .with_body("message accepted")
-            .expect(1)
+            .with_body("{\"msgs\":[{\"uid\":\"CXqcDCE\",\"msgType\":\"connReq\",\"statusCode\":\"MS-104\"}]}")
+            .expect(2)
             .create();
 
         assert_eq!(get_state(handle), CxsStateType::CxsStateAccepted as u32);
@@ -494,6 +1131,128 @@ mod tests {
         release(handle);
     }
 
+    #[test]
+    fn test_state_transitions() {
+        let handle = build_test_connection();
+        assert_eq!(set_state(handle, CxsStateType::CxsStateAccepted), Err(error::INVALID_STATE_TRANSITION.code_num));
+        assert_eq!(set_state(handle, CxsStateType::CxsStateOfferSent), Ok(error::SUCCESS.code_num));
+        assert_eq!(set_state(handle, CxsStateType::CxsStateOfferSent), Ok(error::SUCCESS.code_num));
+        assert_eq!(set_state(handle, CxsStateType::CxsStateInitialized), Err(error::INVALID_STATE_TRANSITION.code_num));
+        assert_eq!(set_state(0, CxsStateType::CxsStateOfferSent), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+
+        let history = get_state_history(handle).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].from, history[0].to), (CxsStateType::CxsStateNone, CxsStateType::CxsStateInitialized));
+        assert_eq!((history[1].from, history[1].to), (CxsStateType::CxsStateInitialized, CxsStateType::CxsStateOfferSent));
+        assert!(history[1].timestamp >= history[0].timestamp);
+        assert!(to_string(handle).contains("\"state_history\":[{\"from\":0,\"to\":1,\"timestamp\":"));
+        release(handle);
+    }
+
+    #[test]
+    fn test_invite_expiration_and_reinvite() {
+        let handle = build_test_connection();
+        assert_eq!(reinvite(handle, "{}".to_string()), error::NOT_READY.code_num);
+        assert_eq!(connect(handle, "{}".to_string()), error::SUCCESS.code_num);
+        assert_eq!(get_state(handle), CxsStateType::CxsStateOfferSent as u32);
+        assert!(is_invite_expired(handle, true));
+
+        CONNECTION_MAP.lock().unwrap().get_mut(&handle).unwrap().invite_expires_at = 1;
+        assert_eq!(get_state(handle), CxsStateType::CxsStateExpired as u32);
+        assert!(!is_invite_expired(handle, true));
+
+        assert_eq!(reinvite(handle, "{}".to_string()), error::SUCCESS.code_num);
+        assert_eq!(get_state(handle), CxsStateType::CxsStateOfferSent as u32);
+        let history = get_state_history(handle).unwrap();
+        assert_eq!((history[2].from, history[2].to), (CxsStateType::CxsStateOfferSent, CxsStateType::CxsStateExpired));
+        assert_eq!((history[3].from, history[3].to), (CxsStateType::CxsStateExpired, CxsStateType::CxsStateOfferSent));
+        assert_eq!(reinvite(0, "{}".to_string()), error::INVALID_CONNECTION_HANDLE.code_num);
+        release(handle);
+
+        let invite = InviteDetail { expires_at: 1510000000, ..Default::default() };
+        assert_eq!(invite_expiry(&Some(invite)), 1510000000);
+    }
+
+    #[test]
+    fn test_get_handle_by_keys() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let source_id = "test_get_handle_by_keys";
+        let handle = build_connection(Some(source_id.to_owned()), None, Some("4fUDR9R7fjwELRvH9JT6HH".to_owned()));
+        thread::sleep(Duration::from_millis(500));
+        set_pw_did(handle, "9sT4M2aUVgAnKVsa6GD4bZ");
+        assert_eq!(get_handle_by_source_id(source_id), Ok(handle));
+        assert_eq!(get_handle_by_pw_did("9sT4M2aUVgAnKVsa6GD4bZ"), Ok(handle));
+        assert_eq!(get_handle_by_their_did("4fUDR9R7fjwELRvH9JT6HH"), Ok(handle));
+        assert!(CONNECTION_INDEX.lock().unwrap().pw_did.get("8XFh8yBzrpJQmNyZzgoTqB").map_or(true, |x| !x.contains(&handle)));
+
+        let handle2 = build_connection(Some(source_id.to_owned()), None, None);
+        assert_eq!(get_handle_by_source_id(source_id), Ok(handle2));
+        release(handle2);
+        assert_eq!(get_handle_by_source_id(source_id), Ok(handle));
+
+        release(handle);
+        assert_eq!(get_handle_by_source_id(source_id), Err(error::CONNECTION_NOT_FOUND.code_num));
+        assert_eq!(get_handle_by_pw_did("9sT4M2aUVgAnKVsa6GD4bZ"), Err(error::CONNECTION_NOT_FOUND.code_num));
+        assert_eq!(get_handle_by_their_did("4fUDR9R7fjwELRvH9JT6HH"), Err(error::CONNECTION_NOT_FOUND.code_num));
+    }
+
+    #[test]
+    fn test_serialize_deserialize() {
+        let handle = build_test_connection();
+        set_uuid(handle, "THISISA!UUID");
+        set_endpoint(handle, "34.210.228.152:80");
+        set_agent_keys(handle, "KkTVEE7RGg7z2d2hrfM2Hj", "AevwvcQBLv5CERRJShzUncV7ubapSgbDZxus42zS8fk1");
+        CONNECTION_MAP.lock().unwrap().get_mut(&handle).unwrap().invite_detail = Some(InviteDetail {
+            conn_req_id: "CXqcDCE".to_string(),
+            ..Default::default()
+        });
+        let data = serialize(handle).unwrap();
+        assert!(data.contains("\"version\":\"1.0\""));
+
+        let restored = deserialize(&data).unwrap();
+        assert_ne!(restored, handle);
+        assert_eq!(get_pw_did(restored), get_pw_did(handle));
+        assert_eq!(get_pw_verkey(restored), get_pw_verkey(handle));
+        assert_eq!(get_endpoint(restored).unwrap(), "34.210.228.152:80");
+        assert_eq!(get_uuid(restored).unwrap(), "THISISA!UUID");
+        assert_eq!(get_invite_detail(restored).unwrap().conn_req_id, "CXqcDCE");
+        assert_eq!(get_state_history(restored), get_state_history(handle));
+        release(handle);
+        release(restored);
+
+        assert_eq!(deserialize(&data.replace("\"1.0\"", "\"0.9\"")), Err(error::INVALID_SERIALIZED_CONNECTION.code_num));
+        assert_eq!(deserialize("{\"version\":\"1.0\",\"data\":{}}"), Err(error::INVALID_SERIALIZED_CONNECTION.code_num));
+        assert_eq!(deserialize("garbage"), Err(error::INVALID_SERIALIZED_CONNECTION.code_num));
+        assert_eq!(serialize(0), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+    }
+
+    #[test]
+    fn test_delete_connection() {
+        let handle = build_accepted_test_connection();
+        assert_eq!(delete_connection(handle, true), Ok(error::SUCCESS.code_num));
+        assert!(!is_valid_connection_handle(handle));
+        assert_eq!(delete_connection(handle, false), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+
+        let handle = build_test_connection();
+        assert_eq!(delete_connection(handle, true), Ok(error::SUCCESS.code_num));
+        assert_eq!(release(handle), error::INVALID_CONNECTION_HANDLE.code_num);
+
+        // before its did is stored the connection only exists locally
+        let handle = build_connection(Some("test_delete_connection".to_owned()), None, None);
+        assert_eq!(get_pw_did(handle), Ok(String::new()));
+        assert_eq!(delete_connection(handle, true), Ok(error::SUCCESS.code_num));
+        assert!(!is_valid_connection_handle(handle));
+
+        let payload = ConnectionDeleted {
+            msg_type: "CONN_DELETED".to_string(),
+            version: "0.1".to_string(),
+            from_did: "8XFh8yBzrpJQmNyZzgoTqB".to_string(),
+        };
+        assert_eq!(serde_json::to_string(&payload).unwrap(),
+                   "{\"msg_type\":\"CONN_DELETED\",\"version\":\"0.1\",\"from_did\":\"8XFh8yBzrpJQmNyZzgoTqB\"}");
+    }
+
     #[test]
     fn test_connect_fails() {
         // Need to add content here once we've implemented connected
@@ -596,12 +1355,18 @@ mod tests {
             handle: handle,
             pw_did: "8XFh8yBzrpJQmNyZzgoTqB".to_string(),
             pw_verkey: "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A".to_string(),
+            their_did: String::new(),
+            their_verkey: String::new(),
+            agent_did: String::new(),
+            agent_verkey: String::new(),
             did_endpoint: String::new(),
             wallet: String::new(),
             state: CxsStateType::CxsStateNone,
             uuid: String::new(),
             endpoint: String::new(),
-            invite_detail: String::new(),
+            invite_detail: None,
+            invite_expires_at: 0,
+            state_history: Vec::new(),
         });
 
         {
@@ -626,12 +1391,18 @@ mod tests {
             handle: handle,
             pw_did: "8XFh8yBzrpJQmNyZzgoTqB".to_string(),
             pw_verkey: "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A".to_string(),
+            their_did: String::new(),
+            their_verkey: String::new(),
+            agent_did: String::new(),
+            agent_verkey: String::new(),
             did_endpoint: String::new(),
             wallet: String::new(),
             state: CxsStateType::CxsStateNone,
             uuid: String::new(),
             endpoint: String::new(),
-            invite_detail: String::new(),
+            invite_detail: None,
+            invite_expires_at: 0,
+            state_history: Vec::new(),
         });
 
         {
@@ -688,7 +1459,7 @@ mod tests {
         let response = "{ \"inviteDetail\": {
                 \"senderEndpoint\": \"34.210.228.152:80\",
                 \"connReqId\": \"CXqcDCE\",
-                \"senderAgentKeyDlgProof\": \"sdfsdf\",
+                \"senderAgentKeyDlgProof\": {\"agentDID\": \"KkTVEE7RGg7z2d2hrfM2Hj\", \"signature\": \"sdfsdf\"},
                 \"senderName\": \"Evernym\",
                 \"senderDID\": \"JiLBHundRhwYaMbPWno8Vg\",
                 \"senderLogoUrl\": \"https://postimg.org/image/do2r09ain/\",
@@ -714,8 +1485,8 @@ mod tests {
         let _m = mockito::mock("POST", "/agency/route")
             .with_status(202)
             .with_header("content-type", "text/plain")
-            .with_body("message accepted")
-            .expect(1)
+            .with_body("{\"msgs\":[{\"uid\":\"CXqcDCE\",\"msgType\":\"connReq\",\"statusCode\":\"MS-104\"}]}")
+            .expect(2)
             .create();
 
         assert_eq!(get_state(handle), CxsStateType::CxsStateAccepted as u32);
@@ -724,12 +1495,56 @@ mod tests {
         release(handle);
     }
 
+    #[test]
+    fn test_update_message_status() {
+        let handle = build_test_connection();
+        let uids = vec!["CXqcDCE".to_string()];
+        assert_eq!(update_message_status(handle, &uids, MSG_STATUS_REVIEWED).unwrap(), error::SUCCESS.code_num);
+        assert_eq!(update_message_status(handle, &[], MSG_STATUS_REVIEWED), Err(error::INVALID_MSG_STATUS.code_num));
+        assert_eq!(update_message_status(0, &uids, MSG_STATUS_REVIEWED), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+        release(handle);
+    }
+
+    #[test]
+    fn test_accept_invitation() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let proof = KeyDlgProof::create("JiLBHundRhwYaMbPWno8Vg", "KkTVEE7RGg7z2d2hrfM2Hj", "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A").unwrap();
+        let invite = json!({
+            "connReqId": "CXqcDCE",
+            "senderEndpoint": "34.210.228.152:80",
+            "senderName": "Evernym",
+            "senderDID": "JiLBHundRhwYaMbPWno8Vg",
+            "senderDIDVerKey": "AevwvcQBLv5CERRJShzUncV7ubapSgbDZxus42zS8fk1",
+            "senderAgentKeyDlgProof": proof
+        }).to_string();
+        let invite = invite.as_str();
+        let handle = accept_invitation(Some("test_accept_invitation".to_owned()), invite).unwrap();
+        assert_eq!(get_pw_did(handle).unwrap(), "8XFh8yBzrpJQmNyZzgoTqB");
+        assert_eq!(get_endpoint(handle).unwrap(), "34.210.228.152:80");
+        assert!(CONNECTION_INDEX.lock().unwrap().their_verkey["AevwvcQBLv5CERRJShzUncV7ubapSgbDZxus42zS8fk1"].contains(&handle));
+        assert_eq!(get_state(handle), CxsStateType::CxsStateAccepted as u32);
+        release(handle);
+
+        let link = InviteDetail::from_str(invite).unwrap().deep_link("http://127.0.0.1:8080/agency/invite").unwrap();
+        let handle = accept_invitation(None, &link).unwrap();
+        assert_eq!(get_invite_detail(handle).unwrap().conn_req_id, "CXqcDCE");
+        assert!(get_invite_link(handle).unwrap().contains("/agency/invite?c="));
+        assert!(String::from_utf8(get_invite_qr_code(handle, "svg").unwrap()).unwrap().starts_with("<?xml"));
+        assert_eq!(get_invite_qr_code(handle, "gif"), Err(error::INVALID_OPTION.code_num));
+        release(handle);
+
+        assert_eq!(accept_invitation(None, "{\"connReqId\":\"CXqcDCE\"}"), Err(error::INVALID_INVITE_DETAILS.code_num));
+        assert_eq!(accept_invitation(None, &invite.replace("JiLBHundRhwYaMbPWno8Vg", "JiLB")), Err(error::INVALID_INVITE_DETAILS.code_num));
+        assert_eq!(accept_invitation(None, &invite.replace(&proof.signature, "3yZe7d")), Err(error::INVALID_KEY_DELEGATE.code_num));
+    }
+
     #[test]
     fn test_jsonfying_invite_details() {
         let response = "{ \"inviteDetail\": {
                 \"senderEndpoint\": \"34.210.228.152:80\",
                 \"connReqId\": \"CXqcDCE\",
-                \"senderAgentKeyDlgProof\": \"sdfsdf\",
+                \"senderAgentKeyDlgProof\": {\"agentDID\": \"KkTVEE7RGg7z2d2hrfM2Hj\", \"signature\": \"sdfsdf\"},
                 \"senderName\": \"Evernym\",
                 \"senderDID\": \"JiLBHundRhwYaMbPWno8Vg\",
                 \"senderLogoUrl\": \"https://postimg.org/image/do2r09ain/\",
@@ -738,8 +1553,9 @@ mod tests {
             }}";
 
 
-        let invite_detail = get_invite_detail(response);
-        info!("Invite Detail Test: {}", invite_detail);
-        assert!(invite_detail.contains("sdfsdf"));
+        let invite_detail = parse_invite_detail(response.as_bytes(), &Recipient::Agent, MessageFormat::Json).unwrap();
+        info!("Invite Detail Test: {:?}", invite_detail);
+        assert_eq!(invite_detail.sender_agent_key_dlg_proof.signature, "sdfsdf");
+        assert_eq!(invite_detail.sender_did, "JiLBHundRhwYaMbPWno8Vg");
     }
 }
@@ -5,8 +5,23 @@ extern crate libc;
 use std::sync::Mutex;
 use std::collections::HashMap;
 use rand::Rng;
+use serde_json::Value;
 use api::CxsStateType;
+use settings;
+use connection;
+use claimdef;
+use messages;
+use messages::GeneralMessage;
+use messages::message::{Message, MSG_STATUS_REVIEWED, unpack_messages};
+use messages::format::MessageFormat;
+use messages::send_message::parse_msg_uid;
 use utils::error;
+use utils::anoncreds;
+
+pub static CLAIM_OFFER_MSG_TYPE: &'static str = "claimOffer";
+pub static CLAIM_REQ_MSG_TYPE: &'static str = "claimReq";
+pub static CLAIM_MSG_TYPE: &'static str = "claim";
+pub static CLAIM_TERMINATE_MSG_TYPE: &'static str = "claimTerminate";
 
 lazy_static! {
     static ref ISSUER_CLAIM_MAP: Mutex<HashMap<u32, Box<IssuerClaim>>> = Default::default();
@@ -20,22 +35,419 @@ struct IssuerClaim {
     claim_attributes: String,
     issued_did: String,
     state: CxsStateType,
+    #[serde(default)]
+    connection_handle: u32,
+    #[serde(default)]
+    msg_uid: String,
+    #[serde(default)]
+    claim_request: Option<ClaimRequest>,
+    // the agent's message the claim request came in, acknowledged once the claim is sent
+    #[serde(default)]
+    claim_request_uid: String,
+    #[serde(default)]
+    signed_claim: String,
+    #[serde(default)]
+    revoc_index: i32,
+    #[serde(default)]
+    termination_reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ClaimOffer {
+    pub msg_type: String,
+    pub version: String,
+    pub from_did: String,
+    pub claim: Value,
+    pub schema_seq_no: u32,
+    pub issuer_did: String,
+    pub claim_name: String,
+    pub claim_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct BlindedMasterSecret {
+    pub prover_did: String,
+    pub u: String,
+    pub ur: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ClaimRequest {
+    pub blinded_ms: BlindedMasterSecret,
+    pub issuer_did: String,
+    pub schema_seq_no: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub msg_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub from_did: String,
+}
+
+impl ClaimRequest {
+    pub fn from_str(payload: &str) -> Result<ClaimRequest, u32> {
+        match serde_json::from_str(payload) {
+            Ok(x) => Ok(x),
+            Err(x) => {
+                warn!("invalid claim request: {}", x);
+                Err(error::INVALID_CLAIM_REQUEST.code_num)
+            },
+        }
+    }
+}
+
+/// Describes why claim data doesn't match the schema of the claim definition.  code_num is
+/// the error for the first kind of problem found, message lists every bad attribute.
+#[derive(Debug, PartialEq)]
+pub struct ClaimDataError {
+    pub code_num: u32,
+    pub message: String,
+}
+
+/// Claim data maps every attribute of the schema to its raw value, given either as a
+/// string, a number or a single element array (ie "name": ["Alex"]).
+pub fn validate_claim_data(claim_def_handle: u32, claim_data: &str) -> Result<(), ClaimDataError> {
+    let attr_names = match claimdef::get_attr_names(claim_def_handle) {
+        Ok(x) => x,
+        Err(x) => return Err(ClaimDataError { code_num: x, message: error::error_message(&x).to_string() }),
+    };
+
+    let attributes: serde_json::Map<String, Value> = match serde_json::from_str(claim_data) {
+        Ok(x) => x,
+        Err(x) => return Err(ClaimDataError {
+            code_num: error::INVALID_OPTION.code_num,
+            message: format!("claim data is not a json object: {}", x),
+        }),
+    };
+
+    let missing: Vec<&str> = attr_names.iter()
+        .filter(|x| !attributes.contains_key(*x))
+        .map(|x| x.as_str())
+        .collect();
+    let unknown: Vec<&str> = attributes.keys()
+        .filter(|x| !attr_names.contains(x))
+        .map(|x| x.as_str())
+        .collect();
+    let malformed: Vec<&str> = attributes.iter()
+        .filter(|&(_, value)| attribute_value(value).is_none())
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    let kinds = [(missing, &error::MISSING_CLAIM_ATTRIBUTES, "missing"),
+                 (unknown, &error::UNKNOWN_CLAIM_ATTRIBUTES, "not in schema"),
+                 (malformed, &error::INVALID_CLAIM_ATTRIBUTES, "malformed")];
+
+    let mut code_num = error::SUCCESS.code_num;
+    let mut problems = Vec::new();
+    for &(ref names, err, label) in kinds.iter() {
+        if names.is_empty() { continue; }
+        if code_num == error::SUCCESS.code_num { code_num = err.code_num; }
+        problems.push(format!("{}: {}", label, names.join(", ")));
+    }
+
+    if problems.is_empty() { return Ok(()); }
+
+    Err(ClaimDataError { code_num: code_num, message: problems.join("; ") })
+}
+
+fn attribute_value(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref x) => Some(x.clone()),
+        Value::Number(ref x) => Some(x.to_string()),
+        Value::Array(ref x) if x.len() == 1 => match x[0] {
+            Value::Array(_) => None,
+            ref x => attribute_value(x),
+        },
+        _ => None,
+    }
 }
 
 impl IssuerClaim {
-    fn validate_claim_offer(&self) -> Result<u32, String> {
-        //TODO: validate claim_attributes against claim_def
+    fn generate_claim_offer(&self, from_did: &str) -> Result<ClaimOffer, u32> {
+        let claim: Value = match serde_json::from_str(&self.claim_attributes) {
+            Ok(x) => x,
+            Err(x) => {
+                warn!("claim {} has invalid claim attributes: {}", self.handle, x);
+                return Err(error::INVALID_OPTION.code_num);
+            },
+        };
+
+        Ok(ClaimOffer {
+            msg_type: "CLAIM_OFFER".to_string(),
+            version: "0.1".to_string(),
+            from_did: from_did.to_string(),
+            claim: claim,
+            schema_seq_no: claimdef::get_schema_seq_no(self.claim_def)?,
+            issuer_did: claimdef::get_issuer_did(self.claim_def)?,
+            claim_name: self.source_id.clone(),
+            claim_id: self.handle.to_string(),
+        })
+    }
+
+    fn send_claim_offer(&mut self, connection_handle: u32) -> Result<u32, u32> {
+        if self.state != CxsStateType::CxsStateInitialized {
+            warn!("claim {} in state {} not ready to send claim offer", self.handle, self.state as u32);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        let to_did = get_accepted_pw_did(connection_handle)?;
+        let claim_offer = self.generate_claim_offer(&to_did)?;
+
+        self.msg_uid = send_to_connection(connection_handle, &to_did, CLAIM_OFFER_MSG_TYPE, &serde_json::to_string(&claim_offer).unwrap())?;
+        self.connection_handle = connection_handle;
+        self.state = CxsStateType::CxsStateOfferSent;
+        info!("sent claim offer for claim {} with uid {}", self.handle, self.msg_uid);
+        Ok(error::SUCCESS.code_num)
+    }
+
+    fn get_claim_request(&mut self) -> Result<String, u32> {
+        if let Some(ref x) = self.claim_request { return Ok(serde_json::to_string(x).unwrap()); }
+
+        if self.state != CxsStateType::CxsStateOfferSent {
+            warn!("claim {} has not sent a claim offer", self.handle);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        let to_did = connection::get_pw_did(self.connection_handle)?;
+        let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+        let recipient = connection::get_recipient(self.connection_handle)?;
+        let format = MessageFormat::from_settings();
+        let msg = messages::get_messages()
+            .to(&to_did)
+            .over(&recipient)
+            .msg_type(CLAIM_REQ_MSG_TYPE)
+            .pack(format)?;
+
+        match messages::post_message(&msg, &url, format) {
+            Ok((response, format)) => self.update_claim_request(unpack_messages(&response, &recipient, format)?),
+            Err(x) => {
+                warn!("could not get messages for claim {}: {}", self.handle, x);
+                Err(error::POST_MSG_FAILURE.code_num)
+            },
+        }
+    }
+
+    fn update_claim_request(&mut self, msgs: Vec<Message>) -> Result<String, u32> {
+        // without the uid of the offer any claim request on the connection could be taken for its answer
+        if self.msg_uid.is_empty() {
+            warn!("claim {} has no offer uid to match claim requests with", self.handle);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        let schema_seq_no = claimdef::get_schema_seq_no(self.claim_def)?;
+        let issuer_did = claimdef::get_issuer_did(self.claim_def)?;
+
+        for msg in msgs {
+            if msg.msg_type != CLAIM_REQ_MSG_TYPE || msg.status_code == MSG_STATUS_REVIEWED || msg.ref_msg_id != self.msg_uid {
+                continue;
+            }
+
+            let claim_request = match ClaimRequest::from_str(&msg.edge_agent_payload) {
+                Ok(x) => x,
+                Err(_) => {
+                    warn!("skipping unparsable claim request {} for claim {}", msg.uid, self.handle);
+                    continue;
+                },
+            };
+            if claim_request.schema_seq_no != schema_seq_no || claim_request.issuer_did != issuer_did {
+                warn!("claim request {} does not match the claim definition of claim {}", msg.uid, self.handle);
+                continue;
+            }
+
+            info!("received claim request {} for claim {}", msg.uid, self.handle);
+            let claim_request_json = serde_json::to_string(&claim_request).unwrap();
+            self.claim_request = Some(claim_request);
+            self.claim_request_uid = msg.uid;
+            self.state = CxsStateType::CxsStateRequestReceived;
+            return Ok(claim_request_json);
+        }
+
+        info!("no claim request received yet for claim {}", self.handle);
+        Err(error::NOT_READY.code_num)
+    }
+
+    fn generate_claim_values(&self) -> Result<String, u32> {
+        let attributes: serde_json::Map<String, Value> = match serde_json::from_str(&self.claim_attributes) {
+            Ok(x) => x,
+            Err(x) => {
+                warn!("claim {} has invalid claim attributes: {}", self.handle, x);
+                return Err(error::INVALID_OPTION.code_num);
+            },
+        };
+
+        let mut values = serde_json::Map::new();
+        for (name, value) in attributes.iter() {
+            match attribute_value(value) {
+                Some(x) => {
+                    let encoded = anoncreds::encode_attribute(&x);
+                    values.insert(name.clone(), json!([x, encoded]));
+                },
+                None => {
+                    warn!("claim {} attribute {} does not have a usable value", self.handle, name);
+                    return Err(error::INVALID_CLAIM_ATTRIBUTES.code_num);
+                },
+            };
+        }
+
+        Ok(Value::Object(values).to_string())
+    }
+
+    fn accept_claim(&mut self) -> Result<u32, u32> {
+        if !self.signed_claim.is_empty() { return Ok(error::SUCCESS.code_num); }
+
+        let claim_request = match self.claim_request {
+            Some(ref x) => serde_json::to_string(x).unwrap(),
+            None => {
+                warn!("claim {} has not received a claim request", self.handle);
+                return Err(error::NOT_READY.code_num);
+            },
+        };
+
+        let claim_values = self.generate_claim_values()?;
+        let revoc_index = claimdef::next_revoc_index(self.claim_def)?;
+        let (_, signed_claim) = anoncreds::issuer_create_claim(&claim_request, &claim_values, revoc_index)?;
+
+        self.signed_claim = signed_claim;
+        self.revoc_index = revoc_index;
+        info!("created signed claim for claim {}", self.handle);
         Ok(error::SUCCESS.code_num)
     }
 
-    fn send_claim_offer(&mut self, connection_handle: u32) -> Result<u32, String> {
+    fn send_claim(&mut self, connection_handle: u32) -> Result<u32, u32> {
+        if self.state != CxsStateType::CxsStateRequestReceived {
+            warn!("claim {} in state {} not ready to send claim", self.handle, self.state as u32);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        if connection_handle != self.connection_handle {
+            warn!("claim {} was offered over connection {}, not {}", self.handle, self.connection_handle, connection_handle);
+            return Err(error::INVALID_CONNECTION_HANDLE.code_num);
+        }
+
+        let to_did = get_accepted_pw_did(connection_handle)?;
+        self.accept_claim()?;
+
+        let signed_claim: Value = match serde_json::from_str(&self.signed_claim) {
+            Ok(x) => x,
+            Err(x) => {
+                warn!("claim {} has an invalid signed claim: {}", self.handle, x);
+                return Err(error::UNKNOWN_ERROR.code_num);
+            },
+        };
+        let payload = json!({
+            "msg_type": "CLAIM",
+            "version": "0.1",
+            "from_did": to_did,
+            "claim_offer_id": self.msg_uid,
+            "claim": signed_claim
+        });
+
+        send_to_connection(connection_handle, &to_did, CLAIM_MSG_TYPE, &payload.to_string())?;
+        self.state = CxsStateType::CxsStateAccepted;
+        // only now that the claim is issued can the agent stop handing the request out
+        if !self.claim_request_uid.is_empty() {
+            connection::acknowledge_messages(connection_handle, &[self.claim_request_uid.clone()]);
+        }
+        info!("sent claim {} to connection {}", self.handle, connection_handle);
         Ok(error::SUCCESS.code_num)
     }
+
+    /// Claims that were issued can only be revoked; claims that weren't issued yet are
+    /// left unfulfilled and the holder is told why.
+    fn terminate(&mut self, termination_type: u32, reason: &str) -> Result<u32, u32> {
+        let new_state = match self.state {
+            CxsStateType::CxsStateAccepted => {
+                if termination_type != CxsStateType::CxsStateRevoked as u32 {
+                    warn!("issued claim {} can only be revoked", self.handle);
+                    return Err(error::INVALID_OPTION.code_num);
+                }
+
+                let issuer_did = claimdef::get_issuer_did(self.claim_def)?;
+                let schema_seq_no = claimdef::get_schema_seq_no(self.claim_def)?;
+                anoncreds::issuer_revoke_claim(&issuer_did, schema_seq_no, self.revoc_index)?;
+                CxsStateType::CxsStateRevoked
+            },
+            CxsStateType::CxsStateInitialized |
+            CxsStateType::CxsStateOfferSent |
+            CxsStateType::CxsStateRequestReceived => {
+                if termination_type != CxsStateType::CxsStateUnfulfilled as u32 {
+                    warn!("claim {} has not been issued and can't be revoked", self.handle);
+                    return Err(error::INVALID_OPTION.code_num);
+                }
+
+                if self.state != CxsStateType::CxsStateInitialized {
+                    let to_did = connection::get_pw_did(self.connection_handle)?;
+                    let payload = json!({
+                        "msg_type": "CLAIM_TERMINATE",
+                        "version": "0.1",
+                        "claim_offer_id": self.msg_uid,
+                        "reason": reason
+                    });
+                    send_to_connection(self.connection_handle, &to_did, CLAIM_TERMINATE_MSG_TYPE, &payload.to_string())?;
+                }
+                CxsStateType::CxsStateUnfulfilled
+            },
+            _ => {
+                warn!("claim {} in state {} can't be terminated", self.handle, self.state as u32);
+                return Err(error::NOT_READY.code_num);
+            },
+        };
+
+        self.state = new_state;
+        self.termination_reason = reason.to_string();
+        info!("terminated claim {} with state {}: {}", self.handle, new_state as u32, reason);
+        Ok(error::SUCCESS.code_num)
+    }
+}
+
+/// Returns the pairwise did of the connection if it is ready to receive claim messages.
+fn get_accepted_pw_did(connection_handle: u32) -> Result<String, u32> {
+    if !connection::is_valid_connection_handle(connection_handle) {
+        return Err(error::INVALID_CONNECTION_HANDLE.code_num);
+    }
+
+    if connection::get_state(connection_handle) != CxsStateType::CxsStateAccepted as u32 {
+        warn!("connection {} has not been accepted", connection_handle);
+        return Err(error::NOT_READY.code_num);
+    }
+
+    connection::get_pw_did(connection_handle)
 }
 
+/// Sends the payload to the connection through the agency and returns the uid of the message.
+fn send_to_connection(connection_handle: u32, to_did: &str, msg_type: &str, payload: &str) -> Result<String, u32> {
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+    let recipient = connection::get_recipient(connection_handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = messages::send_message()
+        .to(to_did)
+        .over(&recipient)
+        .msg_type(msg_type)
+        .edge_agent_payload(payload)
+        .pack(format)?;
+
+    match messages::post_message(&msg, &url, format) {
+        Ok((response, format)) => parse_msg_uid(&response, &recipient, format),
+        Err(x) => {
+            warn!("could not send {} message: {}", msg_type, x);
+            Err(error::POST_MSG_FAILURE.code_num)
+        },
+    }
+}
+
+/// Creates a claim for claim_data.  If the claim data doesn't match the schema the error
+/// message lists every bad attribute, like validate_claim_data.
 pub fn issuer_claim_create(claim_def_handle: u32,
                            source_id: Option<String>,
-                           claim_data: String) -> Result<u32, String> {
+                           claim_data: String) -> Result<u32, ClaimDataError> {
+
+    if !claimdef::is_valid_handle(claim_def_handle) {
+        warn!("claimdef handle {} does not exist", claim_def_handle);
+        let code_num = error::INVALID_CLAIM_DEF_HANDLE.code_num;
+        return Err(ClaimDataError { code_num: code_num, message: error::error_message(&code_num).to_string() });
+    }
 
     let new_handle = rand::thread_rng().gen::<u32>();
 
@@ -47,12 +459,22 @@ pub fn issuer_claim_create(claim_def_handle: u32,
         claim_def: claim_def_handle,
         claim_attributes: claim_data,
         issued_did: String::new(),
-        state: CxsStateType::CxsStateNone,
+        state: CxsStateType::CxsStateInitialized,
+        connection_handle: 0,
+        msg_uid: String::new(),
+        claim_request: None,
+        claim_request_uid: String::new(),
+        signed_claim: String::new(),
+        revoc_index: 0,
+        termination_reason: String::new(),
     });
 
-    match new_issuer_claim.validate_claim_offer() {
+    match validate_claim_data(claim_def_handle, &new_issuer_claim.claim_attributes) {
         Ok(_) => info!("successfully validated issuer_claim {}", new_handle),
-        Err(x) => return Err(x),
+        Err(x) => {
+            warn!("claim {} has invalid claim data: {}", new_handle, x.message);
+            return Err(x);
+        },
     };
 
     {
@@ -65,6 +487,11 @@ pub fn issuer_claim_create(claim_def_handle: u32,
 }
 
 
+/// Returns the handle and current state of every issuer claim object, without asking the agent for a claim request.
+pub fn list_state() -> Vec<(u32, u32)> {
+    ISSUER_CLAIM_MAP.lock().unwrap().iter().map(|(handle, x)| (*handle, x.state as u32)).collect()
+}
+
 pub fn release(handle: u32) -> u32 {
     let mut m = ISSUER_CLAIM_MAP.lock().unwrap();
     let result = m.remove(&handle);
@@ -106,26 +533,67 @@ pub fn from_string(claim_data: &str) -> Result<u32,u32> {
     Ok(new_handle)
 }
 
+pub fn get_state(handle: u32) -> Result<u32,u32> {
+    match ISSUER_CLAIM_MAP.lock().unwrap().get(&handle) {
+        Some(c) => Ok(c.state as u32),
+        None => Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num),
+    }
+}
+
+pub fn get_claim_request(handle: u32) -> Result<String,u32> {
+    let mut t = ISSUER_CLAIM_MAP.lock().unwrap();
+    match t.get_mut(&handle) {
+        Some(c) => c.get_claim_request(),
+        None => Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num),
+    }
+}
+
+pub fn accept_claim(handle: u32) -> Result<u32,u32> {
+    let mut t = ISSUER_CLAIM_MAP.lock().unwrap();
+    match t.get_mut(&handle) {
+        Some(c) => c.accept_claim(),
+        None => Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num),
+    }
+}
+
+pub fn send_claim(handle: u32, connection_handle: u32) -> Result<u32,u32> {
+    let mut t = ISSUER_CLAIM_MAP.lock().unwrap();
+    match t.get_mut(&handle) {
+        Some(c) => c.send_claim(connection_handle),
+        None => Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num),
+    }
+}
+
+pub fn terminate(handle: u32, termination_type: u32, reason: &str) -> Result<u32,u32> {
+    let mut t = ISSUER_CLAIM_MAP.lock().unwrap();
+    match t.get_mut(&handle) {
+        Some(c) => c.terminate(termination_type, reason),
+        None => Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num),
+    }
+}
+
 pub fn send_claim_offer(handle: u32, connection_handle: u32) -> Result<u32,u32> {
     let mut t = ISSUER_CLAIM_MAP.lock().unwrap();
     let result = t.get_mut(&handle);
 
     match result {
-        Some(c) => match c.send_claim_offer(connection_handle) {
-            Ok(_) => Ok(error::SUCCESS.code_num),
-            Err(_) => Err(error::UNKNOWN_ERROR.code_num),
-        },
+        Some(c) => c.send_claim_offer(connection_handle),
         None => Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num),
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub mod tests {
     use super::*;
+    use claimdef::tests::create_test_claimdef;
+    use connection::tests::{build_test_connection, build_accepted_test_connection};
+    use messages::message::parse_messages;
+
+    pub static CLAIM_DATA: &'static str = "{\"name\":[\"Alex\"],\"age\":[\"28\"],\"height\":[\"175\"],\"sex\":[\"male\"]}";
 
     #[test]
     fn test_issuer_claim_create_succeeds() {
-        match issuer_claim_create(0, None, "{\"attr\":\"value\"}".to_owned()) {
+        match issuer_claim_create(create_test_claimdef(), None, CLAIM_DATA.to_owned()) {
             Ok(x) => assert!(x > 0),
             Err(_) => assert_eq!(0,1), //fail if we get here
         }
@@ -133,20 +601,176 @@ mod tests {
 
     #[test]
     fn test_to_string_succeeds() {
-        let handle = issuer_claim_create(0, None,"{\"attr\":\"value\"}".to_owned()).unwrap();
+        let handle = issuer_claim_create(create_test_claimdef(), None,CLAIM_DATA.to_owned()).unwrap();
         let string = to_string(handle).unwrap();
         assert!(!string.is_empty());
     }
 
     #[test]
     fn test_send_claim_offer() {
-        let handle = issuer_claim_create(0, None,"{\"attr\":\"value\"}".to_owned()).unwrap();
-        assert_eq!(send_claim_offer(handle,0).unwrap(),error::SUCCESS.code_num);
+        let connection_handle = build_accepted_test_connection();
+        let handle = issuer_claim_create(create_test_claimdef(), None,CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateInitialized as u32);
+        assert_eq!(send_claim_offer(handle,connection_handle).unwrap(),error::SUCCESS.code_num);
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateOfferSent as u32);
+        assert_eq!(send_claim_offer(handle,connection_handle), Err(error::NOT_READY.code_num));
+        release(handle);
+        connection::release(connection_handle);
+    }
+
+    #[test]
+    fn test_send_claim_offer_fails_without_accepted_connection() {
+        let connection_handle = build_test_connection();
+        let handle = issuer_claim_create(create_test_claimdef(), None,CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(send_claim_offer(handle,0), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+        assert_eq!(send_claim_offer(handle,connection_handle), Err(error::NOT_READY.code_num));
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateInitialized as u32);
+        release(handle);
+        connection::release(connection_handle);
+    }
+
+    fn claim_request_messages(ref_msg_id: &str, issuer_did: &str) -> Vec<Message> {
+        let claim_request = json!({
+            "blinded_ms": {"prover_did": "FQ7wPBUgSPnDGJnS1EYjTK", "u": "923...607", "ur": null},
+            "issuer_did": issuer_did,
+            "schema_seq_no": 15
+        });
+        parse_messages(&json!({"msgs": [
+            {"uid": "1", "msgType": "claimReq", "refMsgId": ref_msg_id, "edgeAgentPayload": claim_request.to_string()}
+        ]}).to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_get_claim_request_before_offer_fails() {
+        let handle = issuer_claim_create(create_test_claimdef(), None,CLAIM_DATA.to_owned()).unwrap();
+        assert_eq!(get_claim_request(handle), Err(error::NOT_READY.code_num));
+        assert_eq!(get_claim_request(0), Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num));
+        release(handle);
+    }
+
+    #[test]
+    fn test_update_claim_request_from_messages() {
+        let handle = issuer_claim_create(create_test_claimdef(), None,CLAIM_DATA.to_owned()).unwrap();
+        let issuer_did = settings::get_config_value(settings::CONFIG_ENTERPRISE_DID).unwrap();
+
+        {
+            let mut m = ISSUER_CLAIM_MAP.lock().unwrap();
+            let claim = m.get_mut(&handle).unwrap();
+            claim.state = CxsStateType::CxsStateOfferSent;
+            assert_eq!(claim.update_claim_request(claim_request_messages("", &issuer_did)), Err(error::NOT_READY.code_num));
+            claim.msg_uid = "123".to_string();
+            assert_eq!(claim.update_claim_request(claim_request_messages("456", &issuer_did)), Err(error::NOT_READY.code_num));
+            assert_eq!(claim.update_claim_request(claim_request_messages("123", "4fUDR9R7fjwELRvH9JT6HH")), Err(error::NOT_READY.code_num));
+            assert_eq!(claim.update_claim_request(parse_messages("{\"msgs\":[{\"uid\":\"1\",\"msgType\":\"claimReq\",\"refMsgId\":\"123\",\"edgeAgentPayload\":\"{}\"}]}").unwrap()),
+                       Err(error::NOT_READY.code_num));
+            let mut msgs = parse_messages("{\"msgs\":[{\"uid\":\"0\",\"msgType\":\"claimReq\",\"refMsgId\":\"123\",\"edgeAgentPayload\":\"not json\"}]}").unwrap();
+            msgs.extend(claim_request_messages("123", &issuer_did));
+            assert!(claim.update_claim_request(msgs).is_ok());
+            assert_eq!(claim.claim_request.as_ref().unwrap().blinded_ms.prover_did, "FQ7wPBUgSPnDGJnS1EYjTK");
+            assert_eq!(claim.claim_request_uid, "1");
+        }
+
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateRequestReceived as u32);
+        let claim_request = ClaimRequest::from_str(&get_claim_request(handle).unwrap()).unwrap();
+        assert_eq!(claim_request.schema_seq_no, 15);
+        release(handle);
+    }
+
+    #[test]
+    fn test_validate_claim_data() {
+        let claim_def = create_test_claimdef();
+        assert_eq!(validate_claim_data(claim_def, CLAIM_DATA), Ok(()));
+        assert_eq!(validate_claim_data(claim_def, "{\"name\":\"Alex\",\"age\":28,\"height\":[175],\"sex\":\"male\"}"), Ok(()));
+
+        let err = validate_claim_data(claim_def, "{\"name\":\"Alex\",\"age\":28}").unwrap_err();
+        assert_eq!(err.code_num, error::MISSING_CLAIM_ATTRIBUTES.code_num);
+        assert_eq!(err.message, "missing: height, sex");
+
+        let err = validate_claim_data(claim_def, "{\"name\":\"Alex\",\"agee\":28,\"height\":175,\"sex\":\"male\"}").unwrap_err();
+        assert_eq!(err.code_num, error::MISSING_CLAIM_ATTRIBUTES.code_num);
+        assert_eq!(err.message, "missing: age; not in schema: agee");
+
+        let err = validate_claim_data(claim_def, "{\"name\":\"Alex\",\"age\":28,\"height\":175,\"sex\":\"male\",\"eyes\":\"blue\"}").unwrap_err();
+        assert_eq!(err.code_num, error::UNKNOWN_CLAIM_ATTRIBUTES.code_num);
+
+        let err = validate_claim_data(claim_def, "{\"name\":[\"Alex\",\"Bob\"],\"age\":null,\"height\":175,\"sex\":{}}").unwrap_err();
+        assert_eq!(err.code_num, error::INVALID_CLAIM_ATTRIBUTES.code_num);
+        assert_eq!(err.message, "malformed: age, name, sex");
+
+        assert_eq!(validate_claim_data(claim_def, "[]").unwrap_err().code_num, error::INVALID_OPTION.code_num);
+        assert_eq!(validate_claim_data(0, CLAIM_DATA).unwrap_err().code_num, error::INVALID_CLAIM_DEF_HANDLE.code_num);
+    }
+
+    #[test]
+    fn test_issuer_claim_create_with_invalid_claim_data_fails() {
+        let err = issuer_claim_create(create_test_claimdef(), None, "{\"attr\":\"value\"}".to_owned()).unwrap_err();
+        assert_eq!(err.code_num, error::MISSING_CLAIM_ATTRIBUTES.code_num);
+        assert_eq!(err.message, "missing: name, age, height, sex; not in schema: attr");
+    }
+
+    #[test]
+    fn test_send_claim() {
+        let connection_handle = build_accepted_test_connection();
+        let handle = issuer_claim_create(create_test_claimdef(), None,"{\"name\":[\"Alex\"],\"age\":\"28\",\"height\":175,\"sex\":\"male\"}".to_owned()).unwrap();
+        let issuer_did = settings::get_config_value(settings::CONFIG_ENTERPRISE_DID).unwrap();
+        assert_eq!(send_claim(handle, connection_handle), Err(error::NOT_READY.code_num));
+
+        {
+            let mut m = ISSUER_CLAIM_MAP.lock().unwrap();
+            let claim = m.get_mut(&handle).unwrap();
+            claim.msg_uid = "123".to_string();
+            claim.state = CxsStateType::CxsStateOfferSent;
+            claim.connection_handle = connection_handle;
+            claim.update_claim_request(claim_request_messages("123", &issuer_did)).unwrap();
+            let values: Value = serde_json::from_str(&claim.generate_claim_values().unwrap()).unwrap();
+            assert_eq!(values["name"], json!(["Alex", "99262857098057710338306967609588410025648622308394250666849665532448612202874"]));
+            assert_eq!(values["age"], json!(["28", "28"]));
+        }
+
+        assert_eq!(send_claim(handle, connection_handle + 1), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateRequestReceived as u32);
+        assert_eq!(send_claim(handle, connection_handle).unwrap(), error::SUCCESS.code_num);
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateAccepted as u32);
+        assert!(!ISSUER_CLAIM_MAP.lock().unwrap().get(&handle).unwrap().signed_claim.is_empty());
+        assert_eq!(send_claim(handle, connection_handle), Err(error::NOT_READY.code_num));
+
+        assert_eq!(terminate(handle, CxsStateType::CxsStateUnfulfilled as u32, "expired"), Err(error::INVALID_OPTION.code_num));
+        assert_eq!(terminate(handle, CxsStateType::CxsStateRevoked as u32, "fraud").unwrap(), error::SUCCESS.code_num);
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateRevoked as u32);
+        assert!(to_string(handle).unwrap().contains("\"termination_reason\":\"fraud\""));
+        assert_eq!(terminate(handle, CxsStateType::CxsStateRevoked as u32, "fraud"), Err(error::NOT_READY.code_num));
+        release(handle);
+        connection::release(connection_handle);
+    }
+
+    #[test]
+    fn test_terminate_before_issuance() {
+        let connection_handle = build_accepted_test_connection();
+        let handle = issuer_claim_create(create_test_claimdef(), None,CLAIM_DATA.to_owned()).unwrap();
+        send_claim_offer(handle, connection_handle).unwrap();
+        assert_eq!(terminate(handle, CxsStateType::CxsStateRevoked as u32, "no longer offered"), Err(error::INVALID_OPTION.code_num));
+        assert_eq!(terminate(handle, CxsStateType::CxsStateUnfulfilled as u32, "no longer offered").unwrap(), error::SUCCESS.code_num);
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateUnfulfilled as u32);
+        assert_eq!(terminate(0, CxsStateType::CxsStateUnfulfilled as u32, ""), Err(error::INVALID_ISSUER_CLAIM_HANDLE.code_num));
+        release(handle);
+        connection::release(connection_handle);
+    }
+
+    #[test]
+    fn test_generate_claim_offer() {
+        let handle = issuer_claim_create(create_test_claimdef(), Some("test_claim".to_owned()),CLAIM_DATA.to_owned()).unwrap();
+        let m = ISSUER_CLAIM_MAP.lock().unwrap();
+        let offer = m.get(&handle).unwrap().generate_claim_offer("8XFh8yBzrpJQmNyZzgoTqB").unwrap();
+        assert_eq!(offer.msg_type, "CLAIM_OFFER");
+        assert_eq!(offer.claim["name"], json!(["Alex"]));
+        assert_eq!(offer.schema_seq_no, 15);
+        assert_eq!(offer.issuer_did, settings::get_config_value(settings::CONFIG_ENTERPRISE_DID).unwrap());
+        assert_eq!(offer.claim_name, "test_claim");
     }
 
     #[test]
     fn test_from_string_succeeds() {
-        let handle = issuer_claim_create(0, None,"{\"attr\":\"value\"}".to_owned()).unwrap();
+        let handle = issuer_claim_create(create_test_claimdef(), None,CLAIM_DATA.to_owned()).unwrap();
         let string = to_string(handle).unwrap();
         assert!(!string.is_empty());
         release(handle);
@@ -155,4 +779,19 @@ mod tests {
         assert_eq!(new_handle,handle);
         assert_eq!(new_string,string);
     }
+
+    #[test]
+    fn test_from_string_reads_claims_without_offer_fields() {
+        let string = "{\"source_id\":\"old_claim\",\"handle\":1234,\"claim_def\":0,\"claim_attributes\":\"{}\",\
+            \"issued_did\":\"\",\"state\":1}";
+        let handle = from_string(string).unwrap();
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateInitialized as u32);
+        release(handle);
+    }
+
+    #[test]
+    fn test_issuer_claim_create_with_invalid_claimdef_fails() {
+        assert_eq!(issuer_claim_create(0, None, CLAIM_DATA.to_owned()).unwrap_err().code_num,
+                   error::INVALID_CLAIM_DEF_HANDLE.code_num);
+    }
 }
@@ -28,6 +28,9 @@ use std::path::Path;
 pub mod api;
 pub mod connection;
 pub mod issuer_claim;
+pub mod schema;
+pub mod claimdef;
+pub mod proof;
 
 pub fn create_path(s:&str) -> &Path {
     Path::new(s)
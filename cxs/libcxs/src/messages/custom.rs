@@ -0,0 +1,217 @@
+extern crate serde_json;
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeMap;
+use serde_json::{Map, Value};
+use connection;
+use settings;
+use utils::error;
+use messages::format::MessageFormat;
+use messages::version::MsgType;
+use messages::{GeneralMessage, MessageHeader, post_message};
+
+/// Types of the messages built into the SDK, which can't be registered again.
+pub static RESERVED_MSG_TYPES: &'static [&'static str] = &["CREATE_KEY", "SEND_INVITE", "UPDATE_PROFILE_DATA",
+                                                           "INVITE_ANSWERED", "GET_MSGS", "SEND_MSG", "GET_VERSIONS",
+                                                           "UPDATE_MSG_STATUS", "UPDATE_CONN_STATUS"];
+
+lazy_static! {
+    // registered message type -> fields every payload of that type must carry
+    static ref CUSTOM_MSG_TYPES: RwLock<HashMap<String, Vec<String>>> = Default::default();
+}
+
+fn is_valid_msg_type(msg_type: &str) -> bool {
+    !msg_type.is_empty() && msg_type.chars().all(|c| match c {
+        'A'...'Z' | 'a'...'z' | '0'...'9' | '_' => true,
+        _ => false,
+    })
+}
+
+/// Registers a message type so CustomMessage can send it.  Registering a type again replaces
+/// its required fields.
+pub fn register_msg_type(msg_type: &str, required_fields: Vec<String>) -> Result<u32, u32> {
+    if !is_valid_msg_type(msg_type) || RESERVED_MSG_TYPES.contains(&msg_type) {
+        warn!("can't register message type {}", msg_type);
+        return Err(error::INVALID_MSG_TYPE.code_num);
+    }
+
+    info!("registered message type {} with fields {:?}", msg_type, required_fields);
+    CUSTOM_MSG_TYPES.write().unwrap().insert(msg_type.to_string(), required_fields);
+    Ok(error::SUCCESS.code_num)
+}
+
+pub fn is_registered(msg_type: &str) -> bool {
+    CUSTOM_MSG_TYPES.read().unwrap().contains_key(msg_type)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomPayload {
+    msg_type: MsgType,
+    fields: Map<String, Value>,
+}
+
+// the registered fields sit next to "type" at the top level of the payload
+impl Serialize for CustomPayload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut map = serializer.serialize_map(Some(self.fields.len() + 1))?;
+        map.serialize_entry("type", &self.msg_type)?;
+        for (key, value) in self.fields.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// A message of a type registered by the embedding application.  It goes through the same
+/// validation, encryption and wire format as the built in messages.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CustomMessage {
+    header: MessageHeader,
+    payload: CustomPayload,
+}
+
+impl CustomMessage {
+
+    pub fn create(msg_type: &str) -> CustomMessage {
+        let validate_rc = if is_registered(msg_type) {
+            error::SUCCESS.code_num
+        } else {
+            warn!("message type {} has not been registered", msg_type);
+            error::UNKNOWN_MSG_TYPE.code_num
+        };
+
+        CustomMessage {
+            header: MessageHeader { validate_rc: validate_rc, ..MessageHeader::new() },
+            payload: CustomPayload {
+                msg_type: MsgType::new(msg_type),
+                fields: Map::new(),
+            },
+        }
+    }
+
+    /// Sets the fields of the payload from a json object, which must have every field the
+    /// message type was registered with.
+    pub fn fields(&mut self, fields: &str) -> &mut Self {
+        let fields: Map<String, Value> = match serde_json::from_str(fields) {
+            Ok(x) => x,
+            Err(x) => {
+                warn!("payload of {} message is not a json object: {}", self.payload.msg_type.name, x);
+                return self.invalid(error::INVALID_MSG_PAYLOAD.code_num);
+            },
+        };
+
+        let missing: Vec<String> = match CUSTOM_MSG_TYPES.read().unwrap().get(&self.payload.msg_type.name) {
+            Some(required) => required.iter().filter(|x| !fields.contains_key(*x)).cloned().collect(),
+            None => Vec::new(),
+        };
+
+        if !missing.is_empty() || fields.contains_key("type") {
+            warn!("payload of {} message is missing {:?} or sets its type", self.payload.msg_type.name, missing);
+            return self.invalid(error::INVALID_MSG_PAYLOAD.code_num);
+        }
+
+        self.payload.fields = fields;
+        self
+    }
+
+    // keeps the first error, like the other builders the message reports it at serialize
+    fn invalid(&mut self, rc: u32) -> &mut Self {
+        if self.header.validate_rc == error::SUCCESS.code_num {
+            self.header.validate_rc = rc;
+        }
+        self
+    }
+}
+
+impl GeneralMessage for CustomMessage {
+    type Msg = CustomMessage;
+    type Response = Map<String, Value>;
+    type Payload = CustomPayload;
+
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
+    }
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
+    }
+
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
+    }
+}
+
+/// Sends a message of a registered type over the pairwise relationship of the connection and
+/// returns the agency's response as json.
+pub fn send(connection_handle: u32, msg_type: &str, fields: &str) -> Result<String, u32> {
+    if !connection::is_valid_connection_handle(connection_handle) {
+        return Err(error::INVALID_CONNECTION_HANDLE.code_num);
+    }
+
+    let to_did = connection::get_pw_did(connection_handle)?;
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+    let recipient = connection::get_recipient(connection_handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = CustomMessage::create(msg_type)
+        .to(&to_did)
+        .over(&recipient)
+        .fields(fields)
+        .pack(format)?;
+
+    match post_message(&msg, &url, format) {
+        Ok((response, format)) => {
+            let response = CustomMessage::unpack_response_over(&response, &recipient, format)?;
+            Ok(Value::Object(response).to_string())
+        },
+        Err(x) => {
+            warn!("could not send {} message: {}", msg_type, x);
+            Err(error::POST_MSG_FAILURE.code_num)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connection::tests::build_test_connection;
+
+    #[test]
+    fn test_register_msg_type() {
+        assert_eq!(register_msg_type("GET_MSGS", Vec::new()), Err(error::INVALID_MSG_TYPE.code_num));
+        assert_eq!(register_msg_type("bad type", Vec::new()), Err(error::INVALID_MSG_TYPE.code_num));
+        assert_eq!(register_msg_type("LOYALTY_POINTS", vec!["points".to_string()]), Ok(error::SUCCESS.code_num));
+        assert!(is_registered("LOYALTY_POINTS"));
+    }
+
+    #[test]
+    fn test_custom_message_serializes_fields() {
+        register_msg_type("SURVEY", vec!["question".to_string()]).unwrap();
+
+        let msg = CustomMessage::create("SURVEY")
+            .to("8XFh8yBzrpJQmNyZzgoTqB")
+            .fields("{\"question\":\"why?\",\"options\":[1,2]}")
+            .serialize_message().unwrap();
+        assert_eq!(msg, "{\"agentPayload\":\
+        \"{\\\"options\\\":[1,2],\\\"question\\\":\\\"why?\\\",\\\"type\\\":\\\"SURVEY\\\"}\",\
+        \"to\":\"8XFh8yBzrpJQmNyZzgoTqB\"}");
+
+        assert_eq!(CustomMessage::create("SURVEY").to("8XFh8yBzrpJQmNyZzgoTqB").fields("{\"options\":[]}").serialize_message(),
+                   Err(error::INVALID_MSG_PAYLOAD.code_num));
+        assert_eq!(CustomMessage::create("SURVEY").to("8XFh8yBzrpJQmNyZzgoTqB").fields("[1]").serialize_message(),
+                   Err(error::INVALID_MSG_PAYLOAD.code_num));
+        assert_eq!(CustomMessage::create("NOT_REGISTERED").to("8XFh8yBzrpJQmNyZzgoTqB").serialize_message(),
+                   Err(error::UNKNOWN_MSG_TYPE.code_num));
+    }
+
+    #[test]
+    fn test_send_custom_message() {
+        let handle = build_test_connection();
+        register_msg_type("PING", Vec::new()).unwrap();
+        assert_eq!(send(handle, "PING", "{}").unwrap(), "{}");
+        assert_eq!(send(0, "PING", "{}"), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+        connection::release(handle);
+    }
+}
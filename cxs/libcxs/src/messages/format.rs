@@ -0,0 +1,93 @@
+extern crate rmp_serde;
+extern crate serde_json;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use settings;
+use utils::error;
+
+pub static JSON_CONTENT_TYPE: &'static str = "application/json";
+pub static MSGPACK_CONTENT_TYPE: &'static str = "application/msgpack";
+
+/// Encoding of the envelope and payload of agency messages.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum MessageFormat {
+    Json,
+    MsgPack,
+}
+
+impl MessageFormat {
+    /// The format chosen with the message_format setting, json unless it is "msgpack".
+    pub fn from_settings() -> MessageFormat {
+        match settings::get_config_value(settings::CONFIG_MESSAGE_FORMAT) {
+            Ok(ref x) if x == "msgpack" => MessageFormat::MsgPack,
+            _ => MessageFormat::Json,
+        }
+    }
+
+    /// The format of a response with the given content type.  Agencies that don't answer in
+    /// msgpack answer in json, whatever they call it.
+    pub fn from_content_type(content_type: &str) -> MessageFormat {
+        if content_type.trim().starts_with(MSGPACK_CONTENT_TYPE) {
+            MessageFormat::MsgPack
+        } else {
+            MessageFormat::Json
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            MessageFormat::Json => JSON_CONTENT_TYPE,
+            MessageFormat::MsgPack => MSGPACK_CONTENT_TYPE,
+        }
+    }
+
+    pub fn pack<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, u32> {
+        match *self {
+            // going through a Value keeps json keys sorted, which the agency has always seen
+            MessageFormat::Json => Ok(json!(value).to_string().into_bytes()),
+            MessageFormat::MsgPack => match rmp_serde::to_vec_named(value) {
+                Ok(x) => Ok(x),
+                Err(x) => {
+                    warn!("could not pack message as msgpack: {}", x);
+                    Err(error::UNKNOWN_ERROR.code_num)
+                },
+            },
+        }
+    }
+
+    pub fn unpack<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, String> {
+        match *self {
+            MessageFormat::Json => serde_json::from_slice(data).map_err(|x| x.to_string()),
+            MessageFormat::MsgPack => rmp_serde::from_slice(data).map_err(|x| x.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let mut value = HashMap::new();
+        value.insert("uid".to_string(), "6gmsuWZ".to_string());
+
+        let json = MessageFormat::Json.pack(&value).unwrap();
+        assert_eq!(json, b"{\"uid\":\"6gmsuWZ\"}".to_vec());
+
+        let packed = MessageFormat::MsgPack.pack(&value).unwrap();
+        assert!(packed.len() < json.len());
+        assert_eq!(MessageFormat::MsgPack.unpack::<HashMap<String, String>>(&packed).unwrap(), value);
+        assert!(MessageFormat::MsgPack.unpack::<HashMap<String, String>>(&json).is_err());
+    }
+
+    #[test]
+    fn test_from_content_type() {
+        assert_eq!(MessageFormat::from_content_type("application/msgpack"), MessageFormat::MsgPack);
+        assert_eq!(MessageFormat::from_content_type("application/json; charset=utf-8"), MessageFormat::Json);
+        assert_eq!(MessageFormat::from_content_type("text/plain"), MessageFormat::Json);
+        assert_eq!(MessageFormat::from_content_type(""), MessageFormat::Json);
+    }
+}
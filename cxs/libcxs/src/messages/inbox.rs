@@ -0,0 +1,172 @@
+extern crate serde_json;
+
+use connection;
+use settings;
+use utils::error;
+use messages::format::MessageFormat;
+use messages::message::{GetMessages, Message};
+use messages::{GeneralMessage, get_messages, post_message};
+
+/// Which messages of a connection to list.  Empty fields match every message and a limit of
+/// 0 returns everything after offset.
+#[derive(Deserialize, Debug, Default, PartialEq, Clone)]
+#[serde(default)]
+pub struct InboxFilter {
+    pub msg_type: String,
+    pub status_code: String,
+    pub sender_did: String,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct InboxEntry {
+    pub uid: String,
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    #[serde(rename = "status")]
+    pub status_code: String,
+    #[serde(rename = "sender")]
+    pub sender_did: String,
+    #[serde(rename = "timestamp")]
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+}
+
+impl InboxEntry {
+    fn from_message(msg: Message, with_payload: bool) -> InboxEntry {
+        InboxEntry {
+            uid: msg.uid,
+            msg_type: msg.msg_type,
+            status_code: msg.status_code,
+            sender_did: msg.sender_did,
+            created_at: msg.created_at,
+            payload: if with_payload { Some(msg.edge_agent_payload) } else { None },
+        }
+    }
+}
+
+/// One page of an inbox.  total counts every message that matched the filter, so callers
+/// know when they have paged through all of them.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct InboxPage {
+    pub total: usize,
+    pub msgs: Vec<InboxEntry>,
+}
+
+// the agency filters by type and status, the sender and the paging are applied here
+fn page(mut msgs: Vec<Message>, filter: &InboxFilter) -> InboxPage {
+    msgs.retain(|x| filter.sender_did.is_empty() || x.sender_did == filter.sender_did);
+    msgs.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+    let total = msgs.len();
+    let limit = if filter.limit == 0 { total } else { filter.limit };
+    let msgs = msgs.into_iter()
+        .skip(filter.offset)
+        .take(limit)
+        .map(|x| InboxEntry::from_message(x, false))
+        .collect();
+
+    InboxPage { total: total, msgs: msgs }
+}
+
+fn fetch(connection_handle: u32, msg: &mut GetMessages) -> Result<Vec<Message>, u32> {
+    if !connection::is_valid_connection_handle(connection_handle) {
+        return Err(error::INVALID_CONNECTION_HANDLE.code_num);
+    }
+
+    let to_did = connection::get_pw_did(connection_handle)?;
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+    let recipient = connection::get_recipient(connection_handle)?;
+    let format = MessageFormat::from_settings();
+    let msg = msg.to(&to_did).over(&recipient).pack(format)?;
+
+    match post_message(&msg, &url, format) {
+        Ok((response, format)) => Ok(GetMessages::unpack_response_over(&response, &recipient, format)?.msgs),
+        Err(x) => {
+            warn!("could not get messages for connection {}: {}", connection_handle, x);
+            Err(error::POST_MSG_FAILURE.code_num)
+        },
+    }
+}
+
+/// Lists the messages of the connection that match filter, a json InboxFilter, oldest
+/// first.  Returns an InboxPage as json, without the message payloads.
+pub fn list(connection_handle: u32, filter: &str) -> Result<String, u32> {
+    let filter: InboxFilter = match serde_json::from_str(filter) {
+        Ok(x) => x,
+        Err(x) => {
+            warn!("invalid inbox filter: {}", x);
+            return Err(error::INVALID_OPTION.code_num);
+        },
+    };
+
+    let msgs = fetch(connection_handle, get_messages()
+        .msg_type(&filter.msg_type)
+        .status_code(&filter.status_code)
+        .include_edge_payload("N"))?;
+
+    Ok(json!(page(msgs, &filter)).to_string())
+}
+
+/// Fetches one message of the connection together with its payload, as json.
+pub fn get_message(connection_handle: u32, uid: &str) -> Result<String, u32> {
+    let msgs = fetch(connection_handle, get_messages()
+        .uid(uid)
+        .include_edge_payload("Y"))?;
+
+    match msgs.into_iter().find(|x| x.uid == uid) {
+        Some(msg) => Ok(json!(InboxEntry::from_message(msg, true)).to_string()),
+        None => {
+            warn!("connection {} has no message {}", connection_handle, uid);
+            Err(error::UNKNOWN_MSG_UID.code_num)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use messages::message::parse_messages;
+    use connection::tests::build_test_connection;
+
+    fn inbox_messages() -> Vec<Message> {
+        parse_messages("{\"msgs\":[\
+            {\"uid\":\"7hntvXA\",\"msgType\":\"proof\",\"statusCode\":\"MS-103\",\"senderDID\":\"KkTVEE7RGg7z2d2hrfM2Hj\",\
+             \"createdAt\":\"2017-11-02T10:07:00Z\",\"edgeAgentPayload\":\"{}\"},\
+            {\"uid\":\"6gmsuWZ\",\"msgType\":\"claimReq\",\"statusCode\":\"MS-104\",\"senderDID\":\"8XFh8yBzrpJQmNyZzgoTqB\",\
+             \"createdAt\":\"2017-11-02T10:05:14Z\"},\
+            {\"uid\":\"9kpvxYB\",\"msgType\":\"proof\",\"statusCode\":\"MS-103\",\"senderDID\":\"8XFh8yBzrpJQmNyZzgoTqB\",\
+             \"createdAt\":\"2017-11-02T10:09:30Z\"}]}").unwrap()
+    }
+
+    #[test]
+    fn test_page_filters_and_orders_messages() {
+        let filter = InboxFilter { sender_did: "8XFh8yBzrpJQmNyZzgoTqB".to_string(), ..Default::default() };
+        let inbox = page(inbox_messages(), &filter);
+        assert_eq!(inbox.total, 2);
+        assert_eq!(inbox.msgs[0].uid, "6gmsuWZ");
+        assert_eq!(inbox.msgs[1].uid, "9kpvxYB");
+        assert_eq!(inbox.msgs[1].payload, None);
+
+        let filter = InboxFilter { offset: 1, limit: 1, ..Default::default() };
+        let inbox = page(inbox_messages(), &filter);
+        assert_eq!(inbox.total, 3);
+        assert_eq!(inbox.msgs.len(), 1);
+        assert_eq!(inbox.msgs[0].uid, "7hntvXA");
+        assert_eq!(json!(inbox.msgs[0]).to_string(), "{\"sender\":\"KkTVEE7RGg7z2d2hrfM2Hj\",\"status\":\"MS-103\",\
+            \"timestamp\":\"2017-11-02T10:07:00Z\",\"type\":\"proof\",\"uid\":\"7hntvXA\"}");
+    }
+
+    #[test]
+    fn test_list_and_get_message() {
+        let handle = build_test_connection();
+        assert_eq!(list(handle, "{\"msg_type\":\"proof\",\"limit\":10}").unwrap(), "{\"msgs\":[],\"total\":0}");
+        assert_eq!(list(handle, "{\"limit\":\"ten\"}"), Err(error::INVALID_OPTION.code_num));
+        assert_eq!(list(0, "{}"), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+        assert_eq!(get_message(handle, "6gmsuWZ"), Err(error::UNKNOWN_MSG_UID.code_num));
+        connection::release(handle);
+    }
+}
@@ -1,117 +1,234 @@
 extern crate rust_base58;
 extern crate serde_json;
+extern crate rmp_serde;
 
+use self::rust_base58::{ToBase58, FromBase58};
+use url::Url;
+use utils::crypto;
 use utils::error;
 use messages::validation;
-use messages::GeneralMessage;
+use messages::version::MsgType;
+use messages::{GeneralMessage, MessageHeader, Recipient};
 
 
 #[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
 #[serde(rename_all = "camelCase")]
-struct CreateKeyPayload{
+pub struct CreateKeyPayload{
     #[serde(rename = "type")]
-    msg_type: String,
+    msg_type: MsgType,
     for_did: String,
     #[serde(rename = "forDIDVerKey")]
     for_verkey: String,
     nonce: String,
 }
 
-#[derive(Serialize, Debug, PartialEq, PartialOrd, Clone)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct CreateKeyMsg {
-    #[serde(rename = "to")]
-    to_did: String,
-    agent_payload: String,
-    #[serde(skip_serializing, default)]
+    header: MessageHeader,
     payload: CreateKeyPayload,
-    #[serde(skip_serializing, default)]
-    validate_rc: u32,
 }
 
 #[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
 #[serde(rename_all = "camelCase")]
-struct UpdateProfileDataPayload{
+pub struct UpdateProfileDataPayload{
     #[serde(rename = "type")]
-    msg_type: String,
+    msg_type: MsgType,
     name: String,
     logo_url: String,
 }
 
-#[derive(Serialize, Debug, PartialEq, PartialOrd, Clone)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct UpdateProfileData {
-    #[serde(rename = "to")]
-    to_did: String,
-    agent_payload: String,
-    #[serde(skip_serializing, default)]
+    header: MessageHeader,
     payload: UpdateProfileDataPayload,
-    #[serde(skip_serializing, default)]
-    validate_rc: u32,
 }
 
 #[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
 #[serde(rename_all = "camelCase")]
-struct SendInvitePayload{
+pub struct SendInvitePayload{
     #[serde(rename = "type")]
-    msg_type: String,
+    msg_type: MsgType,
     #[serde(rename = "keyDlgProof")]
-    key_delegate: String,
+    key_delegate: KeyDlgProof,
     phone_number: String,
 }
 
-#[derive(Serialize, Debug, PartialEq, PartialOrd, Clone)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct SendInvite {
-    #[serde(rename = "to")]
-    to_did: String,
-    agent_payload: String,
-    #[serde(skip_serializing, default)]
+    header: MessageHeader,
     payload: SendInvitePayload,
-    #[serde(skip_serializing, default)]
-    validate_rc: u32,
 }
 
 #[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
 #[serde(rename_all = "camelCase")]
 pub struct AcceptInvitationPayload {
     #[serde(rename = "type")]
-    msg_type: String,
+    msg_type: MsgType,
     msg_uid: String,
     enterprise_name: String,
     logo_url: String,
     sender_did: String,
     sender_verkey: String,
-    key_delegate: String,
+    key_delegate: KeyDlgProof,
     remote_endpoint: String,
     push_com_method: String,
 }
 
-#[derive(Serialize, Debug, PartialEq, PartialOrd, Clone)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct AcceptInvitation {
-    #[serde(rename = "to")]
-    to_did: String,
-    agent_payload: String,
-    #[serde(skip_serializing, default)]
+    header: MessageHeader,
     payload: AcceptInvitationPayload,
-    #[serde(skip_serializing, default)]
-    validate_rc: u32,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct CreateKeyResponse {
+    #[serde(rename = "withPairwiseDID", default)]
+    pub for_did: String,
+    #[serde(rename = "withPairwiseDIDVerKey", default)]
+    pub for_verkey: String,
+}
+
+/// Proof that an agent's key may act for a pairwise did: the signature of the pairwise key
+/// over the agent's did and verkey, base58 encoded.
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Default)]
+#[serde(default)]
+pub struct KeyDlgProof {
+    #[serde(rename = "agentDID")]
+    pub agent_did: String,
+    #[serde(rename = "agentDelegatedKey")]
+    pub agent_delegated_key: String,
+    pub signature: String,
+}
+
+impl KeyDlgProof {
+    /// Signs the agent's did and verkey with the key of pairwise_did.  Fails while the agent is
+    /// not known yet, a proof over empty keys would delegate to nobody.
+    pub fn create(pairwise_did: &str, agent_did: &str, agent_verkey: &str) -> Result<KeyDlgProof, u32> {
+        if agent_did.is_empty() || agent_verkey.is_empty() {
+            warn!("no agent to delegate the key of {} to", pairwise_did);
+            return Err(error::INVALID_KEY_DELEGATE.code_num);
+        }
+        let mut proof = KeyDlgProof {
+            agent_did: agent_did.to_string(),
+            agent_delegated_key: agent_verkey.to_string(),
+            signature: String::new(),
+        };
+        proof.signature = crypto::sign(pairwise_did, &proof.signed_data())?.to_base58();
+        Ok(proof)
+    }
+
+    /// The did is length prefixed so no other did and verkey pair signs the same bytes.
+    pub fn signed_data(&self) -> Vec<u8> {
+        format!("{}:{}{}", self.agent_did.len(), self.agent_did, self.agent_delegated_key).into_bytes()
+    }
+}
+
+/// An invitation as the agency describes it in the SEND_INVITE response, and as an invitee
+/// receives it.  expires_at is in seconds since the epoch, 0 when it doesn't expire.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InviteDetail {
+    pub conn_req_id: String,
+    pub sender_name: String,
+    #[serde(rename = "senderDID")]
+    pub sender_did: String,
+    #[serde(rename = "senderDIDVerKey")]
+    pub sender_verkey: String,
+    pub sender_logo_url: String,
+    pub sender_endpoint: String,
+    pub sender_agent_key_dlg_proof: KeyDlgProof,
+    pub target_name: String,
+    pub status_code: String,
+    pub status_msg: String,
+    pub expires_at: u64,
+}
+
+// the invitation travels in this query parameter of deep links
+static DEEP_LINK_PARAM: &'static str = "c";
+
+impl InviteDetail {
+    pub fn from_str(invite_detail: &str) -> Result<InviteDetail, u32> {
+        match serde_json::from_str(invite_detail) {
+            Ok(x) => Ok(x),
+            Err(x) => {
+                warn!("invalid invite details: {}", x);
+                Err(error::INVALID_INVITE_DETAILS.code_num)
+            },
+        }
+    }
+
+    /// A link to base_url that carries the whole invitation, packed as msgpack with its field
+    /// names and base58 encoded, so it needs no call to the agency and survives fields being
+    /// added to or reordered in InviteDetail.
+    pub fn deep_link(&self, base_url: &str) -> Result<String, u32> {
+        let packed = match rmp_serde::to_vec_named(self) {
+            Ok(x) => x,
+            Err(x) => {
+                warn!("could not pack invite details {}: {}", self.conn_req_id, x);
+                return Err(error::INVALID_INVITE_DETAILS.code_num);
+            },
+        };
+
+        let mut url = match Url::parse(base_url) {
+            Ok(x) => x,
+            Err(_) => return Err(error::INVALID_URL.code_num),
+        };
+        url.query_pairs_mut().append_pair(DEEP_LINK_PARAM, &packed.to_base58());
+        Ok(url.into_string())
+    }
+
+    pub fn from_deep_link(link: &str) -> Result<InviteDetail, u32> {
+        let url = match Url::parse(link) {
+            Ok(x) => x,
+            Err(_) => return Err(error::INVALID_URL.code_num),
+        };
+
+        let packed = url.query_pairs()
+            .find(|&(ref key, _)| key == DEEP_LINK_PARAM)
+            .and_then(|(_, value)| value.from_base58().ok());
+
+        match packed.and_then(|x| rmp_serde::from_slice(&x).ok()) {
+            Some(x) => Ok(x),
+            None => {
+                warn!("link {} does not carry an invitation", link);
+                Err(error::INVALID_INVITE_DETAILS.code_num)
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SendInviteResponse {
+    #[serde(default)]
+    pub invite_detail: Option<InviteDetail>,
+    #[serde(default)]
+    pub url_to_invite_detail: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct UpdateProfileDataResponse {
+    #[serde(rename = "type", default)]
+    pub msg_type: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct AcceptInvitationResponse {
+    #[serde(default)]
+    pub uid: String,
 }
 
 impl CreateKeyMsg{
 
     pub fn create() -> CreateKeyMsg {
         CreateKeyMsg {
-            to_did: String::new(),
+            header: MessageHeader::new(),
             payload: CreateKeyPayload{
-                msg_type: "CREATE_KEY".to_string(),
+                msg_type: MsgType::new("CREATE_KEY"),
                 for_did: String::new(),
                 for_verkey: String::new(),
                 nonce: String::new(),
             },
-            agent_payload: String::new(),
-            validate_rc: error::SUCCESS.code_num,
         }
     }
 
@@ -122,7 +239,7 @@ impl CreateKeyMsg{
                 self
             },
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             },
         }
@@ -135,7 +252,7 @@ impl CreateKeyMsg{
                 self
             },
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             },
         }
@@ -148,31 +265,30 @@ impl CreateKeyMsg{
                 self
             },
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             },
         }
     }
 }
 
-//Todo: Every GeneralMessage extension, duplicates code
 impl GeneralMessage for CreateKeyMsg  {
     type Msg = CreateKeyMsg;
+    type Response = CreateKeyResponse;
+    type Payload = CreateKeyPayload;
 
-    fn set_to_did(&mut self, to_did: String){
-        self.to_did = to_did;
+    fn recipient() -> Recipient { Recipient::Agency }
+
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
     }
 
-    fn set_validate_rc(&mut self, rc: u32){
-        self.validate_rc = rc;
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
     }
 
-    fn serialize_message(&mut self) -> Result<String, u32> {
-        if self.validate_rc != error::SUCCESS.code_num {
-            return Err(self.validate_rc)
-        }
-        self.agent_payload = json!(self.payload).to_string();
-        Ok(json!(self).to_string())
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
     }
 }
 
@@ -180,28 +296,18 @@ impl SendInvite{
 
     pub fn create() -> SendInvite {
         SendInvite {
-            to_did: String::new(),
+            header: MessageHeader::new(),
             payload: SendInvitePayload{
-                msg_type: "SEND_INVITE".to_string(),
-                key_delegate: String::new(),
+                msg_type: MsgType::new("SEND_INVITE"),
+                key_delegate: KeyDlgProof::default(),
                 phone_number: String::new(),
             },
-            agent_payload: String::new(),
-            validate_rc: error::SUCCESS.code_num,
         }
     }
 
-    pub fn key_delegate(&mut self, key: &str) -> &mut Self{
-        match validation::validate_key_delegate(key){
-            Ok(x) => {
-                self.payload.key_delegate = x;
-                self
-            }
-            Err(x) => {
-                self.validate_rc = x;
-                self
-            }
-        }
+    pub fn key_delegate(&mut self, proof: &KeyDlgProof) -> &mut Self{
+        self.payload.key_delegate = proof.clone();
+        self
     }
 
     pub fn phone_number(&mut self, p_num: &str)-> &mut Self{
@@ -211,30 +317,28 @@ impl SendInvite{
                  self
             }
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             }
         }
     }
 }
 
-//Todo: Every GeneralMessage extension, duplicates code
 impl GeneralMessage for SendInvite{
     type Msg = SendInvite;
+    type Response = SendInviteResponse;
+    type Payload = SendInvitePayload;
 
-    fn set_to_did(&mut self, to_did: String){
-        self.to_did = to_did;
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
     }
-    fn set_validate_rc(&mut self, rc: u32){
-        self.validate_rc = rc;
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
     }
 
-    fn serialize_message(&mut self) -> Result<String, u32> {
-        if self.validate_rc != error::SUCCESS.code_num {
-            return Err(self.validate_rc)
-        }
-        self.agent_payload = json!(self.payload).to_string();
-        Ok(json!(self).to_string())
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
     }
 
     }
@@ -243,14 +347,12 @@ impl UpdateProfileData{
 
     pub fn create() -> UpdateProfileData {
         UpdateProfileData {
-            to_did: String::new(),
+            header: MessageHeader::new(),
             payload: UpdateProfileDataPayload{
-                msg_type: "UPDATE_PROFILE_DATA".to_string(),
+                msg_type: MsgType::new("UPDATE_PROFILE_DATA"),
                 name: String::new(),
                 logo_url: String::new(),
             },
-            agent_payload: String::new(),
-            validate_rc: error::SUCCESS.code_num,
         }
     }
 
@@ -266,30 +368,28 @@ impl UpdateProfileData{
                 self
             }
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             }
         }
     }
 }
 
-//Todo: Every GeneralMessage extension, duplicates code
 impl GeneralMessage for UpdateProfileData{
     type Msg = UpdateProfileData;
+    type Response = UpdateProfileDataResponse;
+    type Payload = UpdateProfileDataPayload;
 
-    fn set_to_did(&mut self, to_did: String){
-        self.to_did = to_did;
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
     }
-    fn set_validate_rc(&mut self, rc: u32){
-        self.validate_rc = rc;
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
     }
 
-    fn serialize_message(&mut self) -> Result<String, u32> {
-        if self.validate_rc != error::SUCCESS.code_num {
-            return Err(self.validate_rc)
-        }
-        self.agent_payload = json!(self.payload).to_string();
-        Ok(json!(self).to_string())
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
     }
 
 }
@@ -298,20 +398,18 @@ impl AcceptInvitation{
 
     pub fn create() -> AcceptInvitation {
         AcceptInvitation {
-            to_did: String::new(),
+            header: MessageHeader::new(),
             payload: AcceptInvitationPayload{
-                msg_type: "INVITE_ANSWERED".to_string(),
+                msg_type: MsgType::new("INVITE_ANSWERED"),
                 msg_uid: String::new(),
                 enterprise_name: String::new(),
                 logo_url: String::new(),
                 sender_did: String::new(),
                 sender_verkey: String::new(),
-                key_delegate: String::new(),
+                key_delegate: KeyDlgProof::default(),
                 remote_endpoint: String::new(),
                 push_com_method: String::new(),
             },
-            agent_payload: String::new(),
-            validate_rc: error::SUCCESS.code_num,
         }
     }
 
@@ -333,7 +431,7 @@ impl AcceptInvitation{
                 self
             }
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             }
         }
@@ -346,7 +444,7 @@ impl AcceptInvitation{
                 self
             },
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             },
         }
@@ -359,23 +457,15 @@ impl AcceptInvitation{
                 self
             },
             Err(x) => {
-                self.validate_rc = x;
+                self.header.validate_rc = x;
                 self
             },
         }
     }
 
-    pub fn key_delegate(&mut self, key: &str) -> &mut Self {
-        match validation::validate_key_delegate(key){
-            Ok(x) => {
-                self.payload.key_delegate = x;
-                self
-            }
-            Err(x) => {
-                self.validate_rc = x;
-                self
-            }
-        }
+    pub fn key_delegate(&mut self, proof: &KeyDlgProof) -> &mut Self {
+        self.payload.key_delegate = proof.clone();
+        self
     }
 
     pub fn remote_endpoint(&mut self, endpoint: &str) -> &mut Self {
@@ -391,23 +481,21 @@ impl AcceptInvitation{
     }
 }
 
-//Todo: Every GeneralMessage extension, duplicates code
 impl GeneralMessage for AcceptInvitation{
     type Msg = AcceptInvitation;
+    type Response = AcceptInvitationResponse;
+    type Payload = AcceptInvitationPayload;
 
-    fn set_to_did(&mut self, to_did: String){
-        self.to_did = to_did;
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
     }
-    fn set_validate_rc(&mut self, rc: u32){
-        self.validate_rc = rc;
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
     }
 
-    fn serialize_message(&mut self) -> Result<String, u32> {
-        if self.validate_rc != error::SUCCESS.code_num {
-            return Err(self.validate_rc)
-        }
-        self.agent_payload = json!(self.payload).to_string();
-        Ok(json!(self).to_string())
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
     }
 }
 
@@ -422,7 +510,7 @@ mod tests {
         let msg_payload = CreateKeyPayload{
             for_did: String::new(),
             for_verkey: String::new(),
-            msg_type: "CREATE_KEY".to_string(),
+            msg_type: MsgType::new("CREATE_KEY"),
             nonce: String::new(),
         };
         assert_eq!(msg.payload, msg_payload);
@@ -437,7 +525,7 @@ mod tests {
         let msg_payload = CreateKeyPayload{
             for_did: for_did.to_string(),
             for_verkey: for_verkey.to_string(),
-            msg_type: "CREATE_KEY".to_string(),
+            msg_type: MsgType::new("CREATE_KEY"),
             nonce: nonce.to_string(),
         };
         let msg = create_keys()
@@ -473,7 +561,11 @@ mod tests {
     fn test_send_invite_set_values_and_serialize(){
         let to_did = "8XFh8yBzrpJQmNyZzgoTqB";
         let phone = "phone";
-        let key = "key";
+        let key = KeyDlgProof {
+            agent_did: "99Fh8yBzrpJQmNyZzgoTqB".to_string(),
+            agent_delegated_key: "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A".to_string(),
+            signature: "sig".to_string(),
+        };
         let msg = send_invite()
             .to(to_did)
             .phone_number(&phone)
@@ -481,7 +573,9 @@ mod tests {
             .serialize_message().unwrap();
 
         assert_eq!(msg, "{\"agentPayload\":\
-        \"{\\\"keyDlgProof\\\":\\\"key\\\",\
+        \"{\\\"keyDlgProof\\\":{\\\"agentDID\\\":\\\"99Fh8yBzrpJQmNyZzgoTqB\\\",\
+            \\\"agentDelegatedKey\\\":\\\"EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A\\\",\
+            \\\"signature\\\":\\\"sig\\\"},\
             \\\"phoneNumber\\\":\\\"phone\\\",\
             \\\"type\\\":\\\"SEND_INVITE\\\"}\",\
         \"to\":\"8XFh8yBzrpJQmNyZzgoTqB\"}"
@@ -515,7 +609,7 @@ mod tests {
         let url = "https://random.com";
         let sender_did = "99Fh8yBzrpJQmNyZzgoTqB";
         let sender_verkey = "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A";
-        let key = "key";
+        let key = KeyDlgProof { agent_did: "KkTVEE7RGg7z2d2hrfM2Hj".to_string(), ..Default::default() };
         let endpoint = "https://??.com";
         let push_method = "push??";
         let msg = accept_invitation()
@@ -531,7 +625,7 @@ mod tests {
             .serialize_message().unwrap();
         assert_eq!(msg, "{\"agentPayload\":\
         \"{\\\"enterpriseName\\\":\\\"name\\\",\
-            \\\"keyDelegate\\\":\\\"key\\\",\
+            \\\"keyDelegate\\\":{\\\"agentDID\\\":\\\"KkTVEE7RGg7z2d2hrfM2Hj\\\",\\\"agentDelegatedKey\\\":\\\"\\\",\\\"signature\\\":\\\"\\\"},\
             \\\"logoUrl\\\":\\\"https://random.com\\\",\
             \\\"msgUid\\\":\\\"123\\\",\
             \\\"pushComMethod\\\":\\\"push??\\\",\
@@ -561,4 +655,47 @@ mod tests {
             Err(x) => assert_eq!(x, error::INVALID_DID.code_num)
         }
     }
+
+    #[test]
+    fn test_deserialize_responses(){
+        let key = CreateKeyMsg::deserialize_response("{\"withPairwiseDID\":\"8XFh8yBzrpJQmNyZzgoTqB\",\"withPairwiseDIDVerKey\":\"EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A\"}").unwrap();
+        assert_eq!(key.for_did, "8XFh8yBzrpJQmNyZzgoTqB");
+        assert_eq!(key.for_verkey, "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A");
+
+        let invite = SendInvite::deserialize_response("{\"inviteDetail\":{\"connReqId\":\"CXqcDCE\"},\"urlToInviteDetail\":\"http://localhost/CXqcDCE\"}").unwrap();
+        assert_eq!(invite.invite_detail.unwrap().conn_req_id, "CXqcDCE");
+        assert_eq!(invite.url_to_invite_detail, "http://localhost/CXqcDCE");
+
+        let profile = UpdateProfileData::deserialize_response("{\"type\":\"PROFILE_UPDATED\"}").unwrap();
+        assert_eq!(profile.msg_type, "PROFILE_UPDATED");
+
+        assert_eq!(AcceptInvitation::deserialize_response("{\"uid\":\"6gmsuWZ\"}").unwrap().uid, "6gmsuWZ");
+        assert_eq!(CreateKeyMsg::deserialize_response("message accepted"), Err(error::INVALID_HTTP_RESPONSE.code_num));
+    }
+
+    #[test]
+    fn test_invite_detail_deep_link(){
+        let invite = InviteDetail::from_str("{\"connReqId\":\"CXqcDCE\",\"senderName\":\"Evernym\",\
+            \"senderDID\":\"JiLBHundRhwYaMbPWno8Vg\",\"senderDIDVerKey\":\"AevwvcQBLv5CERRJShzUncV7ubapSgbDZxus42zS8fk1\",\
+            \"statusCode\":\"MS-102\",\"expiresAt\":1510000000}").unwrap();
+        assert_eq!(invite.sender_did, "JiLBHundRhwYaMbPWno8Vg");
+        assert_eq!(invite.expires_at, 1510000000);
+
+        let link = invite.deep_link("https://agency.example.com/invite").unwrap();
+        assert!(link.starts_with("https://agency.example.com/invite?c="));
+        assert_eq!(InviteDetail::from_deep_link(&link).unwrap(), invite);
+
+        // a link packed by field name decodes whatever order the fields come in, missing ones default
+        let fixed = InviteDetail::from_deep_link("https://agency.example.com/invite?c=ikeg37DevjioNsACP8pzKzKPRWimwf\
+            tENwxXfeMgFRcFguyaBKJdY9LMp5q89arc6ocGsQD5DcPom25qdxA6rMMfyhYToppfWnYG7FzWyci6EgA4Fe1D").unwrap();
+        assert_eq!(fixed.conn_req_id, "CXqcDCE");
+        assert_eq!(fixed.sender_did, "JiLBHundRhwYaMbPWno8Vg");
+        assert_eq!(fixed.status_code, "MS-102");
+        assert_eq!(fixed.expires_at, 1510000000);
+        assert_eq!(fixed.sender_name, "");
+
+        assert_eq!(InviteDetail::from_str("{\"connReqId\":1}"), Err(error::INVALID_INVITE_DETAILS.code_num));
+        assert_eq!(InviteDetail::from_deep_link("https://agency.example.com/invite?c=0OIl"), Err(error::INVALID_INVITE_DETAILS.code_num));
+        assert_eq!(invite.deep_link("not a url"), Err(error::INVALID_URL.code_num));
+    }
 }
@@ -2,13 +2,15 @@ extern crate rust_base58;
 extern crate serde_json;
 
 use utils::error;
-use messages::GeneralMessage;
+use messages::format::MessageFormat;
+use messages::version::MsgType;
+use messages::{GeneralMessage, MessageHeader, Recipient, decode_response};
 
 #[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
 #[serde(rename_all = "camelCase")]
-struct GetMessagesPayload{
+pub struct GetMessagesPayload{
     #[serde(rename = "type")]
-    msg_type: String,
+    msg_type: MsgType,
     #[serde(rename = "msgType")]
     message: String,
     uid: String,
@@ -16,32 +18,24 @@ struct GetMessagesPayload{
     include_edge_payload: String,
 }
 
-#[derive(Serialize, Debug, PartialEq, PartialOrd, Clone)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct GetMessages {
-    #[serde(rename = "to")]
-    to_did: String,
-    agent_payload: String,
-    #[serde(skip_serializing, default)]
+    header: MessageHeader,
     payload: GetMessagesPayload,
-    #[serde(skip_serializing, default)]
-    validate_rc: u32,
 }
 
 impl GetMessages{
 
     pub fn create() -> GetMessages {
         GetMessages {
-            to_did: String::new(),
+            header: MessageHeader::new(),
             payload: GetMessagesPayload{
-                msg_type: "GET_MSGS".to_string(),
+                msg_type: MsgType::new("GET_MSGS"),
                 message: String::new(),
                 uid: String::new(),
                 status_code: String::new(),
                 include_edge_payload: String::new(),
             },
-            agent_payload: String::new(),
-            validate_rc: error::SUCCESS.code_num,
         }
     }
 
@@ -71,23 +65,70 @@ impl GetMessages{
     }
 }
 
-//Todo: Every GeneralMessage extension, duplicates code
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    pub uid: String,
+    pub msg_type: String,
+    #[serde(default)]
+    pub status_code: String,
+    #[serde(default)]
+    pub edge_agent_payload: String,
+    #[serde(default)]
+    pub ref_msg_id: String,
+    #[serde(rename = "senderDID", default)]
+    pub sender_did: String,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+pub static MSG_STATUS_ACCEPTED: &'static str = "MS-104";
+pub static MSG_STATUS_REJECTED: &'static str = "MS-105";
+/// Status the SDK gives messages it has processed, so they aren't processed again.
+pub static MSG_STATUS_REVIEWED: &'static str = "MS-106";
+/// Status the agency gives connection requests that weren't answered in time.
+pub static MSG_STATUS_EXPIRED: &'static str = "MS-107";
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct GetMessagesResponse {
+    pub msgs: Vec<Message>,
+}
+
+/// Parses the list of messages returned by the agency for a GET_MSGS request.  Unlike
+/// GetMessages::deserialize_response this doesn't accept the test mode response.
+pub fn parse_messages(response: &str) -> Result<Vec<Message>, u32> {
+    unpack_messages(response.as_bytes(), &GetMessages::recipient(), MessageFormat::Json)
+}
+
+/// parse_messages for a response in the given format to a GET_MSGS request sent over the
+/// given relationship.
+pub fn unpack_messages(response: &[u8], recipient: &Recipient, format: MessageFormat) -> Result<Vec<Message>, u32> {
+    let response = decode_response(response, recipient, format)?;
+
+    match format.unpack::<GetMessagesResponse>(&response) {
+        Ok(x) => Ok(x.msgs),
+        Err(x) => {
+            warn!("could not parse messages from agency response: {}", x);
+            Err(error::INVALID_HTTP_RESPONSE.code_num)
+        },
+    }
+}
+
 impl GeneralMessage for GetMessages{
     type Msg = GetMessages;
+    type Response = GetMessagesResponse;
+    type Payload = GetMessagesPayload;
 
-    fn set_to_did(&mut self, to_did: String){
-        self.to_did = to_did;
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
     }
-    fn set_validate_rc(&mut self, rc: u32){
-        self.validate_rc = rc;
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
     }
 
-    fn serialize_message(&mut self) -> Result<String, u32> {
-        if self.validate_rc != error::SUCCESS.code_num {
-            return Err(self.validate_rc)
-        }
-        self.agent_payload = json!(self.payload).to_string();
-        Ok(json!(self).to_string())
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
     }
 }
 
@@ -126,6 +167,34 @@ mod tests {
         \"to\":\"8XFh8yBzrpJQmNyZzgoTqB\"}");
     }
 
+    #[test]
+    fn test_get_messages_serializes_other_versions(){
+        let msg = get_messages()
+            .to("8XFh8yBzrpJQmNyZzgoTqB")
+            .version("2.0")
+            .serialize_message().unwrap();
+        assert!(msg.contains("\\\"type\\\":{\\\"name\\\":\\\"GET_MSGS\\\",\\\"ver\\\":\\\"2.0\\\"}"));
+        assert!(msg.ends_with("\"version\":\"2.0\"}"));
+
+        assert_eq!(get_messages().to("8XFh8yBzrpJQmNyZzgoTqB").version("0.1").serialize_message(),
+                   Err(error::UNSUPPORTED_PROTOCOL_VERSION.code_num));
+    }
+
+    #[test]
+    fn test_get_messages_packs_as_msgpack(){
+        let mut msg = get_messages();
+        msg.to("8XFh8yBzrpJQmNyZzgoTqB").msg_type("proof");
+        let packed = msg.pack(MessageFormat::MsgPack).unwrap();
+        assert!(packed.len() < msg.serialize_message().unwrap().len());
+
+        let response = MessageFormat::MsgPack.pack(&json!({"msgs": [
+            {"uid": "6gmsuWZ", "msgType": "proof", "statusCode": "MS-103"}
+        ]})).unwrap();
+        let msgs = unpack_messages(&response, &Recipient::Agent, MessageFormat::MsgPack).unwrap();
+        assert_eq!(msgs[0].uid, "6gmsuWZ");
+        assert_eq!(unpack_messages(b"{\"msgs\":[]}", &Recipient::Agent, MessageFormat::MsgPack), Err(error::INVALID_HTTP_RESPONSE.code_num));
+    }
+
     #[test]
     fn test_get_messages_set_invalid_did_errors_at_serialize(){
         let to_did = "A";
@@ -143,4 +212,19 @@ mod tests {
             Err(x) => assert_eq!(x, error::INVALID_DID.code_num)
         }
     }
+
+    #[test]
+    fn test_parse_messages(){
+        let response = "{\"msgs\":[{\"uid\":\"6gmsuWZ\",\"msgType\":\"proof\",\"statusCode\":\"MS-103\",\
+            \"edgeAgentPayload\":\"{}\",\"refMsgId\":\"123\",\"senderDID\":\"8XFh8yBzrpJQmNyZzgoTqB\",\
+            \"createdAt\":\"2017-11-02T10:05:14Z\"},{\"uid\":\"7hntvXA\",\"msgType\":\"connReq\"}]}";
+        let msgs = parse_messages(response).unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].msg_type, "proof");
+        assert_eq!(msgs[0].ref_msg_id, "123");
+        assert_eq!(msgs[0].sender_did, "8XFh8yBzrpJQmNyZzgoTqB");
+        assert_eq!(msgs[1].edge_agent_payload, "");
+        assert_eq!(msgs[1].created_at, "");
+        assert_eq!(parse_messages("test_mode_response"), Err(error::INVALID_HTTP_RESPONSE.code_num));
+    }
 }
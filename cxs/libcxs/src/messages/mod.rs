@@ -1,11 +1,34 @@
 pub mod invite;
 pub mod validation;
 pub mod message;
+pub mod send_message;
+pub mod version;
+pub mod format;
+pub mod custom;
+pub mod inbox;
+pub mod update_message;
+pub mod update_connection;
 
+extern crate rust_base58;
+extern crate serde_json;
+
+use self::rust_base58::{ToBase58, FromBase58};
+use serde::{Serialize, Serializer};
+use serde::de::DeserializeOwned;
+use settings;
+use utils::crypto;
+use utils::error;
+use utils::httpclient;
 use self::invite::{CreateKeyMsg, SendInvite, AcceptInvitation, UpdateProfileData};
 use self::message::{GetMessages};
+use self::send_message::{SendMessage};
+use self::custom::CustomMessage;
+use self::update_message::UpdateMessageStatus;
+use self::update_connection::UpdateConnectionStatus;
+use self::format::MessageFormat;
+use self::version::MsgType;
 
-#[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum MessageType {
     EmptyPayload{},
     CreateKeyMsg(CreateKeyMsg),
@@ -13,14 +36,184 @@ pub enum MessageType {
     AcceptInviteMsg(AcceptInvitation),
     UpdateInfoMsg(UpdateProfileData),
     GetMessagesMsg(GetMessages),
+    SendMessageMsg(SendMessage),
+    UpdateMessageStatusMsg(UpdateMessageStatus),
+    UpdateConnectionStatusMsg(UpdateConnectionStatus),
+}
+
+/// Which of the enterprise's pairwise relationships a message travels over.  Keys are
+/// created with the agency, everything else is handled by the enterprise's agent.  Messages
+/// about a connection travel over the pairwise did of the connection and the agent's key
+/// for it.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub enum Recipient {
+    Agency,
+    Agent,
+    Connection {
+        pw_did: String,
+        agent_did: String,
+        agent_verkey: String,
+    },
+}
+
+impl Recipient {
+    // (enterprise did, their did, their verkey) config keys for the relationship
+    fn config_keys(&self) -> (&'static str, &'static str, &'static str) {
+        match *self {
+            Recipient::Agency => (settings::CONFIG_ENTERPRISE_DID_AGENCY,
+                                  settings::CONFIG_AGENCY_PAIRWISE_DID,
+                                  settings::CONFIG_AGENCY_PAIRWISE_VERKEY),
+            _ => (settings::CONFIG_ENTERPRISE_DID_AGENT,
+                  settings::CONFIG_AGENT_PAIRWISE_DID,
+                  settings::CONFIG_AGENT_PAIRWISE_VERKEY),
+        }
+    }
+
+    fn dids(&self) -> Result<(String, String, String), u32> {
+        let (my_did, their_did, their_verkey) = self.config_keys();
+        match *self {
+            Recipient::Connection { ref pw_did, ref agent_did, ref agent_verkey } if !agent_did.is_empty() =>
+                Ok((pw_did.clone(), agent_did.clone(), agent_verkey.clone())),
+            // until the agent created its key for the connection, the enterprise's agent answers
+            Recipient::Connection { ref pw_did, .. } =>
+                Ok((pw_did.clone(),
+                    settings::get_config_value(their_did)?,
+                    settings::get_config_value(their_verkey)?)),
+            _ => Ok((settings::get_config_value(my_did)?,
+                     settings::get_config_value(their_did)?,
+                     settings::get_config_value(their_verkey)?)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct EncryptedPayload {
+    payload: String,
+    nonce: String,
+}
+
+/// Builds the agentPayload for a message.  When encryption is turned on the payload is
+/// encrypted to the recipient's verkey and authenticated with the enterprise's pairwise key.
+pub fn encode_payload<T: Serialize>(payload: &T, recipient: &Recipient, format: MessageFormat) -> Result<Vec<u8>, u32> {
+    let payload = format.pack(payload)?;
+    if !settings::encryption_enabled() { return Ok(payload); }
+
+    encrypt_payload(&payload, recipient, format)
+}
+
+fn encrypt_payload(payload: &[u8], recipient: &Recipient, format: MessageFormat) -> Result<Vec<u8>, u32> {
+    let (my_did, their_did, their_verkey) = recipient.dids()?;
+    crypto::store_their_did(&their_did, &their_verkey)?;
+    let (encrypted, nonce) = crypto::encrypt(&my_did, &their_did, payload)?;
+
+    format.pack(&EncryptedPayload {
+        payload: encrypted.to_base58(),
+        nonce: nonce.to_base58(),
+    })
+}
+
+/// Reverses encode_payload for a response from the recipient.  Responses that don't decrypt
+/// with the recipient's key are rejected.
+pub fn decode_response(response: &[u8], recipient: &Recipient, format: MessageFormat) -> Result<Vec<u8>, u32> {
+    if !settings::encryption_enabled() { return Ok(response.to_vec()); }
+
+    decrypt_response(response, recipient, format)
+}
+
+fn decrypt_response(response: &[u8], recipient: &Recipient, format: MessageFormat) -> Result<Vec<u8>, u32> {
+    let encrypted: EncryptedPayload = match format.unpack(response) {
+        Ok(x) => x,
+        Err(x) => {
+            warn!("agency response is not encrypted: {}", x);
+            return Err(error::INVALID_ENCRYPTED_RESPONSE.code_num)
+        },
+    };
+
+    let (payload, nonce) = match (encrypted.payload.from_base58(), encrypted.nonce.from_base58()) {
+        (Ok(payload), Ok(nonce)) => (payload, nonce),
+        _ => return Err(error::INVALID_ENCRYPTED_RESPONSE.code_num),
+    };
+
+    let (my_did, their_did, _) = recipient.dids()?;
+    match crypto::decrypt(&my_did, &their_did, &payload, &nonce) {
+        Ok(x) => Ok(x),
+        Err(x) => {
+            warn!("could not decrypt agency response from {}: {}", their_did, x);
+            Err(error::INVALID_ENCRYPTED_RESPONSE.code_num)
+        },
+    }
+}
+
+// json carries the agentPayload as a string, msgpack as raw bytes
+struct AgentPayload {
+    data: Vec<u8>,
+    format: MessageFormat,
+}
+
+impl Serialize for AgentPayload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        match self.format {
+            MessageFormat::Json => serializer.serialize_str(&String::from_utf8_lossy(&self.data)),
+            MessageFormat::MsgPack => serializer.serialize_bytes(&self.data),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope<'a> {
+    agent_payload: AgentPayload,
+    to: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'a str>,
+}
+
+/// Wraps the payload of a message in the envelope that is posted to the agency.  The envelope
+/// only names a version when the message was built for one.
+pub fn pack_envelope<T: Serialize>(to_did: &str, version: Option<&str>, payload: &T, recipient: &Recipient, format: MessageFormat) -> Result<Vec<u8>, u32> {
+    let agent_payload = AgentPayload {
+        data: encode_payload(payload, recipient, format)?,
+        format: format,
+    };
+
+    format.pack(&Envelope { agent_payload: agent_payload, to: to_did, version: version })
+}
+
+/// The envelope fields every message has.  validate_rc keeps the error a builder method ran
+/// into, which is returned when the message is packed.  Without a recipient the message
+/// goes to the recipient of its type.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct MessageHeader {
+    pub to_did: String,
+    pub version: Option<String>,
+    pub validate_rc: u32,
+    pub recipient: Option<Recipient>,
+}
+
+impl MessageHeader {
+    pub fn new() -> MessageHeader {
+        MessageHeader {
+            to_did: String::new(),
+            version: version::agency_version(),
+            validate_rc: error::SUCCESS.code_num,
+            recipient: None,
+        }
+    }
+}
+
+/// Posts a packed message to the agency.  Returns the response together with the format it
+/// is in, which is taken from its content type rather than from the format of the message.
+pub fn post_message(msg: &[u8], url: &str, format: MessageFormat) -> Result<(Vec<u8>, MessageFormat), String> {
+    let (response, content_type) = httpclient::post_u8(msg, url, format.content_type())?;
+    Ok((response, MessageFormat::from_content_type(&content_type)))
 }
 
 pub trait GeneralMessage{
     type Msg;
+    type Response: DeserializeOwned + Default;
+    type Payload: Serialize;
 
-    //todo: add version
-    //todo: add encryption
-    //todo: deserialize_message
+    fn recipient() -> Recipient where Self: Sized { Recipient::Agent }
 
     fn to(&mut self, to_did: &str) -> &mut Self {
         match validation::validate_did(to_did){
@@ -35,10 +228,92 @@ pub trait GeneralMessage{
         }
     }
 
-    fn serialize_message(&mut self) -> Result<String, u32>;
-    fn set_to_did(&mut self, to_did: String);
-    fn set_validate_rc(&mut self, rc: u32);
+    /// Sends the message over the given relationship, e.g. the one of a connection, instead
+    /// of the one its type goes to.
+    fn over(&mut self, recipient: &Recipient) -> &mut Self {
+        self.header().recipient = Some(recipient.clone());
+        self
+    }
 
+    /// Builds the message for one of the SUPPORTED_VERSIONS instead of the version
+    /// negotiated with the agency.
+    fn version(&mut self, version: &str) -> &mut Self {
+        match version::validate_version(version){
+            Ok(x) => {
+                self.set_version(x);
+                self
+            },
+            Err(x) => {
+                self.set_validate_rc(x);
+                self
+            },
+        }
+    }
+
+    /// Parses the agency's json response to this message.
+    fn deserialize_response(response: &str) -> Result<Self::Response, u32> where Self: Sized {
+        Self::unpack_response(response.as_bytes(), MessageFormat::Json)
+    }
+
+    /// Parses the agency's response to this message, which is in the given format.
+    fn unpack_response(response: &[u8], format: MessageFormat) -> Result<Self::Response, u32> where Self: Sized {
+        Self::unpack_response_over(response, &Self::recipient(), format)
+    }
+
+    /// unpack_response for a message that was sent over the given relationship.  The canned
+    /// response that is posted back in test mode parses to an empty response.
+    fn unpack_response_over(response: &[u8], recipient: &Recipient, format: MessageFormat) -> Result<Self::Response, u32> where Self: Sized {
+        if settings::test_mode_enabled() && response == httpclient::TEST_MODE_RESPONSE.as_bytes() {
+            return Ok(Self::Response::default());
+        }
+
+        let response = decode_response(response, recipient, format)?;
+
+        match format.unpack(&response) {
+            Ok(x) => Ok(x),
+            Err(x) => {
+                warn!("could not parse agency response {}: {}", String::from_utf8_lossy(&response), x);
+                Err(error::INVALID_HTTP_RESPONSE.code_num)
+            },
+        }
+    }
+
+    /// The message as json text.
+    fn serialize_message(&mut self) -> Result<String, u32> where Self: Sized {
+        let msg = self.pack(MessageFormat::Json)?;
+        Ok(String::from_utf8(msg).unwrap_or_default())
+    }
+
+    fn pack(&mut self, format: MessageFormat) -> Result<Vec<u8>, u32> where Self: Sized {
+        if self.header().validate_rc != error::SUCCESS.code_num {
+            return Err(self.header().validate_rc)
+        }
+        self.validate_payload()?;
+
+        let header = self.header().clone();
+        let recipient = header.recipient.unwrap_or_else(Self::recipient);
+        pack_envelope(&header.to_did, header.version.as_ref().map(|x| x.as_str()), self.payload(), &recipient, format)
+    }
+
+    fn set_to_did(&mut self, to_did: String){
+        self.header().to_did = to_did;
+    }
+
+    fn set_validate_rc(&mut self, rc: u32){
+        self.header().validate_rc = rc;
+    }
+
+    fn set_version(&mut self, version: String){
+        self.payload_type().version = version.clone();
+        self.header().version = Some(version);
+    }
+
+    /// Checks the payload has everything the message type needs before it is packed.
+    fn validate_payload(&self) -> Result<(), u32> { Ok(()) }
+
+    fn header(&mut self) -> &mut MessageHeader;
+    fn payload_type(&mut self) -> &mut MsgType;
+    fn payload(&self) -> &Self::Payload;
 }
 
 
@@ -60,4 +335,88 @@ pub fn accept_invitation() -> AcceptInvitation{
 
 pub fn get_messages() -> GetMessages {
     GetMessages::create()
-}
\ No newline at end of file
+}
+
+pub fn send_message() -> SendMessage {
+    SendMessage::create()
+}
+
+pub fn update_message_status() -> UpdateMessageStatus {
+    UpdateMessageStatus::create()
+}
+
+pub fn update_connection_status() -> UpdateConnectionStatus {
+    UpdateConnectionStatus::create()
+}
+
+pub fn custom_message(msg_type: &str) -> CustomMessage {
+    CustomMessage::create(msg_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection_recipient() -> Recipient {
+        Recipient::Connection {
+            pw_did: "8XFh8yBzrpJQmNyZzgoTqB".to_string(),
+            agent_did: "KkTVEE7RGg7z2d2hrfM2Hj".to_string(),
+            agent_verkey: "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_connection_recipient_uses_connection_keys() {
+        let (my_did, their_did, their_verkey) = connection_recipient().dids().unwrap();
+        assert_eq!(my_did, "8XFh8yBzrpJQmNyZzgoTqB");
+        assert_eq!(their_did, "KkTVEE7RGg7z2d2hrfM2Hj");
+        assert_eq!(their_verkey, "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A");
+    }
+
+    #[test]
+    fn test_encrypted_payload_envelope() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let payload = MessageFormat::Json.pack(&json!({"type": "GET_MSGS"})).unwrap();
+
+        let encrypted = encrypt_payload(&payload, &connection_recipient(), MessageFormat::Json).unwrap();
+        let envelope: serde_json::Value = serde_json::from_slice(&encrypted).unwrap();
+        assert_eq!(envelope["payload"], json!(payload.to_base58()));
+        assert_eq!(envelope["nonce"], json!(crypto::NONCE_TEST_MODE.to_base58()));
+        assert_eq!(envelope.as_object().unwrap().len(), 2);
+
+        assert_eq!(decrypt_response(&encrypted, &connection_recipient(), MessageFormat::Json).unwrap(), payload);
+
+        let packed = encrypt_payload(&payload, &connection_recipient(), MessageFormat::MsgPack).unwrap();
+        assert_eq!(decrypt_response(&packed, &connection_recipient(), MessageFormat::MsgPack).unwrap(), payload);
+        assert_eq!(decrypt_response(&packed, &connection_recipient(), MessageFormat::Json),
+                   Err(error::INVALID_ENCRYPTED_RESPONSE.code_num));
+    }
+
+    #[test]
+    fn test_decrypt_rejects_unencrypted_response() {
+        assert_eq!(decrypt_response(b"{\"msgs\":[]}", &connection_recipient(), MessageFormat::Json),
+                   Err(error::INVALID_ENCRYPTED_RESPONSE.code_num));
+        assert_eq!(decrypt_response(b"test_mode_response", &Recipient::Agency, MessageFormat::Json),
+                   Err(error::INVALID_ENCRYPTED_RESPONSE.code_num));
+    }
+
+    #[test]
+    fn test_decrypt_rejects_undecodable_response() {
+        // 0, O, I and l aren't base58
+        let response = json!({"payload": "0OIl", "nonce": crypto::NONCE_TEST_MODE.to_base58()}).to_string();
+        assert_eq!(decrypt_response(response.as_bytes(), &connection_recipient(), MessageFormat::Json),
+                   Err(error::INVALID_ENCRYPTED_RESPONSE.code_num));
+
+        let response = json!({"payload": "3mJr7AoUXx2Wqd", "nonce": "0OIl"}).to_string();
+        assert_eq!(decrypt_response(response.as_bytes(), &connection_recipient(), MessageFormat::Json),
+                   Err(error::INVALID_ENCRYPTED_RESPONSE.code_num));
+    }
+
+    #[test]
+    fn test_over_sets_recipient_of_message() {
+        let mut msg = get_messages();
+        msg.to("8XFh8yBzrpJQmNyZzgoTqB").over(&connection_recipient());
+        assert_eq!(msg.header().recipient, Some(connection_recipient()));
+        assert!(get_messages().header().recipient.is_none());
+    }
+}
@@ -0,0 +1,144 @@
+extern crate serde_json;
+
+use messages::format::MessageFormat;
+use messages::version::MsgType;
+use messages::{GeneralMessage, MessageHeader, Recipient};
+use settings;
+use utils::error;
+
+#[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
+#[serde(rename_all = "camelCase")]
+pub struct SendMessagePayload{
+    #[serde(rename = "type")]
+    msg_type: MsgType,
+    #[serde(rename = "msgType")]
+    message: String,
+    status_code: String,
+    edge_agent_payload: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    ref_msg_id: String,
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct SendMessage {
+    header: MessageHeader,
+    payload: SendMessagePayload,
+}
+
+impl SendMessage{
+
+    pub fn create() -> SendMessage {
+        SendMessage {
+            header: MessageHeader::new(),
+            payload: SendMessagePayload{
+                msg_type: MsgType::new("SEND_MSG"),
+                message: String::new(),
+                status_code: String::new(),
+                edge_agent_payload: String::new(),
+                ref_msg_id: String::new(),
+            },
+        }
+    }
+
+    pub fn msg_type(&mut self, msg: &str) -> &mut Self{
+        //Todo: validate msg??
+        self.payload.message = msg.to_string();
+        self
+    }
+
+    pub fn status_code(&mut self, code: &str) -> &mut Self {
+        self.payload.status_code = code.to_string();
+        self
+    }
+
+    pub fn edge_agent_payload(&mut self, payload: &str) -> &mut Self {
+        //todo: is this a json value, String??
+        self.payload.edge_agent_payload = payload.to_string();
+        self
+    }
+
+    pub fn ref_msg_id(&mut self, id: &str) -> &mut Self {
+        self.payload.ref_msg_id = id.to_string();
+        self
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct SendMessageResponse {
+    #[serde(default)]
+    pub uid: String,
+}
+
+impl GeneralMessage for SendMessage{
+    type Msg = SendMessage;
+    type Response = SendMessageResponse;
+    type Payload = SendMessagePayload;
+
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
+    }
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
+    }
+
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
+    }
+}
+
+/// Returns the uid the agency assigned to a sent message.  Without one replies can't be
+/// matched to the message, so a response that doesn't carry it fails with
+/// INVALID_HTTP_RESPONSE; only the test mode response gives an empty uid.
+pub fn parse_msg_uid(response: &[u8], recipient: &Recipient, format: MessageFormat) -> Result<String, u32> {
+    match SendMessage::unpack_response_over(response, recipient, format) {
+        Ok(ref x) if !x.uid.is_empty() || settings::test_mode_enabled() => Ok(x.uid.clone()),
+        _ => {
+            warn!("send message response did not contain a uid: {}", String::from_utf8_lossy(response));
+            Err(error::INVALID_HTTP_RESPONSE.code_num)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use messages::send_message;
+
+    #[test]
+    fn test_send_message_set_values_and_serialize(){
+        let to_did = "8XFh8yBzrpJQmNyZzgoTqB";
+        let msg = send_message()
+            .to(&to_did)
+            .msg_type("proofReq")
+            .status_code("MS-101")
+            .edge_agent_payload("{\"name\":\"proof\"}")
+            .serialize_message().unwrap();
+        assert_eq!(msg, "{\"agentPayload\":\
+        \"{\\\"edgeAgentPayload\\\":\\\"{\\\\\\\"name\\\\\\\":\\\\\\\"proof\\\\\\\"}\\\",\
+            \\\"msgType\\\":\\\"proofReq\\\",\
+            \\\"statusCode\\\":\\\"MS-101\\\",\
+            \\\"type\\\":\\\"SEND_MSG\\\"}\",\
+        \"to\":\"8XFh8yBzrpJQmNyZzgoTqB\"}");
+    }
+
+    #[test]
+    fn test_send_message_set_invalid_did_errors_at_serialize(){
+        let mut msg = send_message()
+            .to("A")
+            .msg_type("proofReq").clone();
+
+        match msg.serialize_message(){
+            Ok(_) => panic!("should have had did error"),
+            Err(x) => assert_eq!(x, error::INVALID_DID.code_num)
+        }
+    }
+
+    #[test]
+    fn test_parse_msg_uid(){
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        assert_eq!(parse_msg_uid(b"{\"uid\":\"123\"}", &Recipient::Agent, MessageFormat::Json), Ok("123".to_string()));
+        assert_eq!(parse_msg_uid(b"test_mode_response", &Recipient::Agent, MessageFormat::Json), Ok(String::new()));
+        assert_eq!(parse_msg_uid(b"message accepted", &Recipient::Agent, MessageFormat::Json), Err(error::INVALID_HTTP_RESPONSE.code_num));
+    }
+}
@@ -0,0 +1,95 @@
+extern crate serde_json;
+
+use utils::error;
+use messages::version::MsgType;
+use messages::{GeneralMessage, MessageHeader};
+
+/// Status of a pairwise relationship the agent no longer serves.
+pub static CONN_STATUS_DELETED: &'static str = "CS-103";
+
+#[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateConnectionStatusPayload{
+    #[serde(rename = "type")]
+    msg_type: MsgType,
+    status_code: String,
+}
+
+/// Changes the status of the pairwise relationship at the agent, e.g. deleting it together
+/// with the keys created for it.
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct UpdateConnectionStatus {
+    header: MessageHeader,
+    payload: UpdateConnectionStatusPayload,
+}
+
+impl UpdateConnectionStatus{
+
+    pub fn create() -> UpdateConnectionStatus {
+        UpdateConnectionStatus {
+            header: MessageHeader::new(),
+            payload: UpdateConnectionStatusPayload{
+                msg_type: MsgType::new("UPDATE_CONN_STATUS"),
+                status_code: String::new(),
+            },
+        }
+    }
+
+    pub fn status_code(&mut self, code: &str) -> &mut Self {
+        self.payload.status_code = code.to_string();
+        self
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateConnectionStatusResponse {
+    #[serde(default)]
+    pub status_code: String,
+}
+
+impl GeneralMessage for UpdateConnectionStatus{
+    type Msg = UpdateConnectionStatus;
+    type Response = UpdateConnectionStatusResponse;
+    type Payload = UpdateConnectionStatusPayload;
+
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
+    }
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
+    }
+
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
+    }
+
+    fn validate_payload(&self) -> Result<(), u32> {
+        if self.payload.status_code.is_empty() {
+            warn!("connection status update needs a status code");
+            return Err(error::INVALID_OPTION.code_num)
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use messages::update_connection_status;
+
+    #[test]
+    fn test_update_connection_status_set_values_and_serialize(){
+        let msg = update_connection_status()
+            .to("8XFh8yBzrpJQmNyZzgoTqB")
+            .status_code(CONN_STATUS_DELETED)
+            .serialize_message().unwrap();
+        assert_eq!(msg, "{\"agentPayload\":\
+        \"{\\\"statusCode\\\":\\\"CS-103\\\",\\\"type\\\":\\\"UPDATE_CONN_STATUS\\\"}\",\
+        \"to\":\"8XFh8yBzrpJQmNyZzgoTqB\"}");
+
+        assert_eq!(update_connection_status().to("8XFh8yBzrpJQmNyZzgoTqB").serialize_message(),
+                   Err(error::INVALID_OPTION.code_num));
+    }
+}
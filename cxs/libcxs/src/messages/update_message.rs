@@ -0,0 +1,115 @@
+extern crate serde_json;
+
+use utils::error;
+use messages::version::MsgType;
+use messages::{GeneralMessage, MessageHeader};
+
+#[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMessageStatusPayload{
+    #[serde(rename = "type")]
+    msg_type: MsgType,
+    uids: Vec<String>,
+    status_code: String,
+}
+
+/// Changes the status of messages stored at the agent, so that messages which have been
+/// handled can be told apart from new ones.
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct UpdateMessageStatus {
+    header: MessageHeader,
+    payload: UpdateMessageStatusPayload,
+}
+
+impl UpdateMessageStatus{
+
+    pub fn create() -> UpdateMessageStatus {
+        UpdateMessageStatus {
+            header: MessageHeader::new(),
+            payload: UpdateMessageStatusPayload{
+                msg_type: MsgType::new("UPDATE_MSG_STATUS"),
+                uids: Vec::new(),
+                status_code: String::new(),
+            },
+        }
+    }
+
+    pub fn uid(&mut self, uid: &str) -> &mut Self {
+        self.payload.uids.push(uid.to_string());
+        self
+    }
+
+    pub fn uids(&mut self, uids: &[String]) -> &mut Self {
+        self.payload.uids.extend_from_slice(uids);
+        self
+    }
+
+    pub fn status_code(&mut self, code: &str) -> &mut Self {
+        self.payload.status_code = code.to_string();
+        self
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMessageStatusResponse {
+    #[serde(default)]
+    pub uids: Vec<String>,
+    #[serde(default)]
+    pub status_code: String,
+}
+
+impl GeneralMessage for UpdateMessageStatus{
+    type Msg = UpdateMessageStatus;
+    type Response = UpdateMessageStatusResponse;
+    type Payload = UpdateMessageStatusPayload;
+
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
+    }
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
+    }
+
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
+    }
+
+    fn validate_payload(&self) -> Result<(), u32> {
+        if self.payload.uids.is_empty() || self.payload.status_code.is_empty() {
+            warn!("message status update needs uids and a status code");
+            return Err(error::INVALID_MSG_STATUS.code_num)
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use messages::update_message_status;
+
+    #[test]
+    fn test_update_message_status_set_values_and_serialize(){
+        let msg = update_message_status()
+            .to("8XFh8yBzrpJQmNyZzgoTqB")
+            .uid("6gmsuWZ")
+            .uids(&vec!["7hntvXA".to_string()])
+            .status_code("MS-106")
+            .serialize_message().unwrap();
+        assert_eq!(msg, "{\"agentPayload\":\
+        \"{\\\"statusCode\\\":\\\"MS-106\\\",\
+            \\\"type\\\":\\\"UPDATE_MSG_STATUS\\\",\
+            \\\"uids\\\":[\\\"6gmsuWZ\\\",\\\"7hntvXA\\\"]}\",\
+        \"to\":\"8XFh8yBzrpJQmNyZzgoTqB\"}");
+    }
+
+    #[test]
+    fn test_update_message_status_needs_uids_and_status(){
+        assert_eq!(update_message_status().to("8XFh8yBzrpJQmNyZzgoTqB").status_code("MS-106").serialize_message(),
+                   Err(error::INVALID_MSG_STATUS.code_num));
+        assert_eq!(update_message_status().to("8XFh8yBzrpJQmNyZzgoTqB").uid("6gmsuWZ").serialize_message(),
+                   Err(error::INVALID_MSG_STATUS.code_num));
+    }
+}
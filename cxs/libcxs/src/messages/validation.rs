@@ -1,7 +1,9 @@
 extern crate rust_base58;
 
 use self::rust_base58::{FromBase58};
+use utils::crypto;
 use utils::error;
+use messages::invite::KeyDlgProof;
 use url::Url;
 
 pub fn validate_did(did: &str) -> Result<String, u32> {
@@ -30,10 +32,20 @@ pub fn validate_nonce(nonce: &str) -> Result<String, u32> {
     Ok(check_nonce)
 }
 
-pub fn validate_key_delegate(delegate: &str) -> Result<String, u32> {
-    //todo: find out what needs to be validated for key_delegate
-    let check_delegate = String::from(delegate);
-    Ok(check_delegate)
+/// Checks that the proof carries signer_did's signature over the agent's did and verkey.
+pub fn validate_key_delegate(proof: &KeyDlgProof, signer_did: &str) -> Result<KeyDlgProof, u32> {
+    let signature = match proof.signature.from_base58() {
+        Ok(x) => x,
+        Err(_) => return Err(error::INVALID_KEY_DELEGATE.code_num),
+    };
+
+    match crypto::verify(signer_did, &proof.signed_data(), &signature)? {
+        true => Ok(proof.clone()),
+        false => {
+            warn!("key delegation proof for agent {} was not signed by {}", proof.agent_did, signer_did);
+            Err(error::INVALID_KEY_DELEGATE.code_num)
+        },
+    }
 }
 
 pub fn validate_url(url: &str)->Result<String, u32>{
@@ -109,5 +121,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_key_delegate() {
+        ::settings::set_config_value(::settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let proof = KeyDlgProof::create("8XFh8yBzrpJQmNyZzgoTqB", "KkTVEE7RGg7z2d2hrfM2Hj",
+                                        "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A").unwrap();
+        assert_eq!(validate_key_delegate(&proof, "8XFh8yBzrpJQmNyZzgoTqB").unwrap(), proof);
+
+        let forged = KeyDlgProof { signature: "3yZe7d".to_string(), ..proof.clone() };
+        assert_eq!(validate_key_delegate(&forged, "8XFh8yBzrpJQmNyZzgoTqB"), Err(error::INVALID_KEY_DELEGATE.code_num));
+        let garbled = KeyDlgProof { signature: "0OIl".to_string(), ..proof };
+        assert_eq!(validate_key_delegate(&garbled, "8XFh8yBzrpJQmNyZzgoTqB"), Err(error::INVALID_KEY_DELEGATE.code_num));
+
+        assert_eq!(KeyDlgProof::create("8XFh8yBzrpJQmNyZzgoTqB", "", "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A"),
+                   Err(error::INVALID_KEY_DELEGATE.code_num));
+        assert_eq!(KeyDlgProof::create("8XFh8yBzrpJQmNyZzgoTqB", "KkTVEE7RGg7z2d2hrfM2Hj", ""),
+                   Err(error::INVALID_KEY_DELEGATE.code_num));
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_validate_key_delegate_signed_in_wallet() {
+        use utils::wallet;
+
+        ::settings::set_defaults();
+        ::settings::set_config_value(::settings::CONFIG_ENABLE_TEST_MODE,"false");
+        wallet::tests::make_wallet("test_validate_key_delegate_signed_in_wallet");
+        let (pw_did, _) = wallet::create_my_did("{}").unwrap();
+
+        let proof = KeyDlgProof::create(&pw_did, "KkTVEE7RGg7z2d2hrfM2Hj",
+                                        "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A").unwrap();
+        assert_eq!(validate_key_delegate(&proof, &pw_did).unwrap(), proof);
+
+        // the signature doesn't cover a proof whose did and verkey split differently
+        let shifted = KeyDlgProof {
+            agent_did: "KkTVEE7RGg7z2d2hrfM2HjE".to_string(),
+            agent_delegated_key: "kVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A".to_string(),
+            ..proof.clone()
+        };
+        assert_eq!(validate_key_delegate(&shifted, &pw_did), Err(error::INVALID_KEY_DELEGATE.code_num));
+
+        wallet::tests::delete_wallet("test_validate_key_delegate_signed_in_wallet");
+    }
+}
@@ -0,0 +1,175 @@
+extern crate serde_json;
+
+use std::sync::RwLock;
+use serde::{Serialize, Serializer};
+use settings;
+use utils::error;
+use messages::{GeneralMessage, MessageHeader, Recipient, post_message};
+use messages::format::MessageFormat;
+
+pub static PROTOCOL_VERSION_1: &'static str = "1.0";
+pub static PROTOCOL_VERSION_2: &'static str = "2.0";
+
+/// Protocol versions the SDK can emit, oldest first.
+pub static SUPPORTED_VERSIONS: &'static [&'static str] = &["1.0", "2.0"];
+
+lazy_static! {
+    // unset until negotiate() learns the agency's version, messages without a version are
+    // read as 1.0 by every agency
+    static ref AGENCY_VERSION: RwLock<Option<String>> = RwLock::new(None);
+}
+
+/// The version new messages are built with, if one was negotiated with the agency.
+pub fn agency_version() -> Option<String> {
+    AGENCY_VERSION.read().unwrap().clone()
+}
+
+pub fn validate_version(version: &str) -> Result<String, u32> {
+    if SUPPORTED_VERSIONS.contains(&version) {
+        Ok(version.to_string())
+    } else {
+        warn!("unsupported message protocol version {}", version);
+        Err(error::UNSUPPORTED_PROTOCOL_VERSION.code_num)
+    }
+}
+
+/// Picks the newest version that both the SDK and the agency support.
+pub fn select_version(agency_versions: &[String]) -> Result<String, u32> {
+    match SUPPORTED_VERSIONS.iter().rev().find(|v| agency_versions.iter().any(|x| x == *v)) {
+        Some(v) => Ok(v.to_string()),
+        None => Err(error::UNSUPPORTED_PROTOCOL_VERSION.code_num),
+    }
+}
+
+/// Message type of a payload.  Version 1.0 sends the bare name, later versions send the
+/// name together with the version of the payload.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct MsgType {
+    pub name: String,
+    pub version: String,
+}
+
+impl MsgType {
+    pub fn new(name: &str) -> MsgType {
+        MsgType { name: name.to_string(), version: agency_version().unwrap_or_else(|| PROTOCOL_VERSION_1.to_string()) }
+    }
+}
+
+impl Serialize for MsgType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        if self.version == PROTOCOL_VERSION_1 {
+            serializer.serialize_str(&self.name)
+        } else {
+            json!({"name": self.name, "ver": self.version}).serialize(serializer)
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq, PartialOrd)]
+pub struct GetVersionsPayload {
+    #[serde(rename = "type")]
+    msg_type: MsgType,
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct GetVersions {
+    header: MessageHeader,
+    payload: GetVersionsPayload,
+}
+
+impl GetVersions {
+    // always asked without a version, which every agency reads as 1.0
+    pub fn create() -> GetVersions {
+        GetVersions {
+            header: MessageHeader { version: None, ..MessageHeader::new() },
+            payload: GetVersionsPayload {
+                msg_type: MsgType { name: "GET_VERSIONS".to_string(), version: PROTOCOL_VERSION_1.to_string() },
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct GetVersionsResponse {
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+
+impl GeneralMessage for GetVersions {
+    type Msg = GetVersions;
+    type Response = GetVersionsResponse;
+    type Payload = GetVersionsPayload;
+
+    fn recipient() -> Recipient { Recipient::Agency }
+
+    fn header(&mut self) -> &mut MessageHeader {
+        &mut self.header
+    }
+
+    fn payload_type(&mut self) -> &mut MsgType {
+        &mut self.payload.msg_type
+    }
+
+    fn payload(&self) -> &Self::Payload {
+        &self.payload
+    }
+}
+
+/// Asks the agency which protocol versions it supports and switches new messages to the
+/// newest one both sides understand.  Messages to agencies that don't report their versions
+/// keep going out without a version.
+pub fn negotiate() -> Result<Option<String>, u32> {
+    let agency_did = settings::get_config_value(settings::CONFIG_AGENCY_PAIRWISE_DID)?;
+    let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+    let format = MessageFormat::from_settings();
+    let msg = GetVersions::create().to(&agency_did).pack(format)?;
+
+    let (response, format) = match post_message(&msg, &url, format) {
+        Ok(x) => x,
+        Err(_) => return Err(error::POST_MSG_FAILURE.code_num),
+    };
+
+    let versions = GetVersions::unpack_response(&response, format)?.versions;
+    if versions.is_empty() {
+        info!("agency did not report its protocol versions");
+        return Ok(agency_version());
+    }
+
+    let version = select_version(&versions)?;
+    info!("agency supports protocol versions {:?}, using {}", versions, version);
+    *AGENCY_VERSION.write().unwrap() = Some(version.clone());
+    Ok(Some(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_version() {
+        assert_eq!(select_version(&vec!["1.0".to_string()]).unwrap(), "1.0");
+        assert_eq!(select_version(&vec!["1.0".to_string(), "2.0".to_string(), "3.0".to_string()]).unwrap(), "2.0");
+        assert_eq!(select_version(&vec!["3.0".to_string()]), Err(error::UNSUPPORTED_PROTOCOL_VERSION.code_num));
+        assert_eq!(validate_version("0.9"), Err(error::UNSUPPORTED_PROTOCOL_VERSION.code_num));
+    }
+
+    #[test]
+    fn test_msg_type_serializes_per_version() {
+        let mut msg_type = MsgType { name: "GET_MSGS".to_string(), version: PROTOCOL_VERSION_1.to_string() };
+        assert_eq!(json!(msg_type).to_string(), "\"GET_MSGS\"");
+        msg_type.version = PROTOCOL_VERSION_2.to_string();
+        assert_eq!(json!(msg_type).to_string(), "{\"name\":\"GET_MSGS\",\"ver\":\"2.0\"}");
+    }
+
+    #[test]
+    fn test_negotiate_in_test_mode_keeps_version() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        settings::set_config_value(settings::CONFIG_AGENCY_PAIRWISE_DID,"72x8p4HubxzUK1dwxcc5FU");
+        assert_eq!(negotiate().unwrap(), None);
+        assert_eq!(GetVersions::create().to("72x8p4HubxzUK1dwxcc5FU").serialize_message().unwrap(),
+                   "{\"agentPayload\":\"{\\\"type\\\":\\\"GET_VERSIONS\\\"}\",\"to\":\"72x8p4HubxzUK1dwxcc5FU\"}");
+        assert_eq!(GetVersions::deserialize_response("{\"versions\":[\"1.0\",\"2.0\"]}").unwrap().versions.len(), 2);
+    }
+}
@@ -0,0 +1,699 @@
+extern crate rand;
+extern crate serde_json;
+
+use std::sync::Mutex;
+use std::collections::HashMap;
+use rand::Rng;
+use serde_json::Value;
+use api::CxsStateType;
+use settings;
+use connection;
+use messages;
+use messages::GeneralMessage;
+use messages::message::{Message, MSG_STATUS_REVIEWED, unpack_messages};
+use messages::format::MessageFormat;
+use messages::send_message::parse_msg_uid;
+use utils::error;
+use utils::ledger;
+use utils::anoncreds;
+
+pub static PROOF_REQUEST_MSG_TYPE: &'static str = "proofReq";
+pub static PROOF_MSG_TYPE: &'static str = "proof";
+
+lazy_static! {
+    static ref PROOF_MAP: Mutex<HashMap<u32, Box<Proof>>> = Default::default();
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ProofStateType {
+    ProofUndefined = 0,
+    ProofValidated = 1,
+    ProofInvalid = 2,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Proof {
+    source_id: String,
+    handle: u32,
+    proof_request: String,
+    connection_handle: u32,
+    msg_uid: String,
+    proof_offer: String,
+    // the agent's message the proof offer came in, acknowledged once the proof is validated
+    #[serde(default)]
+    proof_offer_uid: String,
+    state: CxsStateType,
+    proof_state: ProofStateType,
+    #[serde(default)]
+    proof_result: String,
+}
+
+impl Proof {
+    fn send_request(&mut self) -> Result<u32, u32> {
+        if self.state != CxsStateType::CxsStateInitialized {
+            warn!("proof {} in state {} not ready to send request", self.handle, self.state as u32);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        if !connection::is_valid_connection_handle(self.connection_handle) {
+            warn!("proof {} does not have a valid connection", self.handle);
+            return Err(error::INVALID_CONNECTION_HANDLE.code_num);
+        }
+
+        let to_did = connection::get_pw_did(self.connection_handle)?;
+        let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+        let recipient = connection::get_recipient(self.connection_handle)?;
+        let format = MessageFormat::from_settings();
+        let msg = messages::send_message()
+            .to(&to_did)
+            .over(&recipient)
+            .msg_type(PROOF_REQUEST_MSG_TYPE)
+            .edge_agent_payload(&self.proof_request)
+            .pack(format)?;
+
+        match messages::post_message(&msg, &url, format) {
+            Ok((response, format)) => {
+                self.msg_uid = parse_msg_uid(&response, &recipient, format)?;
+                self.state = CxsStateType::CxsStateOfferSent;
+                info!("sent proof request for proof {} with uid {}", self.handle, self.msg_uid);
+                Ok(error::SUCCESS.code_num)
+            },
+            Err(x) => {
+                warn!("could not send proof request for proof {}: {}", self.handle, x);
+                Err(error::POST_MSG_FAILURE.code_num)
+            },
+        }
+    }
+
+    fn get_proof_offer(&mut self) -> Result<String, u32> {
+        if !self.proof_offer.is_empty() { return Ok(self.proof_offer.clone()); }
+
+        if self.state != CxsStateType::CxsStateOfferSent {
+            warn!("proof {} has not sent a proof request", self.handle);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        let to_did = connection::get_pw_did(self.connection_handle)?;
+        let url = format!("{}/agency/route", settings::get_config_value(settings::CONFIG_AGENT_ENDPOINT)?);
+
+        let recipient = connection::get_recipient(self.connection_handle)?;
+        let format = MessageFormat::from_settings();
+        let msg = messages::get_messages()
+            .to(&to_did)
+            .over(&recipient)
+            .msg_type(PROOF_MSG_TYPE)
+            .pack(format)?;
+
+        match messages::post_message(&msg, &url, format) {
+            Ok((response, format)) => self.update_proof_offer(unpack_messages(&response, &recipient, format)?),
+            Err(x) => {
+                warn!("could not get messages for proof {}: {}", self.handle, x);
+                Err(error::POST_MSG_FAILURE.code_num)
+            },
+        }
+    }
+
+    fn update_proof_offer(&mut self, msgs: Vec<Message>) -> Result<String, u32> {
+        // without the uid of the request any proof on the connection could be taken for its answer
+        if self.msg_uid.is_empty() {
+            warn!("proof {} has no request uid to match proof offers with", self.handle);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        for msg in msgs {
+            if msg.msg_type == PROOF_MSG_TYPE && msg.status_code != MSG_STATUS_REVIEWED && msg.ref_msg_id == self.msg_uid {
+                info!("received proof offer {} for proof {}", msg.uid, self.handle);
+                self.proof_offer = msg.edge_agent_payload;
+                self.proof_offer_uid = msg.uid;
+                self.state = CxsStateType::CxsStateRequestReceived;
+                return Ok(self.proof_offer.clone());
+            }
+        }
+
+        info!("no proof offer received yet for proof {}", self.handle);
+        Err(error::NOT_READY.code_num)
+    }
+
+    /// Validates the proof offer.  An invalid offer is dropped and its message acknowledged, so
+    /// the proof goes back to waiting for a corrected offer instead of validating it again.
+    fn validate_proof(&mut self) -> Result<String, u32> {
+        if self.proof_offer.is_empty() {
+            warn!("proof {} has no proof offer to validate", self.handle);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        let result = match self.verify_proof_offer() {
+            Ok(x) => x,
+            Err(x) => {
+                warn!("discarding the invalid proof offer of proof {}: {}", self.handle, x);
+                if !self.proof_offer_uid.is_empty() {
+                    connection::acknowledge_messages(self.connection_handle, &[self.proof_offer_uid.clone()]);
+                }
+                self.proof_offer = String::new();
+                self.proof_offer_uid = String::new();
+                self.proof_state = ProofStateType::ProofInvalid;
+                self.state = CxsStateType::CxsStateOfferSent;
+                return Err(x);
+            },
+        };
+
+        info!("proof {} is valid", self.handle);
+        self.proof_result = result.to_string();
+        self.proof_state = ProofStateType::ProofValidated;
+        self.state = CxsStateType::CxsStateAccepted;
+        // only now that the proof is kept can the agent stop handing its message out
+        if !self.proof_offer_uid.is_empty() {
+            connection::acknowledge_messages(self.connection_handle, &[self.proof_offer_uid.clone()]);
+        }
+        Ok(self.proof_result.clone())
+    }
+
+    /// Validates proof_offer, or the offer retrieved from the agency when it is None.  Only a
+    /// proof whose request is out takes an offer, and a validated proof keeps its result.
+    fn validate_response(&mut self, proof_offer: Option<String>) -> Result<String, u32> {
+        if self.proof_state == ProofStateType::ProofValidated {
+            if proof_offer.is_some() {
+                warn!("proof {} is already validated", self.handle);
+                return Err(error::NOT_READY.code_num);
+            }
+            return Ok(self.proof_result.clone());
+        }
+
+        if self.state != CxsStateType::CxsStateOfferSent && self.state != CxsStateType::CxsStateRequestReceived {
+            warn!("proof {} in state {} has no proof request out", self.handle, self.state as u32);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        match proof_offer {
+            Some(x) => {
+                self.proof_offer = x;
+                self.proof_offer_uid = String::new();
+                self.state = CxsStateType::CxsStateRequestReceived;
+            },
+            None => { self.get_proof_offer()?; },
+        };
+        self.validate_proof()
+    }
+
+    fn accept(&mut self) -> Result<u32, u32> {
+        if self.proof_state != ProofStateType::ProofValidated {
+            warn!("proof {} can't be accepted before it is validated", self.handle);
+            return Err(error::NOT_READY.code_num);
+        }
+
+        self.state = CxsStateType::CxsStateAccepted;
+        Ok(error::SUCCESS.code_num)
+    }
+
+    fn verify_proof_offer(&self) -> Result<Value, u32> {
+        let proof: Value = match serde_json::from_str(&self.proof_offer) {
+            Ok(x) => x,
+            Err(x) => {
+                warn!("proof offer for proof {} is not valid json: {}", self.handle, x);
+                return Err(error::INVALID_PROOF.code_num);
+            },
+        };
+
+        let proof_request: Value = serde_json::from_str(&self.proof_request).unwrap_or(Value::Null);
+
+        // The proof carries the nonce it was built over; without it the proof could be replayed
+        // from an earlier request.
+        if !proof["nonce"].is_string() || proof["nonce"] != proof_request["nonce"] {
+            warn!("proof {} was built for nonce {} instead of {}", self.handle, proof["nonce"], proof_request["nonce"]);
+            return Err(error::INVALID_PROOF_NONCE.code_num);
+        }
+
+        let submitter_did = settings::get_config_value(settings::CONFIG_ENTERPRISE_DID)?;
+        let mut schemas = serde_json::Map::new();
+        let mut claim_defs = serde_json::Map::new();
+
+        let claims = match proof["proofs"].as_object() {
+            Some(x) => x,
+            None => return Err(error::INVALID_PROOF.code_num),
+        };
+
+        for (claim_uuid, claim) in claims.iter() {
+            let (schema_seq_no, issuer_did) = match (claim["schema_seq_no"].as_u64(), claim["issuer_did"].as_str()) {
+                (Some(x), Some(y)) => (x as u32, y),
+                _ => {
+                    warn!("claim {} in proof {} does not identify its schema and issuer", claim_uuid, self.handle);
+                    return Err(error::INVALID_PROOF.code_num);
+                },
+            };
+
+            schemas.insert(claim_uuid.clone(), retrieve_schema(&submitter_did, schema_seq_no)?);
+            claim_defs.insert(claim_uuid.clone(), retrieve_claim_def(&submitter_did, schema_seq_no, issuer_did)?);
+        }
+
+        let result = build_proof_result(&proof_request, &proof)?;
+
+        let valid = anoncreds::verifier_verify_proof(&self.proof_request,
+                                                     &self.proof_offer,
+                                                     &Value::Object(schemas).to_string(),
+                                                     &Value::Object(claim_defs).to_string(),
+                                                     "{}")?;
+
+        if !valid {
+            warn!("proof {} failed verification", self.handle);
+            return Err(error::INVALID_PROOF_SIGNATURE.code_num);
+        }
+
+        Ok(result)
+    }
+}
+
+/// Lists what the proof showed: the value of each revealed attribute and each predicate that
+/// held, along with the schema and issuer of the claim they came from.
+fn build_proof_result(proof_request: &Value, proof: &Value) -> Result<Value, u32> {
+    let requested_proof = &proof["requested_proof"];
+    let mut revealed_attrs = serde_json::Map::new();
+    let mut predicates = serde_json::Map::new();
+
+    if let Some(attrs) = requested_proof["revealed_attrs"].as_object() {
+        for (attr_id, attr) in attrs.iter() {
+            let (claim_uuid, value) = match (attr[0].as_str(), attr[1].as_str(), attr[2].as_str()) {
+                (Some(x), Some(y), Some(z)) if z == anoncreds::encode_attribute(y) => (x, y),
+                _ => {
+                    warn!("revealed attribute {} is malformed or does not match its encoding", attr_id);
+                    return Err(error::INVALID_PROOF.code_num);
+                },
+            };
+
+            let claim = &proof["proofs"][claim_uuid];
+            revealed_attrs.insert(attr_id.clone(), json!({
+                "name": proof_request["requested_attrs"][attr_id]["name"],
+                "value": value,
+                "issuer_did": claim["issuer_did"],
+                "schema_seq_no": claim["schema_seq_no"]
+            }));
+        }
+    }
+
+    if let Some(preds) = requested_proof["predicates"].as_object() {
+        for (predicate_id, claim_uuid) in preds.iter() {
+            let predicate = &proof_request["requested_predicates"][predicate_id];
+            let claim = &proof["proofs"][claim_uuid.as_str().unwrap_or_default()];
+            predicates.insert(predicate_id.clone(), json!({
+                "attr_name": predicate["attr_name"],
+                "p_type": predicate["p_type"],
+                "value": predicate["value"],
+                "satisfied": true,
+                "issuer_did": claim["issuer_did"],
+                "schema_seq_no": claim["schema_seq_no"]
+            }));
+        }
+    }
+
+    Ok(json!({
+        "revealed_attrs": revealed_attrs,
+        "predicates": predicates
+    }))
+}
+
+// The ledger may hand back a transaction, or its data, as an embedded json string.
+fn as_json(value: &Value) -> Value {
+    match *value {
+        Value::String(ref x) => serde_json::from_str(x).unwrap_or(Value::Null),
+        ref x => x.clone(),
+    }
+}
+
+fn retrieve_schema(submitter_did: &str, schema_seq_no: u32) -> Result<Value, u32> {
+    let request = ledger::build_get_txn_request(submitter_did, schema_seq_no)?;
+    let result = ledger::get_txn_result(&ledger::submit_request(&request)?)?;
+    let data = as_json(&as_json(&result["data"])["data"]);
+
+    if data["name"].is_null() || data["attr_names"].is_null() {
+        warn!("no schema found on the ledger with sequence number {}", schema_seq_no);
+        return Err(error::INVALID_SCHEMA_SEQ_NO.code_num);
+    }
+
+    Ok(json!({
+        "seqNo": schema_seq_no,
+        "data": {
+            "name": data["name"],
+            "version": data["version"],
+            "keys": data["attr_names"]
+        }
+    }))
+}
+
+fn retrieve_claim_def(submitter_did: &str, schema_seq_no: u32, issuer_did: &str) -> Result<Value, u32> {
+    let request = ledger::build_get_claim_def_txn(submitter_did, schema_seq_no, issuer_did)?;
+    let result = ledger::get_txn_result(&ledger::submit_request(&request)?)?;
+
+    if result["data"].is_null() {
+        warn!("no claim definition found on the ledger for schema {} and issuer {}", schema_seq_no, issuer_did);
+        return Err(error::UNKNOWN_CLAIM_DEF.code_num);
+    }
+
+    Ok(json!({
+        "ref": schema_seq_no,
+        "origin": issuer_did,
+        "signature_type": result["signature_type"].as_str().unwrap_or(ledger::CLAIM_DEF_SIGNATURE_TYPE),
+        "data": result["data"]
+    }))
+}
+
+fn validate_proof_request(proof_request_data: &str) -> Result<String, u32> {
+    let mut request: Value = match serde_json::from_str(proof_request_data) {
+        Ok(x) => x,
+        Err(x) => {
+            warn!("invalid proof request json: {}", x);
+            return Err(error::INVALID_PROOF_REQUEST.code_num);
+        },
+    };
+
+    if !request.is_object() || request["name"].as_str().map_or(true, |x| x.trim().is_empty()) {
+        warn!("proof request must have a name");
+        return Err(error::INVALID_PROOF_REQUEST.code_num);
+    }
+
+    if request["requested_predicates"].is_null() { request["requested_predicates"] = json!({}); }
+
+    let num_requested = match (request["requested_attrs"].as_object(), request["requested_predicates"].as_object()) {
+        (Some(x), Some(y)) => x.len() + y.len(),
+        _ => {
+            warn!("proof request attributes and predicates must be json objects");
+            return Err(error::INVALID_PROOF_REQUEST.code_num);
+        },
+    };
+
+    if num_requested == 0 {
+        warn!("proof request must ask for at least one attribute or predicate");
+        return Err(error::INVALID_PROOF_REQUEST.code_num);
+    }
+
+    if request["version"].is_null() { request["version"] = json!("0.1"); }
+    if request["nonce"].is_null() { request["nonce"] = json!(rand::thread_rng().gen::<u32>().to_string()); }
+
+    Ok(request.to_string())
+}
+
+pub fn create(source_id: Option<String>, proof_request_data: String) -> Result<u32, u32> {
+    let proof_request = validate_proof_request(&proof_request_data)?;
+
+    let new_handle = rand::thread_rng().gen::<u32>();
+
+    let new_proof = Box::new(Proof {
+        source_id: source_id.unwrap_or("".to_string()),
+        handle: new_handle,
+        proof_request: proof_request,
+        connection_handle: 0,
+        msg_uid: String::new(),
+        proof_offer: String::new(),
+        proof_offer_uid: String::new(),
+        state: CxsStateType::CxsStateInitialized,
+        proof_state: ProofStateType::ProofUndefined,
+        proof_result: String::new(),
+    });
+
+    {
+        let mut m = PROOF_MAP.lock().unwrap();
+        info!("inserting handle {} into proof table", new_handle);
+        m.insert(new_handle, new_proof);
+    }
+
+    Ok(new_handle)
+}
+
+pub fn is_valid_handle(handle: u32) -> bool {
+    PROOF_MAP.lock().unwrap().contains_key(&handle)
+}
+
+pub fn set_connection(handle: u32, connection_handle: u32) -> Result<u32, u32> {
+    if !connection::is_valid_connection_handle(connection_handle) {
+        return Err(error::INVALID_CONNECTION_HANDLE.code_num);
+    }
+
+    let mut m = PROOF_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(p) => {
+            p.connection_handle = connection_handle;
+            Ok(error::SUCCESS.code_num)
+        },
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+pub fn send_request(handle: u32) -> Result<u32, u32> {
+    let mut m = PROOF_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(p) => p.send_request(),
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+pub fn get_proof_offer(handle: u32) -> Result<String, u32> {
+    let mut m = PROOF_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(p) => p.get_proof_offer(),
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+/// Verifies the proof offer against the ledger, using `proof_offer` in place of
+/// polling the agency when the caller already has it.  Returns the revealed attributes
+/// and predicates of a valid proof.
+pub fn validate_response(handle: u32, proof_offer: Option<String>) -> Result<String, u32> {
+    let mut m = PROOF_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(p) => p.validate_response(proof_offer),
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+/// Marks the proof offer accepted, which only a validated proof can be.
+pub fn accepted(handle: u32) -> Result<u32, u32> {
+    match PROOF_MAP.lock().unwrap().get_mut(&handle) {
+        Some(p) => p.accept(),
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+pub fn get_state(handle: u32) -> Result<u32, u32> {
+    let mut m = PROOF_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(p) => {
+            // Try to pick up a proof offer from the agent first
+            if p.state == CxsStateType::CxsStateOfferSent { let _ = p.get_proof_offer(); }
+            Ok(p.state as u32)
+        },
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+pub fn get_proof_state(handle: u32) -> Result<u32, u32> {
+    match PROOF_MAP.lock().unwrap().get(&handle) {
+        Some(p) => Ok(p.proof_state as u32),
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+pub fn to_string(handle: u32) -> Result<String, u32> {
+    match PROOF_MAP.lock().unwrap().get(&handle) {
+        Some(p) => Ok(serde_json::to_string(&p).unwrap()),
+        None => Err(error::INVALID_PROOF_HANDLE.code_num),
+    }
+}
+
+pub fn from_string(proof_data: &str) -> Result<u32, u32> {
+    let derived_proof: Proof = match serde_json::from_str(proof_data) {
+        Ok(x) => x,
+        Err(_) => return Err(error::INVALID_PROOF.code_num),
+    };
+
+    let new_handle = derived_proof.handle;
+
+    {
+        let mut m = PROOF_MAP.lock().unwrap();
+        info!("inserting handle {} into proof table", new_handle);
+        m.insert(new_handle, Box::new(derived_proof));
+    }
+
+    Ok(new_handle)
+}
+
+/// Returns the handle and current state of every proof object, without asking the agent for a proof offer.
+pub fn list_state() -> Vec<(u32, u32)> {
+    PROOF_MAP.lock().unwrap().iter().map(|(handle, x)| (*handle, x.state as u32)).collect()
+}
+
+pub fn release(handle: u32) -> u32 {
+    match PROOF_MAP.lock().unwrap().remove(&handle) {
+        Some(_) => error::SUCCESS.code_num,
+        None => error::INVALID_PROOF_HANDLE.code_num,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use connection::tests::build_test_connection;
+    use messages::message::parse_messages;
+
+    pub static PROOF_REQUEST: &'static str = "{\"name\":\"proof_req\",\"requested_attrs\":{\"attr1_uuid\":{\"schema_seq_no\":15,\"name\":\"name\"}},\"requested_predicates\":{\"predicate1_uuid\":{\"attr_name\":\"age\",\"p_type\":\"GE\",\"value\":18}},\"nonce\":\"123432421212\"}";
+    pub static PROOF_OFFER: &'static str = "{\"proofs\":{\"claim::e5fec91f\":{\"proof\":{},\"schema_seq_no\":15,\"issuer_did\":\"4fUDR9R7fjwELRvH9JT6HH\"}},\"aggregated_proof\":{},\"requested_proof\":{\"revealed_attrs\":{\"attr1_uuid\":[\"claim::e5fec91f\",\"Alex\",\"99262857098057710338306967609588410025648622308394250666849665532448612202874\"]},\"unrevealed_attrs\":{},\"self_attested_attrs\":{},\"predicates\":{\"predicate1_uuid\":\"claim::e5fec91f\"}},\"nonce\":\"123432421212\"}";
+
+    fn set_test_mode() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+    }
+
+    // a proof whose request went out, ready to validate an offer
+    pub fn set_request_sent(handle: u32) {
+        PROOF_MAP.lock().unwrap().get_mut(&handle).unwrap().state = CxsStateType::CxsStateOfferSent;
+    }
+
+    #[test]
+    fn test_create_proof_succeeds() {
+        let mut request: Value = serde_json::from_str(PROOF_REQUEST).unwrap();
+        request.as_object_mut().unwrap().remove("nonce");
+        let handle = create(Some("test_create_proof_succeeds".to_owned()), request.to_string()).unwrap();
+        assert!(handle > 0);
+        assert!(is_valid_handle(handle));
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateInitialized as u32);
+        let request: Value = serde_json::from_str(&PROOF_MAP.lock().unwrap().get(&handle).unwrap().proof_request).unwrap();
+        assert!(request["nonce"].is_string());
+        assert!(request["nonce"] != json!("123432421212"));
+        assert_eq!(request["version"], json!("0.1"));
+        release(handle);
+    }
+
+    #[test]
+    fn test_create_proof_with_invalid_request_fails() {
+        assert_eq!(create(None, "garbage".to_owned()), Err(error::INVALID_PROOF_REQUEST.code_num));
+        assert_eq!(create(None, "{\"requested_attrs\":{\"a\":{}}}".to_owned()), Err(error::INVALID_PROOF_REQUEST.code_num));
+        assert_eq!(create(None, "{\"name\":\"proof_req\",\"requested_attrs\":[]}".to_owned()), Err(error::INVALID_PROOF_REQUEST.code_num));
+        assert_eq!(create(None, "{\"name\":\"proof_req\",\"requested_attrs\":{}}".to_owned()), Err(error::INVALID_PROOF_REQUEST.code_num));
+    }
+
+    #[test]
+    fn test_send_proof_request() {
+        let connection_handle = build_test_connection();
+        let handle = create(None, PROOF_REQUEST.to_owned()).unwrap();
+        assert_eq!(send_request(handle), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+        assert_eq!(set_connection(handle, 0), Err(error::INVALID_CONNECTION_HANDLE.code_num));
+        assert_eq!(set_connection(handle, connection_handle).unwrap(), error::SUCCESS.code_num);
+        assert_eq!(send_request(handle).unwrap(), error::SUCCESS.code_num);
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateOfferSent as u32);
+        assert_eq!(send_request(handle), Err(error::NOT_READY.code_num));
+        release(handle);
+        connection::release(connection_handle);
+    }
+
+    #[test]
+    fn test_update_proof_offer_from_messages() {
+        let handle = create(None, PROOF_REQUEST.to_owned()).unwrap();
+        let response = json!({"msgs": [
+            {"uid": "0", "msgType": "proof", "refMsgId": "123", "statusCode": "MS-106", "edgeAgentPayload": "{\"old\":1}"},
+            {"uid": "1", "msgType": "proof", "refMsgId": "456", "edgeAgentPayload": "{\"other\":1}"},
+            {"uid": "2", "msgType": "proof", "refMsgId": "123", "edgeAgentPayload": PROOF_OFFER},
+            {"uid": "3", "msgType": "proof", "edgeAgentPayload": "{\"unrelated\":1}"}
+        ]}).to_string();
+
+        {
+            let mut m = PROOF_MAP.lock().unwrap();
+            let proof = m.get_mut(&handle).unwrap();
+            assert_eq!(proof.update_proof_offer(parse_messages(&response).unwrap()), Err(error::NOT_READY.code_num));
+            proof.msg_uid = "123".to_string();
+            assert_eq!(proof.update_proof_offer(Vec::new()), Err(error::NOT_READY.code_num));
+            assert_eq!(proof.update_proof_offer(parse_messages(&response).unwrap()).unwrap(), PROOF_OFFER);
+            assert_eq!(proof.state, CxsStateType::CxsStateRequestReceived);
+            assert_eq!(proof.proof_offer_uid, "2");
+        }
+
+        assert_eq!(get_proof_offer(handle).unwrap(), PROOF_OFFER);
+        release(handle);
+    }
+
+    #[test]
+    fn test_validate_proof() {
+        set_test_mode();
+        let handle = create(None, PROOF_REQUEST.to_owned()).unwrap();
+        assert_eq!(validate_response(handle, None), Err(error::NOT_READY.code_num));
+        assert_eq!(validate_response(handle, Some(PROOF_OFFER.to_owned())), Err(error::NOT_READY.code_num));
+        assert_eq!(accepted(handle), Err(error::NOT_READY.code_num));
+        set_request_sent(handle);
+        assert_eq!(validate_response(handle, Some("garbage".to_owned())), Err(error::INVALID_PROOF.code_num));
+        let result: Value = serde_json::from_str(&validate_response(handle, Some(PROOF_OFFER.to_owned())).unwrap()).unwrap();
+        assert_eq!(result["revealed_attrs"]["attr1_uuid"], json!({"name": "name", "value": "Alex", "issuer_did": "4fUDR9R7fjwELRvH9JT6HH", "schema_seq_no": 15}));
+        assert_eq!(result["predicates"]["predicate1_uuid"]["attr_name"], json!("age"));
+        assert_eq!(result["predicates"]["predicate1_uuid"]["satisfied"], json!(true));
+        assert_eq!(result["predicates"]["predicate1_uuid"]["issuer_did"], json!("4fUDR9R7fjwELRvH9JT6HH"));
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateAccepted as u32);
+        assert_eq!(get_proof_state(handle).unwrap(), ProofStateType::ProofValidated as u32);
+
+        // a validated proof keeps its result
+        assert_eq!(validate_response(handle, Some("garbage".to_owned())), Err(error::NOT_READY.code_num));
+        assert_eq!(validate_response(handle, None), Ok(result.to_string()));
+        assert_eq!(accepted(handle), Ok(error::SUCCESS.code_num));
+        assert_eq!(get_state(handle).unwrap(), CxsStateType::CxsStateAccepted as u32);
+        assert_eq!(accepted(0), Err(error::INVALID_PROOF_HANDLE.code_num));
+        release(handle);
+    }
+
+    #[test]
+    fn test_validate_proof_with_wrong_nonce_fails() {
+        set_test_mode();
+        let handle = create(None, PROOF_REQUEST.to_owned()).unwrap();
+        set_request_sent(handle);
+        let mut offer: Value = serde_json::from_str(PROOF_OFFER).unwrap();
+        offer["nonce"] = json!("not the nonce");
+        assert_eq!(validate_response(handle, Some(offer.to_string())), Err(error::INVALID_PROOF_NONCE.code_num));
+        assert_eq!(get_proof_state(handle).unwrap(), ProofStateType::ProofInvalid as u32);
+        {
+            let m = PROOF_MAP.lock().unwrap();
+            let proof = m.get(&handle).unwrap();
+            assert_eq!(proof.state, CxsStateType::CxsStateOfferSent);
+            assert!(proof.proof_offer.is_empty());
+            assert!(proof.proof_offer_uid.is_empty());
+        }
+        offer.as_object_mut().unwrap().remove("nonce");
+        assert_eq!(validate_response(handle, Some(offer.to_string())), Err(error::INVALID_PROOF_NONCE.code_num));
+        release(handle);
+    }
+
+    #[test]
+    fn test_build_proof_result_with_malformed_attribute_fails() {
+        let request: Value = serde_json::from_str(PROOF_REQUEST).unwrap();
+        let mut offer: Value = serde_json::from_str(PROOF_OFFER).unwrap();
+        offer["requested_proof"]["revealed_attrs"]["attr1_uuid"] = json!("Alex");
+        assert_eq!(build_proof_result(&request, &offer), Err(error::INVALID_PROOF.code_num));
+        offer["requested_proof"]["revealed_attrs"]["attr1_uuid"] = json!(["claim::e5fec91f", "Alex", "1139481716457488690172217916278103335"]);
+        assert_eq!(build_proof_result(&request, &offer), Err(error::INVALID_PROOF.code_num));
+    }
+
+    #[test]
+    fn test_retrieve_schema_and_claim_def_in_test_mode() {
+        set_test_mode();
+        let schema = retrieve_schema("8XFh8yBzrpJQmNyZzgoTqB", 15).unwrap();
+        assert_eq!(schema["seqNo"], json!(15));
+        assert_eq!(schema["data"]["keys"], json!(["name", "age", "height", "sex"]));
+        let claim_def = retrieve_claim_def("8XFh8yBzrpJQmNyZzgoTqB", 15, "4fUDR9R7fjwELRvH9JT6HH").unwrap();
+        assert_eq!(claim_def["origin"], json!("4fUDR9R7fjwELRvH9JT6HH"));
+        assert!(claim_def["data"]["primary"].is_object());
+    }
+
+    #[test]
+    fn test_from_string_succeeds() {
+        let handle = create(None, PROOF_REQUEST.to_owned()).unwrap();
+        let string = to_string(handle).unwrap();
+        release(handle);
+        let new_handle = from_string(&string).unwrap();
+        assert_eq!(new_handle, handle);
+        assert_eq!(to_string(new_handle).unwrap(), string);
+        release(new_handle);
+    }
+
+    #[test]
+    fn test_invalid_handle_fails() {
+        assert!(!is_valid_handle(0));
+        assert_eq!(send_request(0), Err(error::INVALID_PROOF_HANDLE.code_num));
+        assert_eq!(get_state(0), Err(error::INVALID_PROOF_HANDLE.code_num));
+        assert_eq!(to_string(0), Err(error::INVALID_PROOF_HANDLE.code_num));
+        assert_eq!(release(0), error::INVALID_PROOF_HANDLE.code_num);
+        assert_eq!(from_string("{}"), Err(error::INVALID_PROOF.code_num));
+    }
+}
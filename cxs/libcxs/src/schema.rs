@@ -0,0 +1,274 @@
+extern crate rand;
+extern crate serde_json;
+
+use std::sync::Mutex;
+use std::collections::HashMap;
+use rand::Rng;
+use api::CxsStateType;
+use settings;
+use utils::error;
+use utils::ledger;
+
+lazy_static! {
+    static ref SCHEMA_MAP: Mutex<HashMap<u32, Box<Schema>>> = Default::default();
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchemaData {
+    pub name: String,
+    pub version: String,
+    pub attr_names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Schema {
+    source_id: String,
+    handle: u32,
+    data: SchemaData,
+    sequence_num: u32,
+    state: CxsStateType,
+}
+
+impl Schema {
+    fn commit(&mut self) -> Result<u32, u32> {
+        if self.sequence_num > 0 {
+            info!("schema {} already committed with sequence number {}", self.handle, self.sequence_num);
+            return Ok(self.sequence_num);
+        }
+
+        let submitter_did = settings::get_config_value(settings::CONFIG_ENTERPRISE_DID)?;
+        let data = serde_json::to_string(&self.data).unwrap();
+
+        let request = ledger::build_schema_request(&submitter_did, &data)?;
+        let response = ledger::sign_and_submit_request(&submitter_did, &request)?;
+
+        self.sequence_num = ledger::get_seq_no(&response)?;
+        self.state = CxsStateType::CxsStateAccepted;
+        info!("committed schema {} with sequence number {}", self.handle, self.sequence_num);
+        Ok(self.sequence_num)
+    }
+
+    fn retrieve_sequence_no(&mut self) -> Result<u32, u32> {
+        if self.sequence_num > 0 { return Ok(self.sequence_num); }
+
+        let submitter_did = settings::get_config_value(settings::CONFIG_ENTERPRISE_DID)?;
+        let data = json!({"name": self.data.name, "version": self.data.version}).to_string();
+
+        let request = ledger::build_get_schema_request(&submitter_did, &submitter_did, &data)?;
+        let response = ledger::submit_request(&request)?;
+        let result = ledger::get_txn_result(&response)?;
+
+        if result["data"].is_null() {
+            warn!("schema {} {} was not found on the ledger", self.data.name, self.data.version);
+            return Err(error::INVALID_SCHEMA_SEQ_NO.code_num);
+        }
+
+        match result["seqNo"].as_u64() {
+            Some(x) => {
+                self.sequence_num = x as u32;
+                self.state = CxsStateType::CxsStateAccepted;
+                Ok(self.sequence_num)
+            },
+            None => Err(error::INVALID_SCHEMA_SEQ_NO.code_num),
+        }
+    }
+}
+
+fn validate_schema_data(schema_data: &str) -> Result<SchemaData, u32> {
+    let data: SchemaData = match serde_json::from_str(schema_data) {
+        Ok(x) => x,
+        Err(x) => {
+            warn!("invalid schema json: {}", x);
+            return Err(error::INVALID_SCHEMA.code_num)
+        },
+    };
+
+    if data.name.trim().is_empty() {
+        warn!("schema name cannot be empty");
+        return Err(error::INVALID_SCHEMA.code_num);
+    }
+
+    if data.version.is_empty() || !data.version.chars().all(|c| c.is_digit(10) || c == '.') {
+        warn!("schema version must be numbers separated by dots: {}", data.version);
+        return Err(error::INVALID_SCHEMA.code_num);
+    }
+
+    if data.attr_names.is_empty() {
+        warn!("schema must have at least one attribute");
+        return Err(error::INVALID_SCHEMA.code_num);
+    }
+
+    let mut seen: Vec<&String> = Vec::new();
+    for attr in data.attr_names.iter() {
+        if attr.trim().is_empty() || seen.contains(&attr) {
+            warn!("schema attribute names must be unique and non-empty: {:?}", data.attr_names);
+            return Err(error::INVALID_SCHEMA.code_num);
+        }
+        seen.push(attr);
+    }
+
+    Ok(data)
+}
+
+pub fn create(source_id: Option<String>, schema_data: String) -> Result<u32, u32> {
+    let data = validate_schema_data(&schema_data)?;
+
+    let new_handle = rand::thread_rng().gen::<u32>();
+
+    let new_schema = Box::new(Schema {
+        source_id: source_id.unwrap_or("".to_string()),
+        handle: new_handle,
+        data: data,
+        sequence_num: 0,
+        state: CxsStateType::CxsStateInitialized,
+    });
+
+    {
+        let mut m = SCHEMA_MAP.lock().unwrap();
+        info!("inserting handle {} into schema table", new_handle);
+        m.insert(new_handle, new_schema);
+    }
+
+    Ok(new_handle)
+}
+
+pub fn is_valid_handle(handle: u32) -> bool {
+    SCHEMA_MAP.lock().unwrap().contains_key(&handle)
+}
+
+pub fn commit(handle: u32) -> Result<u32, u32> {
+    let mut m = SCHEMA_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(s) => s.commit(),
+        None => Err(error::INVALID_SCHEMA_HANDLE.code_num),
+    }
+}
+
+pub fn get_sequence_no(handle: u32) -> Result<u32, u32> {
+    let mut m = SCHEMA_MAP.lock().unwrap();
+    match m.get_mut(&handle) {
+        Some(s) => s.retrieve_sequence_no(),
+        None => Err(error::INVALID_SCHEMA_HANDLE.code_num),
+    }
+}
+
+pub fn get_data(handle: u32) -> Result<String, u32> {
+    let m = SCHEMA_MAP.lock().unwrap();
+    match m.get(&handle) {
+        Some(s) => Ok(serde_json::to_string(&s.data).unwrap()),
+        None => Err(error::INVALID_SCHEMA_HANDLE.code_num),
+    }
+}
+
+pub fn to_string(handle: u32) -> Result<String, u32> {
+    let m = SCHEMA_MAP.lock().unwrap();
+    match m.get(&handle) {
+        Some(s) => Ok(serde_json::to_string(&s).unwrap()),
+        None => Err(error::INVALID_SCHEMA_HANDLE.code_num),
+    }
+}
+
+pub fn from_string(schema_data: &str) -> Result<u32, u32> {
+    let derived_schema: Schema = match serde_json::from_str(schema_data) {
+        Ok(x) => x,
+        Err(_) => return Err(error::INVALID_SCHEMA.code_num),
+    };
+
+    let new_handle = derived_schema.handle;
+
+    {
+        let mut m = SCHEMA_MAP.lock().unwrap();
+        info!("inserting handle {} into schema table", new_handle);
+        m.insert(new_handle, Box::new(derived_schema));
+    }
+
+    Ok(new_handle)
+}
+
+/// Returns the handle and current state of every schema object.
+pub fn list_state() -> Vec<(u32, u32)> {
+    SCHEMA_MAP.lock().unwrap().iter().map(|(handle, x)| (*handle, x.state as u32)).collect()
+}
+
+pub fn release(handle: u32) -> u32 {
+    match SCHEMA_MAP.lock().unwrap().remove(&handle) {
+        Some(_) => error::SUCCESS.code_num,
+        None => error::INVALID_SCHEMA_HANDLE.code_num,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SCHEMA_DATA: &'static str = "{\"name\":\"test-schema\",\"version\":\"1.0\",\"attr_names\":[\"name\",\"age\"]}";
+
+    fn set_test_mode() {
+        settings::set_defaults();
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+    }
+
+    #[test]
+    fn test_create_schema_succeeds() {
+        let handle = create(Some("test_create_schema_succeeds".to_owned()), SCHEMA_DATA.to_owned()).unwrap();
+        assert!(handle > 0);
+        assert!(is_valid_handle(handle));
+        release(handle);
+    }
+
+    #[test]
+    fn test_create_schema_with_invalid_data_fails() {
+        assert_eq!(create(None, "garbage".to_owned()), Err(error::INVALID_SCHEMA.code_num));
+        assert_eq!(create(None, "{\"name\":\"test\",\"version\":\"1.0\"}".to_owned()), Err(error::INVALID_SCHEMA.code_num));
+        assert_eq!(create(None, "{\"name\":\"\",\"version\":\"1.0\",\"attr_names\":[\"a\"]}".to_owned()), Err(error::INVALID_SCHEMA.code_num));
+        assert_eq!(create(None, "{\"name\":\"test\",\"version\":\"one\",\"attr_names\":[\"a\"]}".to_owned()), Err(error::INVALID_SCHEMA.code_num));
+        assert_eq!(create(None, "{\"name\":\"test\",\"version\":\"1.0\",\"attr_names\":[]}".to_owned()), Err(error::INVALID_SCHEMA.code_num));
+        assert_eq!(create(None, "{\"name\":\"test\",\"version\":\"1.0\",\"attr_names\":[\"a\",\"a\"]}".to_owned()), Err(error::INVALID_SCHEMA.code_num));
+    }
+
+    #[test]
+    fn test_commit_schema_in_test_mode() {
+        set_test_mode();
+        let handle = create(None, SCHEMA_DATA.to_owned()).unwrap();
+        assert_eq!(commit(handle).unwrap(), ledger::TEST_MODE_SEQ_NO);
+        assert_eq!(get_sequence_no(handle).unwrap(), ledger::TEST_MODE_SEQ_NO);
+        release(handle);
+    }
+
+    #[test]
+    fn test_get_sequence_no_from_ledger() {
+        set_test_mode();
+        let handle = create(None, SCHEMA_DATA.to_owned()).unwrap();
+        assert_eq!(get_sequence_no(handle).unwrap(), ledger::TEST_MODE_SEQ_NO);
+        assert!(to_string(handle).unwrap().contains("\"sequence_num\":15"));
+        release(handle);
+    }
+
+    #[test]
+    fn test_get_data() {
+        let handle = create(None, SCHEMA_DATA.to_owned()).unwrap();
+        let data: SchemaData = serde_json::from_str(&get_data(handle).unwrap()).unwrap();
+        assert_eq!(data.name, "test-schema");
+        assert_eq!(data.attr_names, vec!["name".to_string(), "age".to_string()]);
+        release(handle);
+    }
+
+    #[test]
+    fn test_from_string_succeeds() {
+        let handle = create(Some("test_from_string_succeeds".to_owned()), SCHEMA_DATA.to_owned()).unwrap();
+        let string = to_string(handle).unwrap();
+        release(handle);
+        let new_handle = from_string(&string).unwrap();
+        assert_eq!(new_handle, handle);
+        assert_eq!(to_string(new_handle).unwrap(), string);
+        release(new_handle);
+    }
+
+    #[test]
+    fn test_invalid_handle_fails() {
+        assert_eq!(release(0), error::INVALID_SCHEMA_HANDLE.code_num);
+        assert_eq!(commit(0), Err(error::INVALID_SCHEMA_HANDLE.code_num));
+        assert_eq!(to_string(0), Err(error::INVALID_SCHEMA_HANDLE.code_num));
+        assert_eq!(from_string("{}"), Err(error::INVALID_SCHEMA.code_num));
+    }
+}
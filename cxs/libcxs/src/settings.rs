@@ -24,6 +24,10 @@ pub static CONFIG_ENTERPRISE_DID_AGENT: &'static str = "enterprise_did_agent";
 pub static CONFIG_ENTERPRISE_NAME: &'static str = "enterprise_name";
 pub static CONFIG_LOGO_URL: &'static str = "logo_url";
 pub static CONFIG_ENABLE_TEST_MODE: &'static str = "enable_test_mode";
+pub static CONFIG_ENABLE_ENCRYPTION: &'static str = "enable_encryption";
+pub static CONFIG_MESSAGE_FORMAT: &'static str = "message_format";
+/// Seconds an invitation stays open before its connection expires, 0 leaving it to the agency.
+pub static CONFIG_INVITE_TTL: &'static str = "invite_ttl";
 
 lazy_static! {
     static ref SETTINGS: RwLock<Config> = RwLock::new(Config::default());
@@ -49,6 +53,9 @@ pub fn set_defaults() -> u32 {
     settings.set_default(CONFIG_ENTERPRISE_NAME,"default");
     settings.set_default(CONFIG_LOGO_URL,"http://www.evernym.com");
     settings.set_default(CONFIG_ENABLE_TEST_MODE,"false");
+    settings.set_default(CONFIG_ENABLE_ENCRYPTION,"false");
+    settings.set_default(CONFIG_MESSAGE_FORMAT,"json");
+    settings.set_default(CONFIG_INVITE_TTL,"0");
 
     error::SUCCESS.code_num
 }
@@ -95,6 +102,8 @@ fn validate_config() -> Result<u32, String> {
             valid = false;
         } else if setting.0 == CONFIG_ENTERPRISE_NAME && !is_valid(setting.1) {
             valid = false;
+        } else if setting.0 == CONFIG_INVITE_TTL && setting.1.parse::<u64>().is_err() {
+            valid = false;
         } else if setting.0 == CONFIG_LOGO_URL {
             match Url::parse(setting.1) {
                 Err(x) => valid = false,
@@ -129,6 +138,18 @@ pub fn test_mode_enabled() -> bool {
     }
 }
 
+/// Agent payloads are only encrypted when enable_encryption is "true", and never in test mode.
+pub fn encryption_enabled() -> bool {
+    if test_mode_enabled() { return false; }
+
+    let config = SETTINGS.read().unwrap();
+
+    match config.get_str(CONFIG_ENABLE_ENCRYPTION) {
+        Err(_) => false,
+        Ok(value) => value == "true",
+    }
+}
+
 pub fn process_config_file(path: &str) -> Result<u32, String> {
 
     if !Path::new(path).is_file() {
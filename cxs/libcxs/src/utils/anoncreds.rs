@@ -0,0 +1,246 @@
+extern crate libc;
+extern crate serde_json;
+extern crate sha2;
+
+use self::libc::c_char;
+use self::sha2::{Sha256, Digest};
+use std::ffi::CString;
+use std::ptr::null;
+use serde_json::Value;
+use settings;
+use utils::wallet;
+use utils::callback::{ReturnI32Str, ReturnI32StrStr, ReturnI32Bool, check_indy_call};
+
+pub static CLAIM_DEF_TEST_MODE: &'static str = "{\"ref\":15,\"origin\":\"4fUDR9R7fjwELRvH9JT6HH\",\"signature_type\":\"CL\",\"data\":{\"primary\":{\"n\":\"9\",\"s\":\"5\",\"rms\":\"4\",\"r\":{\"name\":\"1\",\"age\":\"2\",\"height\":\"3\",\"sex\":\"4\"},\"rctxt\":\"7\",\"z\":\"8\"},\"revocation\":null}}";
+pub static REVOC_REG_TEST_MODE: &'static str = "{\"issuer_did\":\"4fUDR9R7fjwELRvH9JT6HH\",\"schema_seq_no\":15,\"revocation_registry\":{\"acc\":{\"accumulator\":\"1\"},\"acc_pk\":{\"z\":\"1\"},\"max_claim_num\":100}}";
+pub static REVOC_REG_UUID_TEST_MODE: &'static str = "b6a3c0e9-8f46-4b24-a35c-2e4e8ac9e1b5";
+
+extern {
+    fn indy_issuer_create_and_store_claim_def(command_handle: i32,
+                                              wallet_handle: i32,
+                                              issuer_did: *const c_char,
+                                              schema_json: *const c_char,
+                                              signature_type: *const c_char,
+                                              create_non_revoc: bool,
+                                              cb: Option<extern fn(xcommand_handle: i32, err: i32, claim_def_json: *const c_char)>) -> i32;
+
+    fn indy_issuer_create_claim(command_handle: i32,
+                                wallet_handle: i32,
+                                claim_req_json: *const c_char,
+                                claim_json: *const c_char,
+                                user_revoc_index: i32,
+                                cb: Option<extern fn(xcommand_handle: i32, err: i32, revoc_reg_update_json: *const c_char, xclaim_json: *const c_char)>) -> i32;
+
+    fn indy_issuer_create_and_store_revoc_reg(command_handle: i32,
+                                              wallet_handle: i32,
+                                              issuer_did: *const c_char,
+                                              schema_seq_no: i32,
+                                              max_claim_num: i32,
+                                              cb: Option<extern fn(xcommand_handle: i32, err: i32, revoc_reg_json: *const c_char, revoc_reg_uuid: *const c_char)>) -> i32;
+
+    fn indy_issuer_revoke_claim(command_handle: i32,
+                                wallet_handle: i32,
+                                issuer_did: *const c_char,
+                                schema_seq_no: i32,
+                                user_revoc_index: i32,
+                                cb: Option<extern fn(xcommand_handle: i32, err: i32, revoc_reg_update_json: *const c_char)>) -> i32;
+
+    fn indy_verifier_verify_proof(command_handle: i32,
+                                  proof_request_json: *const c_char,
+                                  proof_json: *const c_char,
+                                  schemas_json: *const c_char,
+                                  claim_defs_jsons: *const c_char,
+                                  revoc_regs_json: *const c_char,
+                                  cb: Option<extern fn(xcommand_handle: i32, err: i32, valid: bool)>) -> i32;
+}
+
+pub fn issuer_create_and_store_claim_def(issuer_did: &str, schema_json: &str, create_non_revoc: bool) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(CLAIM_DEF_TEST_MODE.to_string()); }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    info!("creating claim def for issuer {} from schema {}", issuer_did, schema_json);
+    unsafe {
+        let indy_err = indy_issuer_create_and_store_claim_def(rtn_obj.command_handle,
+                                                              wallet::get_wallet_handle(),
+                                                              CString::new(issuer_did).unwrap().as_ptr(),
+                                                              CString::new(schema_json).unwrap().as_ptr(),
+                                                              null(),
+                                                              create_non_revoc,
+                                                              rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Signs the claim values for the holder's claim request.  Returns the revocation registry
+/// update and the signed claim; user_revoc_index is -1 for claims that can't be revoked.
+pub fn issuer_create_claim(claim_req_json: &str, claim_json: &str, user_revoc_index: i32) -> Result<(String, String), u32> {
+    if settings::test_mode_enabled() { return Ok(test_mode_claim(claim_req_json, claim_json)); }
+
+    let rtn_obj = ReturnI32StrStr::new();
+
+    info!("creating claim {} for claim request {}", claim_json, claim_req_json);
+    unsafe {
+        let indy_err = indy_issuer_create_claim(rtn_obj.command_handle,
+                                                wallet::get_wallet_handle(),
+                                                CString::new(claim_req_json).unwrap().as_ptr(),
+                                                CString::new(claim_json).unwrap().as_ptr(),
+                                                user_revoc_index,
+                                                rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+// TEST MODE: echo the claim values back with a placeholder signature.
+fn test_mode_claim(claim_req_json: &str, claim_json: &str) -> (String, String) {
+    let claim_req: Value = serde_json::from_str(claim_req_json).unwrap_or(Value::Null);
+    let claim: Value = serde_json::from_str(claim_json).unwrap_or(Value::Null);
+    let xclaim = json!({
+        "claim": claim,
+        "schema_seq_no": claim_req["schema_seq_no"],
+        "issuer_did": claim_req["issuer_did"],
+        "revoc_reg_seq_no": null,
+        "signature": {"primary_claim": {"m2": "1", "a": "2", "e": "3", "v": "4"}, "non_revocation_claim": null}
+    });
+    ("{}".to_string(), xclaim.to_string())
+}
+
+/// Encodes a claim value the way indy verifiers expect it: integers are used as they are,
+/// anything else is the sha256 digest of its utf-8 bytes read as a big-endian integer.
+pub fn encode_attribute(value: &str) -> String {
+    if value.parse::<u32>().is_ok() { return value.to_string(); }
+
+    let mut hasher = Sha256::default();
+    hasher.input(value.as_bytes());
+
+    // little-endian decimal digits
+    let mut digits: Vec<u8> = vec![0];
+    for byte in hasher.result().iter() {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            let x = *digit as u32 * 256 + carry;
+            *digit = (x % 10) as u8;
+            carry = x / 10;
+        }
+        while carry > 0 {
+            digits.push((carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+
+    digits.iter().rev().map(|x| (b'0' + x) as char).collect()
+}
+
+/// Creates the revocation registry for the issuer's claim definition.  Returns the registry
+/// and its uuid.
+pub fn issuer_create_and_store_revoc_reg(issuer_did: &str, schema_seq_no: u32, max_claim_num: u32) -> Result<(String, String), u32> {
+    if settings::test_mode_enabled() { return Ok((REVOC_REG_TEST_MODE.to_string(), REVOC_REG_UUID_TEST_MODE.to_string())); }
+
+    let rtn_obj = ReturnI32StrStr::new();
+
+    info!("creating revocation registry for issuer {} and schema {}", issuer_did, schema_seq_no);
+    unsafe {
+        let indy_err = indy_issuer_create_and_store_revoc_reg(rtn_obj.command_handle,
+                                                              wallet::get_wallet_handle(),
+                                                              CString::new(issuer_did).unwrap().as_ptr(),
+                                                              schema_seq_no as i32,
+                                                              max_claim_num as i32,
+                                                              rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Revokes the claim issued with user_revoc_index.  Returns the revocation registry update.
+pub fn issuer_revoke_claim(issuer_did: &str, schema_seq_no: u32, user_revoc_index: i32) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(REVOC_REG_TEST_MODE.to_string()); }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    info!("revoking claim {} for issuer {} and schema {}", user_revoc_index, issuer_did, schema_seq_no);
+    unsafe {
+        let indy_err = indy_issuer_revoke_claim(rtn_obj.command_handle,
+                                                wallet::get_wallet_handle(),
+                                                CString::new(issuer_did).unwrap().as_ptr(),
+                                                schema_seq_no as i32,
+                                                user_revoc_index,
+                                                rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+pub fn verifier_verify_proof(proof_request_json: &str,
+                             proof_json: &str,
+                             schemas_json: &str,
+                             claim_defs_json: &str,
+                             revoc_regs_json: &str) -> Result<bool, u32> {
+    if settings::test_mode_enabled() { return Ok(true); }
+
+    let rtn_obj = ReturnI32Bool::new();
+
+    info!("verifying proof {} for proof request {}", proof_json, proof_request_json);
+    unsafe {
+        let indy_err = indy_verifier_verify_proof(rtn_obj.command_handle,
+                                                  CString::new(proof_request_json).unwrap().as_ptr(),
+                                                  CString::new(proof_json).unwrap().as_ptr(),
+                                                  CString::new(schemas_json).unwrap().as_ptr(),
+                                                  CString::new(claim_defs_json).unwrap().as_ptr(),
+                                                  CString::new(revoc_regs_json).unwrap().as_ptr(),
+                                                  rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_claim_def_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let claim_def = issuer_create_and_store_claim_def("4fUDR9R7fjwELRvH9JT6HH", "{}", false).unwrap();
+        assert_eq!(claim_def, CLAIM_DEF_TEST_MODE);
+    }
+
+    #[test]
+    fn test_create_claim_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let claim_req = "{\"blinded_ms\":{\"prover_did\":\"FQ7wPBUgSPnDGJnS1EYjTK\",\"u\":\"1\",\"ur\":null},\"issuer_did\":\"4fUDR9R7fjwELRvH9JT6HH\",\"schema_seq_no\":15}";
+        let (_, xclaim) = issuer_create_claim(claim_req, "{\"name\":[\"Alex\",\"1\"]}", -1).unwrap();
+        let xclaim: Value = serde_json::from_str(&xclaim).unwrap();
+        assert_eq!(xclaim["claim"]["name"], json!(["Alex", "1"]));
+        assert_eq!(xclaim["schema_seq_no"], json!(15));
+        assert!(xclaim["signature"].is_object());
+    }
+
+    #[test]
+    fn test_encode_attribute() {
+        assert_eq!(encode_attribute("28"), "28");
+        assert_eq!(encode_attribute("male"), "5944657099558967239210949258394887428692050081607692519917050011144233115103");
+        assert_eq!(encode_attribute("Alex"), "99262857098057710338306967609588410025648622308394250666849665532448612202874");
+    }
+
+    #[test]
+    fn test_revocation_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let (revoc_reg, uuid) = issuer_create_and_store_revoc_reg("4fUDR9R7fjwELRvH9JT6HH", 15, 100).unwrap();
+        assert_eq!(revoc_reg, REVOC_REG_TEST_MODE);
+        assert_eq!(uuid, REVOC_REG_UUID_TEST_MODE);
+        assert!(issuer_revoke_claim("4fUDR9R7fjwELRvH9JT6HH", 15, 1).is_ok());
+    }
+
+    #[test]
+    fn test_verify_proof_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        assert_eq!(verifier_verify_proof("{}", "{}", "{}", "{}", "{}"), Ok(true));
+    }
+}
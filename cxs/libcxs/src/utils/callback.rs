@@ -0,0 +1,349 @@
+extern crate libc;
+
+use self::libc::c_char;
+use std::collections::HashMap;
+use std::slice;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+use utils::cstring::CStringUtils;
+use utils::error;
+use utils::generate_command_handle;
+use utils::init::indy_error_to_cxs_error_code;
+
+// libindy only reports results through callbacks, so every blocking call registers a
+// sender under its command handle and waits on the matching receiver.
+
+pub static DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+lazy_static! {
+    static ref CALLBACKS_I32: Mutex<HashMap<i32, Sender<i32>>> = Default::default();
+    static ref CALLBACKS_I32_I32: Mutex<HashMap<i32, Sender<(i32, i32)>>> = Default::default();
+    static ref CALLBACKS_I32_STR: Mutex<HashMap<i32, Sender<(i32, Option<String>)>>> = Default::default();
+    static ref CALLBACKS_I32_STR_STR: Mutex<HashMap<i32, Sender<(i32, Option<String>, Option<String>)>>> = Default::default();
+    static ref CALLBACKS_I32_STR_STR_STR: Mutex<HashMap<i32, Sender<(i32, Option<String>, Option<String>, Option<String>)>>> = Default::default();
+    static ref CALLBACKS_I32_BOOL: Mutex<HashMap<i32, Sender<(i32, bool)>>> = Default::default();
+    static ref CALLBACKS_I32_BIN: Mutex<HashMap<i32, Sender<(i32, Vec<u8>)>>> = Default::default();
+    static ref CALLBACKS_I32_BIN_BIN: Mutex<HashMap<i32, Sender<(i32, Vec<u8>, Vec<u8>)>>> = Default::default();
+}
+
+fn remove_sender<T>(map: &Mutex<HashMap<i32, Sender<T>>>, command_handle: i32) -> Option<Sender<T>> {
+    map.lock().unwrap().remove(&command_handle)
+}
+
+fn receive<T>(receiver: &Receiver<T>, command_handle: i32) -> Result<T, u32> {
+    match receiver.recv_timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS)) {
+        Ok(x) => Ok(x),
+        Err(_) => {
+            error!("timed out waiting for libindy callback on command handle {}", command_handle);
+            Err(error::TIMEOUT_LIBINDY_ERROR.code_num)
+        },
+    }
+}
+
+fn check_err(err: i32) -> Result<(), u32> {
+    match indy_error_to_cxs_error_code(err) {
+        0 => Ok(()),
+        x => Err(x),
+    }
+}
+
+extern "C" fn call_cb_i32(command_handle: i32, err: i32) {
+    if let Some(sender) = remove_sender(&CALLBACKS_I32, command_handle) {
+        sender.send(err).unwrap_or(());
+    }
+}
+
+extern "C" fn call_cb_i32_i32(command_handle: i32, err: i32, arg1: i32) {
+    if let Some(sender) = remove_sender(&CALLBACKS_I32_I32, command_handle) {
+        sender.send((err, arg1)).unwrap_or(());
+    }
+}
+
+extern "C" fn call_cb_i32_str(command_handle: i32, err: i32, arg1: *const c_char) {
+    let arg1 = CStringUtils::c_str_to_string(arg1).unwrap_or(None);
+    if let Some(sender) = remove_sender(&CALLBACKS_I32_STR, command_handle) {
+        sender.send((err, arg1)).unwrap_or(());
+    }
+}
+
+extern "C" fn call_cb_i32_str_str(command_handle: i32, err: i32, arg1: *const c_char, arg2: *const c_char) {
+    let arg1 = CStringUtils::c_str_to_string(arg1).unwrap_or(None);
+    let arg2 = CStringUtils::c_str_to_string(arg2).unwrap_or(None);
+    if let Some(sender) = remove_sender(&CALLBACKS_I32_STR_STR, command_handle) {
+        sender.send((err, arg1, arg2)).unwrap_or(());
+    }
+}
+
+extern "C" fn call_cb_i32_str_str_str(command_handle: i32, err: i32, arg1: *const c_char, arg2: *const c_char, arg3: *const c_char) {
+    let arg1 = CStringUtils::c_str_to_string(arg1).unwrap_or(None);
+    let arg2 = CStringUtils::c_str_to_string(arg2).unwrap_or(None);
+    let arg3 = CStringUtils::c_str_to_string(arg3).unwrap_or(None);
+    if let Some(sender) = remove_sender(&CALLBACKS_I32_STR_STR_STR, command_handle) {
+        sender.send((err, arg1, arg2, arg3)).unwrap_or(());
+    }
+}
+
+extern "C" fn call_cb_i32_bool(command_handle: i32, err: i32, arg1: bool) {
+    if let Some(sender) = remove_sender(&CALLBACKS_I32_BOOL, command_handle) {
+        sender.send((err, arg1)).unwrap_or(());
+    }
+}
+
+// libindy owns the buffers it hands to a callback, so they are copied before returning.
+fn copy_bytes(data: *const u8, len: u32) -> Vec<u8> {
+    if data.is_null() { return Vec::new(); }
+    unsafe { slice::from_raw_parts(data, len as usize).to_vec() }
+}
+
+extern "C" fn call_cb_i32_bin(command_handle: i32, err: i32, arg1: *const u8, arg1_len: u32) {
+    let arg1 = copy_bytes(arg1, arg1_len);
+    if let Some(sender) = remove_sender(&CALLBACKS_I32_BIN, command_handle) {
+        sender.send((err, arg1)).unwrap_or(());
+    }
+}
+
+extern "C" fn call_cb_i32_bin_bin(command_handle: i32, err: i32, arg1: *const u8, arg1_len: u32, arg2: *const u8, arg2_len: u32) {
+    let arg1 = copy_bytes(arg1, arg1_len);
+    let arg2 = copy_bytes(arg2, arg2_len);
+    if let Some(sender) = remove_sender(&CALLBACKS_I32_BIN_BIN, command_handle) {
+        sender.send((err, arg1, arg2)).unwrap_or(());
+    }
+}
+
+/// Result of a libindy call whose callback only carries an error code.
+pub struct ReturnI32 {
+    pub command_handle: i32,
+    receiver: Receiver<i32>,
+}
+
+impl ReturnI32 {
+    pub fn new() -> ReturnI32 {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32.lock().unwrap().insert(command_handle, sender);
+        ReturnI32 { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32)> { Some(call_cb_i32) }
+
+    pub fn receive(&self) -> Result<(), u32> {
+        let err = receive(&self.receiver, self.command_handle)?;
+        check_err(err)
+    }
+}
+
+/// Result of a libindy call whose callback carries a single handle.
+pub struct ReturnI32I32 {
+    pub command_handle: i32,
+    receiver: Receiver<(i32, i32)>,
+}
+
+impl ReturnI32I32 {
+    pub fn new() -> ReturnI32I32 {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32_I32.lock().unwrap().insert(command_handle, sender);
+        ReturnI32I32 { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32, i32)> { Some(call_cb_i32_i32) }
+
+    pub fn receive(&self) -> Result<i32, u32> {
+        let (err, arg1) = receive(&self.receiver, self.command_handle)?;
+        check_err(err)?;
+        Ok(arg1)
+    }
+}
+
+/// Result of a libindy call whose callback carries a single string (usually json).
+pub struct ReturnI32Str {
+    pub command_handle: i32,
+    receiver: Receiver<(i32, Option<String>)>,
+}
+
+impl ReturnI32Str {
+    pub fn new() -> ReturnI32Str {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32_STR.lock().unwrap().insert(command_handle, sender);
+        ReturnI32Str { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32, *const c_char)> { Some(call_cb_i32_str) }
+
+    pub fn receive(&self) -> Result<String, u32> {
+        let (err, arg1) = receive(&self.receiver, self.command_handle)?;
+        check_err(err)?;
+        Ok(arg1.unwrap_or_default())
+    }
+}
+
+/// Result of a libindy call whose callback carries two strings.
+pub struct ReturnI32StrStr {
+    pub command_handle: i32,
+    receiver: Receiver<(i32, Option<String>, Option<String>)>,
+}
+
+impl ReturnI32StrStr {
+    pub fn new() -> ReturnI32StrStr {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32_STR_STR.lock().unwrap().insert(command_handle, sender);
+        ReturnI32StrStr { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32, *const c_char, *const c_char)> { Some(call_cb_i32_str_str) }
+
+    pub fn receive(&self) -> Result<(String, String), u32> {
+        let (err, arg1, arg2) = receive(&self.receiver, self.command_handle)?;
+        check_err(err)?;
+        Ok((arg1.unwrap_or_default(), arg2.unwrap_or_default()))
+    }
+}
+
+/// Result of a libindy call whose callback carries three strings (a new did, its verkey and pk).
+pub struct ReturnI32StrStrStr {
+    pub command_handle: i32,
+    receiver: Receiver<(i32, Option<String>, Option<String>, Option<String>)>,
+}
+
+impl ReturnI32StrStrStr {
+    pub fn new() -> ReturnI32StrStrStr {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32_STR_STR_STR.lock().unwrap().insert(command_handle, sender);
+        ReturnI32StrStrStr { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32, *const c_char, *const c_char, *const c_char)> { Some(call_cb_i32_str_str_str) }
+
+    pub fn receive(&self) -> Result<(String, String, String), u32> {
+        let (err, arg1, arg2, arg3) = receive(&self.receiver, self.command_handle)?;
+        check_err(err)?;
+        Ok((arg1.unwrap_or_default(), arg2.unwrap_or_default(), arg3.unwrap_or_default()))
+    }
+}
+
+/// Result of a libindy call whose callback carries a boolean (verifications).
+pub struct ReturnI32Bool {
+    pub command_handle: i32,
+    receiver: Receiver<(i32, bool)>,
+}
+
+impl ReturnI32Bool {
+    pub fn new() -> ReturnI32Bool {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32_BOOL.lock().unwrap().insert(command_handle, sender);
+        ReturnI32Bool { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32, bool)> { Some(call_cb_i32_bool) }
+
+    pub fn receive(&self) -> Result<bool, u32> {
+        let (err, arg1) = receive(&self.receiver, self.command_handle)?;
+        check_err(err)?;
+        Ok(arg1)
+    }
+}
+
+/// Result of a libindy call whose callback carries a byte buffer (decrypted messages).
+pub struct ReturnI32Bin {
+    pub command_handle: i32,
+    receiver: Receiver<(i32, Vec<u8>)>,
+}
+
+impl ReturnI32Bin {
+    pub fn new() -> ReturnI32Bin {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32_BIN.lock().unwrap().insert(command_handle, sender);
+        ReturnI32Bin { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32, *const u8, u32)> { Some(call_cb_i32_bin) }
+
+    pub fn receive(&self) -> Result<Vec<u8>, u32> {
+        let (err, arg1) = receive(&self.receiver, self.command_handle)?;
+        check_err(err)?;
+        Ok(arg1)
+    }
+}
+
+/// Result of a libindy call whose callback carries two byte buffers (a message and its nonce).
+pub struct ReturnI32BinBin {
+    pub command_handle: i32,
+    receiver: Receiver<(i32, Vec<u8>, Vec<u8>)>,
+}
+
+impl ReturnI32BinBin {
+    pub fn new() -> ReturnI32BinBin {
+        let (sender, receiver) = channel();
+        let command_handle = generate_command_handle();
+        CALLBACKS_I32_BIN_BIN.lock().unwrap().insert(command_handle, sender);
+        ReturnI32BinBin { command_handle: command_handle, receiver: receiver }
+    }
+
+    pub fn get_callback(&self) -> Option<extern fn(i32, i32, *const u8, u32, *const u8, u32)> { Some(call_cb_i32_bin_bin) }
+
+    pub fn receive(&self) -> Result<(Vec<u8>, Vec<u8>), u32> {
+        let (err, arg1, arg2) = receive(&self.receiver, self.command_handle)?;
+        check_err(err)?;
+        Ok((arg1, arg2))
+    }
+}
+
+/// Returns the error code if libindy refused the call, so the callback will never fire.
+pub fn check_indy_call(command_handle: i32, indy_err: i32) -> Result<(), u32> {
+    if indy_err != 0 {
+        warn!("libindy call with command handle {} returned {}", command_handle, indy_err);
+        CALLBACKS_I32.lock().unwrap().remove(&command_handle);
+        CALLBACKS_I32_I32.lock().unwrap().remove(&command_handle);
+        CALLBACKS_I32_STR.lock().unwrap().remove(&command_handle);
+        CALLBACKS_I32_STR_STR.lock().unwrap().remove(&command_handle);
+        CALLBACKS_I32_STR_STR_STR.lock().unwrap().remove(&command_handle);
+        CALLBACKS_I32_BOOL.lock().unwrap().remove(&command_handle);
+        CALLBACKS_I32_BIN.lock().unwrap().remove(&command_handle);
+        CALLBACKS_I32_BIN_BIN.lock().unwrap().remove(&command_handle);
+    }
+    check_err(indy_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_return_i32_str_receives_callback() {
+        let rtn = ReturnI32Str::new();
+        let cb = rtn.get_callback().unwrap();
+        cb(rtn.command_handle, 0, CString::new("{\"a\":1}").unwrap().as_ptr());
+        assert_eq!(rtn.receive().unwrap(), "{\"a\":1}");
+    }
+
+    #[test]
+    fn test_return_i32_reports_indy_error() {
+        let rtn = ReturnI32::new();
+        let cb = rtn.get_callback().unwrap();
+        cb(rtn.command_handle, 206);
+        assert_eq!(rtn.receive(), Err(error::UNKNOWN_ERROR.code_num));
+    }
+
+    #[test]
+    fn test_return_i32_bin_bin_copies_buffers() {
+        let rtn = ReturnI32BinBin::new();
+        let cb = rtn.get_callback().unwrap();
+        let msg = vec![1u8, 2, 3];
+        let nonce = vec![9u8; 24];
+        cb(rtn.command_handle, 0, msg.as_ptr(), msg.len() as u32, nonce.as_ptr(), nonce.len() as u32);
+        assert_eq!(rtn.receive().unwrap(), (msg, nonce));
+    }
+
+    #[test]
+    fn test_check_indy_call_fails() {
+        let rtn = ReturnI32Bool::new();
+        assert_eq!(check_indy_call(rtn.command_handle, 100), Err(error::UNKNOWN_ERROR.code_num));
+    }
+}
@@ -0,0 +1,187 @@
+extern crate libc;
+
+use self::libc::c_char;
+use std::ffi::CString;
+use settings;
+use utils::pool;
+use utils::wallet;
+use utils::callback::{ReturnI32, ReturnI32Bin, ReturnI32BinBin, ReturnI32Bool, check_indy_call};
+
+pub static NONCE_TEST_MODE: &'static [u8] = &[0u8; 24];
+pub static SIGNATURE_TEST_MODE: &'static [u8] = &[1u8; 64];
+
+extern {
+    fn indy_store_their_did(command_handle: i32,
+                            wallet_handle: i32,
+                            identity_json: *const c_char,
+                            cb: Option<extern fn(xcommand_handle: i32, err: i32)>) -> i32;
+
+    fn indy_encrypt(command_handle: i32,
+                    wallet_handle: i32,
+                    pool_handle: i32,
+                    my_did: *const c_char,
+                    did: *const c_char,
+                    msg_data: *const u8,
+                    msg_len: u32,
+                    cb: Option<extern fn(xcommand_handle: i32, err: i32,
+                                         encrypted_msg: *const u8, encrypted_len: u32,
+                                         nonce: *const u8, nonce_len: u32)>) -> i32;
+
+    fn indy_decrypt(command_handle: i32,
+                    wallet_handle: i32,
+                    my_did: *const c_char,
+                    did: *const c_char,
+                    encrypted_msg: *const u8,
+                    encrypted_len: u32,
+                    nonce: *const u8,
+                    nonce_len: u32,
+                    cb: Option<extern fn(xcommand_handle: i32, err: i32,
+                                         decrypted_msg: *const u8, decrypted_len: u32)>) -> i32;
+
+    fn indy_sign(command_handle: i32,
+                 wallet_handle: i32,
+                 did: *const c_char,
+                 msg_data: *const u8,
+                 msg_len: u32,
+                 cb: Option<extern fn(xcommand_handle: i32, err: i32,
+                                      signature: *const u8, signature_len: u32)>) -> i32;
+
+    fn indy_verify_signature(command_handle: i32,
+                             wallet_handle: i32,
+                             pool_handle: i32,
+                             did: *const c_char,
+                             msg_data: *const u8,
+                             msg_len: u32,
+                             signature: *const u8,
+                             signature_len: u32,
+                             cb: Option<extern fn(xcommand_handle: i32, err: i32, valid: bool)>) -> i32;
+}
+
+/// Stores the verkey of a remote did in the wallet so encrypt doesn't have to look it up on the ledger.
+pub fn store_their_did(did: &str, verkey: &str) -> Result<(), u32> {
+    if settings::test_mode_enabled() { return Ok(()); }
+
+    let rtn_obj = ReturnI32::new();
+    let identity_json = json!({"did": did, "verkey": verkey}).to_string();
+
+    unsafe {
+        let indy_err = indy_store_their_did(rtn_obj.command_handle,
+                                            wallet::get_wallet_handle(),
+                                            CString::new(identity_json).unwrap().as_ptr(),
+                                            rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Encrypts msg for their_did and authenticates it with the key of my_did, which must be in
+/// the wallet.  Returns the encrypted message and its nonce.
+pub fn encrypt(my_did: &str, their_did: &str, msg: &[u8]) -> Result<(Vec<u8>, Vec<u8>), u32> {
+    if settings::test_mode_enabled() { return Ok((msg.to_vec(), NONCE_TEST_MODE.to_vec())); }
+
+    let pool_handle = pool::get_pool_handle()?;
+    let rtn_obj = ReturnI32BinBin::new();
+
+    debug!("encrypting message from {} to {}", my_did, their_did);
+    unsafe {
+        let indy_err = indy_encrypt(rtn_obj.command_handle,
+                                    wallet::get_wallet_handle(),
+                                    pool_handle,
+                                    CString::new(my_did).unwrap().as_ptr(),
+                                    CString::new(their_did).unwrap().as_ptr(),
+                                    msg.as_ptr(),
+                                    msg.len() as u32,
+                                    rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Decrypts a message that their_did encrypted for my_did.  Fails if the message wasn't
+/// authenticated with their_did's key.
+pub fn decrypt(my_did: &str, their_did: &str, encrypted_msg: &[u8], nonce: &[u8]) -> Result<Vec<u8>, u32> {
+    if settings::test_mode_enabled() { return Ok(encrypted_msg.to_vec()); }
+
+    let rtn_obj = ReturnI32Bin::new();
+
+    debug!("decrypting message from {} to {}", their_did, my_did);
+    unsafe {
+        let indy_err = indy_decrypt(rtn_obj.command_handle,
+                                    wallet::get_wallet_handle(),
+                                    CString::new(my_did).unwrap().as_ptr(),
+                                    CString::new(their_did).unwrap().as_ptr(),
+                                    encrypted_msg.as_ptr(),
+                                    encrypted_msg.len() as u32,
+                                    nonce.as_ptr(),
+                                    nonce.len() as u32,
+                                    rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Signs msg with the key of my_did, which must be in the wallet.
+pub fn sign(my_did: &str, msg: &[u8]) -> Result<Vec<u8>, u32> {
+    if settings::test_mode_enabled() { return Ok(SIGNATURE_TEST_MODE.to_vec()); }
+
+    let rtn_obj = ReturnI32Bin::new();
+
+    debug!("signing message with the key of {}", my_did);
+    unsafe {
+        let indy_err = indy_sign(rtn_obj.command_handle,
+                                 wallet::get_wallet_handle(),
+                                 CString::new(my_did).unwrap().as_ptr(),
+                                 msg.as_ptr(),
+                                 msg.len() as u32,
+                                 rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Checks that signature is their_did's signature of msg.  Store their_did first to avoid
+/// a lookup on the ledger.
+pub fn verify(their_did: &str, msg: &[u8], signature: &[u8]) -> Result<bool, u32> {
+    if settings::test_mode_enabled() { return Ok(signature == SIGNATURE_TEST_MODE); }
+
+    let pool_handle = pool::get_pool_handle()?;
+    let rtn_obj = ReturnI32Bool::new();
+
+    unsafe {
+        let indy_err = indy_verify_signature(rtn_obj.command_handle,
+                                             wallet::get_wallet_handle(),
+                                             pool_handle,
+                                             CString::new(their_did).unwrap().as_ptr(),
+                                             msg.as_ptr(),
+                                             msg.len() as u32,
+                                             signature.as_ptr(),
+                                             signature.len() as u32,
+                                             rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let (encrypted, nonce) = encrypt("8XFh8yBzrpJQmNyZzgoTqB", "KkTVEE7RGg7z2d2hrfM2Hj", b"hello").unwrap();
+        assert_eq!(nonce.len(), 24);
+        let decrypted = decrypt("KkTVEE7RGg7z2d2hrfM2Hj", "8XFh8yBzrpJQmNyZzgoTqB", &encrypted, &nonce).unwrap();
+        assert_eq!(decrypted, b"hello".to_vec());
+        assert_eq!(store_their_did("KkTVEE7RGg7z2d2hrfM2Hj", "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A"), Ok(()));
+
+        let signature = sign("8XFh8yBzrpJQmNyZzgoTqB", b"hello").unwrap();
+        assert_eq!(verify("8XFh8yBzrpJQmNyZzgoTqB", b"hello", &signature), Ok(true));
+        assert_eq!(verify("8XFh8yBzrpJQmNyZzgoTqB", b"hello", b"forged"), Ok(false));
+    }
+}
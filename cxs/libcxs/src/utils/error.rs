@@ -23,6 +23,38 @@ pub static INVALID_KEY_DELEGATE: Error = Error{code_num:1012, message:"Invalid D
 pub static INVALID_URL: Error = Error{code_num:1013, message:"Invalid URL"};
 pub static NOT_BASE58: Error = Error{code_num:1014, message:"Value needs to be base58"};
 pub static INVALID_ISSUER_CLAIM_HANDLE: Error = Error{code_num:1015, message:"Invalid Claim Issuer Handle"};
+pub static INVALID_SCHEMA: Error = Error{code_num:1016, message:"Invalid Schema"};
+pub static INVALID_SCHEMA_HANDLE: Error = Error{code_num:1017, message:"Invalid Schema Handle"};
+pub static INVALID_LEDGER_RESPONSE: Error = Error{code_num:1018, message:"Invalid response from the ledger"};
+pub static TIMEOUT_LIBINDY_ERROR: Error = Error{code_num:1019, message:"Waiting for callback timed out"};
+pub static INVALID_SCHEMA_SEQ_NO: Error = Error{code_num:1020, message:"No Schema for that name and version on the ledger"};
+pub static INVALID_CLAIM_DEF_HANDLE: Error = Error{code_num:1021, message:"Invalid Claim Definition Handle"};
+pub static INVALID_PROOF_HANDLE: Error = Error{code_num:1022, message:"Invalid Proof Handle"};
+pub static INVALID_PROOF_REQUEST: Error = Error{code_num:1023, message:"Invalid Proof Request"};
+pub static INVALID_HTTP_RESPONSE: Error = Error{code_num:1024, message:"Invalid response from the agency"};
+pub static INVALID_PROOF: Error = Error{code_num:1025, message:"Proof is invalid"};
+pub static INVALID_CLAIM_REQUEST: Error = Error{code_num:1026, message:"Invalid Claim Request"};
+pub static MISSING_CLAIM_ATTRIBUTES: Error = Error{code_num:1027, message:"Claim data is missing attributes of the schema"};
+pub static UNKNOWN_CLAIM_ATTRIBUTES: Error = Error{code_num:1028, message:"Claim data has attributes that are not in the schema"};
+pub static INVALID_CLAIM_ATTRIBUTES: Error = Error{code_num:1029, message:"Claim data has malformed attribute values"};
+pub static INSUFFICIENT_STATUS_ARRAY: Error = Error{code_num:1030, message:"Status array is too small for all objects"};
+pub static INVALID_PROOF_SIGNATURE: Error = Error{code_num:1031, message:"Proof signature is invalid"};
+pub static UNKNOWN_CLAIM_DEF: Error = Error{code_num:1032, message:"Claim definition referenced by the proof is not on the ledger"};
+pub static INVALID_PROOF_NONCE: Error = Error{code_num:1033, message:"Proof nonce does not match the proof request"};
+pub static INVALID_ENCRYPTED_RESPONSE: Error = Error{code_num:1034, message:"Agency response could not be decrypted"};
+pub static UNSUPPORTED_PROTOCOL_VERSION: Error = Error{code_num:1035, message:"Unsupported agent message protocol version"};
+pub static INVALID_MSG_TYPE: Error = Error{code_num:1036, message:"Message type is reserved or malformed"};
+pub static UNKNOWN_MSG_TYPE: Error = Error{code_num:1037, message:"Message type has not been registered"};
+pub static INVALID_MSG_PAYLOAD: Error = Error{code_num:1038, message:"Message payload is missing fields of its message type"};
+pub static UNKNOWN_MSG_UID: Error = Error{code_num:1039, message:"No message with that uid for the connection"};
+pub static INVALID_MSG_STATUS: Error = Error{code_num:1040, message:"Message status update needs message uids and a status code"};
+pub static INVALID_INVITE_DETAILS: Error = Error{code_num:1041, message:"Invite details are not valid json or miss the sender's keys"};
+pub static QR_CODE_ERROR: Error = Error{code_num:1042, message:"Could not encode the invitation as a QR code"};
+pub static INVALID_STATE_TRANSITION: Error = Error{code_num:1043, message:"Connection can not move from its current state to the requested one"};
+pub static CONNECTION_NOT_FOUND: Error = Error{code_num:1044, message:"No connection matches the given key"};
+pub static INVALID_SERIALIZED_CONNECTION: Error = Error{code_num:1045, message:"Serialized connection is not valid or has an unsupported version"};
+pub static REVOCATION_REGISTRY_FULL: Error = Error{code_num:1046, message:"Revocation registry of the claim definition has no index left for another claim"};
+pub static WALLET_RECORD_NOT_FOUND: Error = Error{code_num:1047, message:"Wallet or wallet record not found"};
 
 lazy_static! {
     static ref ERROR_MESSAGES: HashMap<u32, &'static str> = {
@@ -35,6 +67,38 @@ lazy_static! {
         insert_message(&mut m, &INVALID_OPTION);
         insert_message(&mut m, &NOT_READY);
         insert_message(&mut m, &NO_ENDPOINT);
+        insert_message(&mut m, &INVALID_SCHEMA);
+        insert_message(&mut m, &INVALID_SCHEMA_HANDLE);
+        insert_message(&mut m, &INVALID_LEDGER_RESPONSE);
+        insert_message(&mut m, &TIMEOUT_LIBINDY_ERROR);
+        insert_message(&mut m, &INVALID_SCHEMA_SEQ_NO);
+        insert_message(&mut m, &INVALID_CLAIM_DEF_HANDLE);
+        insert_message(&mut m, &INVALID_PROOF_HANDLE);
+        insert_message(&mut m, &INVALID_PROOF_REQUEST);
+        insert_message(&mut m, &INVALID_HTTP_RESPONSE);
+        insert_message(&mut m, &INVALID_PROOF);
+        insert_message(&mut m, &INVALID_CLAIM_REQUEST);
+        insert_message(&mut m, &MISSING_CLAIM_ATTRIBUTES);
+        insert_message(&mut m, &UNKNOWN_CLAIM_ATTRIBUTES);
+        insert_message(&mut m, &INVALID_CLAIM_ATTRIBUTES);
+        insert_message(&mut m, &INSUFFICIENT_STATUS_ARRAY);
+        insert_message(&mut m, &INVALID_PROOF_SIGNATURE);
+        insert_message(&mut m, &UNKNOWN_CLAIM_DEF);
+        insert_message(&mut m, &INVALID_PROOF_NONCE);
+        insert_message(&mut m, &INVALID_ENCRYPTED_RESPONSE);
+        insert_message(&mut m, &UNSUPPORTED_PROTOCOL_VERSION);
+        insert_message(&mut m, &INVALID_MSG_TYPE);
+        insert_message(&mut m, &UNKNOWN_MSG_TYPE);
+        insert_message(&mut m, &INVALID_MSG_PAYLOAD);
+        insert_message(&mut m, &UNKNOWN_MSG_UID);
+        insert_message(&mut m, &INVALID_MSG_STATUS);
+        insert_message(&mut m, &INVALID_INVITE_DETAILS);
+        insert_message(&mut m, &QR_CODE_ERROR);
+        insert_message(&mut m, &INVALID_STATE_TRANSITION);
+        insert_message(&mut m, &CONNECTION_NOT_FOUND);
+        insert_message(&mut m, &INVALID_SERIALIZED_CONNECTION);
+        insert_message(&mut m, &REVOCATION_REGISTRY_FULL);
+        insert_message(&mut m, &WALLET_RECORD_NOT_FOUND);
         m
     };
 
@@ -129,4 +193,164 @@ mod tests {
     fn test_invalid_option_error(){
         assert_eq!(error_message(&INVALID_OPTION.code_num), INVALID_OPTION.message);
     }
+
+    #[test]
+    fn test_invalid_schema_error(){
+        assert_eq!(error_message(&INVALID_SCHEMA.code_num), INVALID_SCHEMA.message);
+    }
+
+    #[test]
+    fn test_invalid_schema_handle_error(){
+        assert_eq!(error_message(&INVALID_SCHEMA_HANDLE.code_num), INVALID_SCHEMA_HANDLE.message);
+    }
+
+    #[test]
+    fn test_invalid_ledger_response_error(){
+        assert_eq!(error_message(&INVALID_LEDGER_RESPONSE.code_num), INVALID_LEDGER_RESPONSE.message);
+    }
+
+    #[test]
+    fn test_timeout_libindy_error(){
+        assert_eq!(error_message(&TIMEOUT_LIBINDY_ERROR.code_num), TIMEOUT_LIBINDY_ERROR.message);
+    }
+
+    #[test]
+    fn test_invalid_schema_seq_no_error(){
+        assert_eq!(error_message(&INVALID_SCHEMA_SEQ_NO.code_num), INVALID_SCHEMA_SEQ_NO.message);
+    }
+
+    #[test]
+    fn test_invalid_claim_def_handle_error(){
+        assert_eq!(error_message(&INVALID_CLAIM_DEF_HANDLE.code_num), INVALID_CLAIM_DEF_HANDLE.message);
+    }
+
+    #[test]
+    fn test_invalid_proof_handle_error(){
+        assert_eq!(error_message(&INVALID_PROOF_HANDLE.code_num), INVALID_PROOF_HANDLE.message);
+    }
+
+    #[test]
+    fn test_invalid_proof_request_error(){
+        assert_eq!(error_message(&INVALID_PROOF_REQUEST.code_num), INVALID_PROOF_REQUEST.message);
+    }
+
+    #[test]
+    fn test_invalid_http_response_error(){
+        assert_eq!(error_message(&INVALID_HTTP_RESPONSE.code_num), INVALID_HTTP_RESPONSE.message);
+    }
+
+    #[test]
+    fn test_invalid_proof_error(){
+        assert_eq!(error_message(&INVALID_PROOF.code_num), INVALID_PROOF.message);
+    }
+
+    #[test]
+    fn test_invalid_claim_request_error(){
+        assert_eq!(error_message(&INVALID_CLAIM_REQUEST.code_num), INVALID_CLAIM_REQUEST.message);
+    }
+
+    #[test]
+    fn test_missing_claim_attributes_error(){
+        assert_eq!(error_message(&MISSING_CLAIM_ATTRIBUTES.code_num), MISSING_CLAIM_ATTRIBUTES.message);
+    }
+
+    #[test]
+    fn test_unknown_claim_attributes_error(){
+        assert_eq!(error_message(&UNKNOWN_CLAIM_ATTRIBUTES.code_num), UNKNOWN_CLAIM_ATTRIBUTES.message);
+    }
+
+    #[test]
+    fn test_invalid_claim_attributes_error(){
+        assert_eq!(error_message(&INVALID_CLAIM_ATTRIBUTES.code_num), INVALID_CLAIM_ATTRIBUTES.message);
+    }
+
+    #[test]
+    fn test_insufficient_status_array_error(){
+        assert_eq!(error_message(&INSUFFICIENT_STATUS_ARRAY.code_num), INSUFFICIENT_STATUS_ARRAY.message);
+    }
+
+    #[test]
+    fn test_invalid_proof_signature_error(){
+        assert_eq!(error_message(&INVALID_PROOF_SIGNATURE.code_num), INVALID_PROOF_SIGNATURE.message);
+    }
+
+    #[test]
+    fn test_unknown_claim_def_error(){
+        assert_eq!(error_message(&UNKNOWN_CLAIM_DEF.code_num), UNKNOWN_CLAIM_DEF.message);
+    }
+
+    #[test]
+    fn test_invalid_proof_nonce_error(){
+        assert_eq!(error_message(&INVALID_PROOF_NONCE.code_num), INVALID_PROOF_NONCE.message);
+    }
+
+    #[test]
+    fn test_invalid_encrypted_response_error(){
+        assert_eq!(error_message(&INVALID_ENCRYPTED_RESPONSE.code_num), INVALID_ENCRYPTED_RESPONSE.message);
+    }
+
+    #[test]
+    fn test_unsupported_protocol_version_error(){
+        assert_eq!(error_message(&UNSUPPORTED_PROTOCOL_VERSION.code_num), UNSUPPORTED_PROTOCOL_VERSION.message);
+    }
+
+    #[test]
+    fn test_invalid_msg_type_error(){
+        assert_eq!(error_message(&INVALID_MSG_TYPE.code_num), INVALID_MSG_TYPE.message);
+    }
+
+    #[test]
+    fn test_unknown_msg_type_error(){
+        assert_eq!(error_message(&UNKNOWN_MSG_TYPE.code_num), UNKNOWN_MSG_TYPE.message);
+    }
+
+    #[test]
+    fn test_invalid_msg_payload_error(){
+        assert_eq!(error_message(&INVALID_MSG_PAYLOAD.code_num), INVALID_MSG_PAYLOAD.message);
+    }
+
+    #[test]
+    fn test_unknown_msg_uid_error(){
+        assert_eq!(error_message(&UNKNOWN_MSG_UID.code_num), UNKNOWN_MSG_UID.message);
+    }
+
+    #[test]
+    fn test_invalid_msg_status_error(){
+        assert_eq!(error_message(&INVALID_MSG_STATUS.code_num), INVALID_MSG_STATUS.message);
+    }
+
+    #[test]
+    fn test_invalid_invite_details_error(){
+        assert_eq!(error_message(&INVALID_INVITE_DETAILS.code_num), INVALID_INVITE_DETAILS.message);
+    }
+
+    #[test]
+    fn test_qr_code_error(){
+        assert_eq!(error_message(&QR_CODE_ERROR.code_num), QR_CODE_ERROR.message);
+    }
+
+    #[test]
+    fn test_invalid_state_transition_error(){
+        assert_eq!(error_message(&INVALID_STATE_TRANSITION.code_num), INVALID_STATE_TRANSITION.message);
+    }
+
+    #[test]
+    fn test_connection_not_found_error(){
+        assert_eq!(error_message(&CONNECTION_NOT_FOUND.code_num), CONNECTION_NOT_FOUND.message);
+    }
+
+    #[test]
+    fn test_invalid_serialized_connection_error(){
+        assert_eq!(error_message(&INVALID_SERIALIZED_CONNECTION.code_num), INVALID_SERIALIZED_CONNECTION.message);
+    }
+
+    #[test]
+    fn test_revocation_registry_full_error(){
+        assert_eq!(error_message(&REVOCATION_REGISTRY_FULL.code_num), REVOCATION_REGISTRY_FULL.message);
+    }
+
+    #[test]
+    fn test_wallet_record_not_found_error(){
+        assert_eq!(error_message(&WALLET_RECORD_NOT_FOUND.code_num), WALLET_RECORD_NOT_FOUND.message);
+    }
 }
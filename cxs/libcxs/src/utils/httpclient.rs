@@ -1,12 +1,32 @@
 use settings;
 use std::io::Read;
 use reqwest;
+use reqwest::header::ContentType;
+use messages::format::JSON_CONTENT_TYPE;
+
+pub static TEST_MODE_RESPONSE: &'static str = "test_mode_response";
 
 pub fn post(body_content: &str, url: &str) -> Result<String,String> {
-    let client = reqwest::Client::new();
     info!("Posting \"{}\" to: \"{}\"", body_content, url);
-    if settings::test_mode_enabled() {return Ok("test_mode_response".to_owned());}
-    let mut response = match  client.post(url).body(body_content.to_owned()).send() {
+    let (content, _) = post_u8(body_content.as_bytes(), url, JSON_CONTENT_TYPE)?;
+
+    match String::from_utf8(content) {
+        Ok(x) => {info!("Response: {}", x); Ok(x)},
+        Err(_) => Err("could not read response".to_string()),
+    }
+}
+
+/// Posts a body that isn't json (e.g. msgpack) and returns the raw response body with the
+/// content type of the response, which is empty when it doesn't name one.
+pub fn post_u8(body_content: &[u8], url: &str, content_type: &str) -> Result<(Vec<u8>, String),String> {
+    let client = reqwest::Client::new();
+    info!("Posting {} bytes of {} to: \"{}\"", body_content.len(), content_type, url);
+    if settings::test_mode_enabled() {return Ok((TEST_MODE_RESPONSE.as_bytes().to_vec(), String::new()));}
+    let content_type = match content_type.parse() {
+        Ok(x) => ContentType(x),
+        Err(_) => return Err("invalid content type".to_string()),
+    };
+    let mut response = match client.post(url).header(content_type).body(body_content.to_vec()).send() {
         Ok(result) => result,
         Err(err) => return Err("could not connect".to_string()),
     };
@@ -14,15 +34,14 @@ pub fn post(body_content: &str, url: &str) -> Result<String,String> {
     info!("Response: {:?}", response);
     if !response.status().is_success() {return Err("POST failed".to_string());}
 
-    let mut content = String::new();
-    match response.read_to_string(&mut content) {
-        Ok(_) => {info!("Response: {}", content); Ok(content.to_owned())},
+    let response_type = response.headers().get::<ContentType>().map(|x| x.to_string()).unwrap_or_default();
+    let mut content = Vec::new();
+    match response.read_to_end(&mut content) {
+        Ok(_) => {info!("Response: {} bytes of {}", content.len(), response_type); Ok((content, response_type))},
         Err(_) => Err("could not read response".to_string()),
     }
 }
 
-
-
 #[cfg(test)]
 mod tests {
     extern crate mockito;
@@ -61,6 +80,24 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_httpclient_returns_response_content_type() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"false");
+        let _m = mockito::mock("POST", "/agent/msgpack")
+            .with_status(200)
+            .with_header("content-type", "application/msgpack")
+            .with_body("world")
+            .create();
+
+        let mut my_url = String::from(URL);
+        my_url.push_str("/agent/msgpack");
+
+        match httpclient::post_u8(b"anything", &my_url, "application/json") {
+            Err(x) => assert_eq!(1,0), //should fail if we get here
+            Ok(x) => assert_eq!(x, (b"world".to_vec(), "application/msgpack".to_string())),
+        };
+    }
+
     #[test]
     fn test_httpclient_fails_with_bad_url() {
         settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"false");
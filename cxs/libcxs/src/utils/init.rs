@@ -4,6 +4,8 @@ use utils::error;
 pub fn indy_error_to_cxs_error_code(err: i32) ->  u32 {
     match err {
         0 => error::SUCCESS.code_num,
+        // WalletNotFoundError, also what a lookup of a missing wallet record fails with
+        204 => error::WALLET_RECORD_NOT_FOUND.code_num,
         _ => error::UNKNOWN_ERROR.code_num,
     }
 }
@@ -22,6 +24,7 @@ mod tests {
         let cxs_error = &error::UNKNOWN_ERROR;
         assert_eq!(indy_error_to_cxs_error_code(indy_error), cxs_error.code_num);
 
+        assert_eq!(indy_error_to_cxs_error_code(204), error::WALLET_RECORD_NOT_FOUND.code_num);
     }
 
 
@@ -0,0 +1,309 @@
+extern crate libc;
+extern crate serde_json;
+
+use self::libc::c_char;
+use std::ffi::CString;
+use serde_json::Value;
+use settings;
+use utils::error;
+use utils::pool;
+use utils::wallet;
+use utils::anoncreds;
+use utils::callback::{ReturnI32Str, check_indy_call};
+
+pub static SCHEMA_TXN_TYPE: &'static str = "101";
+pub static GET_SCHEMA_TXN_TYPE: &'static str = "107";
+pub static CLAIM_DEF_TXN_TYPE: &'static str = "102";
+pub static GET_CLAIM_DEF_TXN_TYPE: &'static str = "108";
+pub static GET_TXN_TYPE: &'static str = "3";
+pub static CLAIM_DEF_SIGNATURE_TYPE: &'static str = "CL";
+pub static TEST_MODE_SEQ_NO: u32 = 15;
+
+extern {
+    fn indy_build_schema_request(command_handle: i32,
+                                 submitter_did: *const c_char,
+                                 data: *const c_char,
+                                 cb: Option<extern fn(xcommand_handle: i32, err: i32, request_json: *const c_char)>) -> i32;
+
+    fn indy_build_get_schema_request(command_handle: i32,
+                                     submitter_did: *const c_char,
+                                     dest: *const c_char,
+                                     data: *const c_char,
+                                     cb: Option<extern fn(xcommand_handle: i32, err: i32, request_json: *const c_char)>) -> i32;
+
+    fn indy_build_claim_def_txn(command_handle: i32,
+                                submitter_did: *const c_char,
+                                xref: i32,
+                                signature_type: *const c_char,
+                                data: *const c_char,
+                                cb: Option<extern fn(xcommand_handle: i32, err: i32, request_json: *const c_char)>) -> i32;
+
+    fn indy_build_get_claim_def_txn(command_handle: i32,
+                                    submitter_did: *const c_char,
+                                    xref: i32,
+                                    signature_type: *const c_char,
+                                    origin: *const c_char,
+                                    cb: Option<extern fn(xcommand_handle: i32, err: i32, request_json: *const c_char)>) -> i32;
+
+    fn indy_build_get_txn_request(command_handle: i32,
+                                  submitter_did: *const c_char,
+                                  data: i32,
+                                  cb: Option<extern fn(xcommand_handle: i32, err: i32, request_json: *const c_char)>) -> i32;
+
+    fn indy_sign_and_submit_request(command_handle: i32,
+                                    pool_handle: i32,
+                                    wallet_handle: i32,
+                                    submitter_did: *const c_char,
+                                    request_json: *const c_char,
+                                    cb: Option<extern fn(xcommand_handle: i32, err: i32, request_result_json: *const c_char)>) -> i32;
+
+    fn indy_submit_request(command_handle: i32,
+                           pool_handle: i32,
+                           request_json: *const c_char,
+                           cb: Option<extern fn(xcommand_handle: i32, err: i32, request_result_json: *const c_char)>) -> i32;
+}
+
+pub fn build_schema_request(submitter_did: &str, data: &str) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(test_mode_request(submitter_did, SCHEMA_TXN_TYPE, data)); }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    unsafe {
+        let indy_err = indy_build_schema_request(rtn_obj.command_handle,
+                                                 CString::new(submitter_did).unwrap().as_ptr(),
+                                                 CString::new(data).unwrap().as_ptr(),
+                                                 rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+pub fn build_get_schema_request(submitter_did: &str, dest: &str, data: &str) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(test_mode_request(submitter_did, GET_SCHEMA_TXN_TYPE, data)); }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    unsafe {
+        let indy_err = indy_build_get_schema_request(rtn_obj.command_handle,
+                                                     CString::new(submitter_did).unwrap().as_ptr(),
+                                                     CString::new(dest).unwrap().as_ptr(),
+                                                     CString::new(data).unwrap().as_ptr(),
+                                                     rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+pub fn build_claim_def_txn(submitter_did: &str, schema_seq_no: u32, data: &str) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(test_mode_request(submitter_did, CLAIM_DEF_TXN_TYPE, data)); }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    unsafe {
+        let indy_err = indy_build_claim_def_txn(rtn_obj.command_handle,
+                                                CString::new(submitter_did).unwrap().as_ptr(),
+                                                schema_seq_no as i32,
+                                                CString::new(CLAIM_DEF_SIGNATURE_TYPE).unwrap().as_ptr(),
+                                                CString::new(data).unwrap().as_ptr(),
+                                                rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+pub fn build_get_claim_def_txn(submitter_did: &str, schema_seq_no: u32, origin: &str) -> Result<String, u32> {
+    if settings::test_mode_enabled() {
+        let data = json!({"ref": schema_seq_no, "origin": origin}).to_string();
+        return Ok(test_mode_request(submitter_did, GET_CLAIM_DEF_TXN_TYPE, &data));
+    }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    unsafe {
+        let indy_err = indy_build_get_claim_def_txn(rtn_obj.command_handle,
+                                                    CString::new(submitter_did).unwrap().as_ptr(),
+                                                    schema_seq_no as i32,
+                                                    CString::new(CLAIM_DEF_SIGNATURE_TYPE).unwrap().as_ptr(),
+                                                    CString::new(origin).unwrap().as_ptr(),
+                                                    rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+pub fn build_get_txn_request(submitter_did: &str, seq_no: u32) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(test_mode_request(submitter_did, GET_TXN_TYPE, &seq_no.to_string())); }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    unsafe {
+        let indy_err = indy_build_get_txn_request(rtn_obj.command_handle,
+                                                  CString::new(submitter_did).unwrap().as_ptr(),
+                                                  seq_no as i32,
+                                                  rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+pub fn sign_and_submit_request(submitter_did: &str, request_json: &str) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(test_mode_response(request_json)); }
+
+    let pool_handle = pool::get_pool_handle()?;
+    let wallet_handle = wallet::get_wallet_handle();
+    let rtn_obj = ReturnI32Str::new();
+
+    info!("signing and submitting ledger request: {}", request_json);
+    unsafe {
+        let indy_err = indy_sign_and_submit_request(rtn_obj.command_handle,
+                                                    pool_handle,
+                                                    wallet_handle,
+                                                    CString::new(submitter_did).unwrap().as_ptr(),
+                                                    CString::new(request_json).unwrap().as_ptr(),
+                                                    rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+pub fn submit_request(request_json: &str) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Ok(test_mode_response(request_json)); }
+
+    let pool_handle = pool::get_pool_handle()?;
+    let rtn_obj = ReturnI32Str::new();
+
+    info!("submitting ledger request: {}", request_json);
+    unsafe {
+        let indy_err = indy_submit_request(rtn_obj.command_handle,
+                                           pool_handle,
+                                           CString::new(request_json).unwrap().as_ptr(),
+                                           rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Returns the "result" object of a ledger reply, or an error if the ledger rejected the request.
+pub fn get_txn_result(response: &str) -> Result<Value, u32> {
+    let json: Value = match serde_json::from_str(response) {
+        Ok(x) => x,
+        Err(_) => {
+            warn!("ledger response was not valid json: {}", response);
+            return Err(error::INVALID_LEDGER_RESPONSE.code_num)
+        },
+    };
+
+    match json["op"].as_str() {
+        Some("REPLY") => Ok(json["result"].clone()),
+        _ => {
+            warn!("ledger did not accept request: {}", json["reason"]);
+            Err(error::INVALID_LEDGER_RESPONSE.code_num)
+        },
+    }
+}
+
+pub fn get_seq_no(response: &str) -> Result<u32, u32> {
+    match get_txn_result(response)?["seqNo"].as_u64() {
+        Some(x) => Ok(x as u32),
+        None => Err(error::INVALID_LEDGER_RESPONSE.code_num),
+    }
+}
+
+// TEST MODE: ledger requests are never sent, so build a request that keeps enough of the
+// operation around for test_mode_response to answer it.
+fn test_mode_request(submitter_did: &str, txn_type: &str, data: &str) -> String {
+    let data: Value = serde_json::from_str(data).unwrap_or(Value::String(data.to_string()));
+    json!({
+        "reqId": 1,
+        "identifier": submitter_did,
+        "operation": {
+            "type": txn_type,
+            "data": data
+        }
+    }).to_string()
+}
+
+fn test_mode_response(request_json: &str) -> String {
+    let request: Value = serde_json::from_str(request_json).unwrap_or(Value::Null);
+    let operation = &request["operation"];
+    let txn_type = operation["type"].as_str().unwrap_or_default();
+
+    let mut result = json!({
+        "seqNo": TEST_MODE_SEQ_NO,
+        "type": txn_type,
+        "identifier": request["identifier"],
+        "reqId": request["reqId"]
+    });
+
+    if txn_type == GET_SCHEMA_TXN_TYPE {
+        let mut data = operation["data"].clone();
+        data["attr_names"] = json!(["name", "age", "height", "sex"]);
+        result["data"] = data;
+    } else if txn_type == GET_CLAIM_DEF_TXN_TYPE {
+        let claim_def: Value = serde_json::from_str(anoncreds::CLAIM_DEF_TEST_MODE).unwrap();
+        result["ref"] = operation["data"]["ref"].clone();
+        result["origin"] = operation["data"]["origin"].clone();
+        result["signature_type"] = json!(CLAIM_DEF_SIGNATURE_TYPE);
+        result["data"] = claim_def["data"].clone();
+    } else if txn_type == GET_TXN_TYPE {
+        result["data"] = json!({
+            "seqNo": operation["data"],
+            "type": SCHEMA_TXN_TYPE,
+            "identifier": request["identifier"],
+            "data": {"name": "test-schema", "version": "1.0", "attr_names": ["name", "age", "height", "sex"]}
+        });
+    }
+
+    json!({"op": "REPLY", "result": result}).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_seq_no_from_reply() {
+        let response = "{\"op\":\"REPLY\",\"result\":{\"seqNo\":22,\"type\":\"101\"}}";
+        assert_eq!(get_seq_no(response).unwrap(), 22);
+    }
+
+    #[test]
+    fn test_get_txn_result_fails_on_reject() {
+        let response = "{\"op\":\"REJECT\",\"reason\":\"client request invalid\"}";
+        assert_eq!(get_txn_result(response), Err(error::INVALID_LEDGER_RESPONSE.code_num));
+        assert_eq!(get_seq_no("garbage"), Err(error::INVALID_LEDGER_RESPONSE.code_num));
+    }
+
+    #[test]
+    fn test_schema_request_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let request = build_schema_request("8XFh8yBzrpJQmNyZzgoTqB", "{\"name\":\"test\"}").unwrap();
+        let response = sign_and_submit_request("8XFh8yBzrpJQmNyZzgoTqB", &request).unwrap();
+        assert_eq!(get_seq_no(&response).unwrap(), TEST_MODE_SEQ_NO);
+    }
+
+    #[test]
+    fn test_get_claim_def_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let request = build_get_claim_def_txn("8XFh8yBzrpJQmNyZzgoTqB", 15, "4fUDR9R7fjwELRvH9JT6HH").unwrap();
+        let result = get_txn_result(&submit_request(&request).unwrap()).unwrap();
+        assert_eq!(result["ref"], json!(15));
+        assert!(result["data"]["primary"].is_object());
+    }
+
+    #[test]
+    fn test_get_txn_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        let request = build_get_txn_request("8XFh8yBzrpJQmNyZzgoTqB", 15).unwrap();
+        let result = get_txn_result(&submit_request(&request).unwrap()).unwrap();
+        assert_eq!(result["data"]["seqNo"], json!(15));
+        assert_eq!(result["data"]["type"], json!(SCHEMA_TXN_TYPE));
+    }
+}
@@ -9,8 +9,14 @@ pub mod wallet;
 pub mod init;
 pub mod error;
 pub mod httpclient;
+pub mod callback;
+pub mod ledger;
+pub mod anoncreds;
+pub mod crypto;
+pub mod qr;
 
 use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 lazy_static! {
     static ref COMMAND_HANDLE_COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
 }
@@ -20,5 +26,13 @@ pub fn generate_command_handle() -> i32 {
     command_handle
 }
 
+// seconds since the epoch, for timestamps kept on objects
+pub fn now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(x) => x.as_secs(),
+        Err(_) => 0,
+    }
+}
+
 #[macro_use]
 pub mod logger;
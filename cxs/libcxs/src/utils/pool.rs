@@ -5,6 +5,11 @@ use std::ffi::CString;
 use utils::cstring::CStringUtils;
 use utils::generate_command_handle;
 use utils::init::indy_error_to_cxs_error_code;
+use utils::callback::{ReturnI32I32, check_indy_call};
+use std::ptr::null;
+use settings;
+
+pub static mut POOL_HANDLE: i32 = 0;
 
 extern {
     fn indy_create_pool_ledger_config(command_handle: i32,
@@ -15,6 +20,11 @@ extern {
     fn indy_delete_pool_ledger_config(command_handle: i32,
                                              config_name: *const c_char,
                                              cb: Option<extern fn(xcommand_handle: i32, err: i32)>) -> i32;
+
+    fn indy_open_pool_ledger(command_handle: i32,
+                             config_name: *const c_char,
+                             config: *const c_char,
+                             cb: Option<extern fn(xcommand_handle: i32, err: i32, pool_handle: i32)>) -> i32;
 }
 
 pub fn create_pool_config<'a>(pool1:&str, config_name:&str)-> u32 {
@@ -39,6 +49,37 @@ pub fn create_pool_config<'a>(pool1:&str, config_name:&str)-> u32 {
     }
 }
 
+pub fn open_pool_ledger(config_name: &str) -> Result<i32, u32> {
+    let rtn_obj = ReturnI32I32::new();
+    let c_config_name = CString::new(config_name).unwrap();
+
+    unsafe {
+        let indy_err = indy_open_pool_ledger(rtn_obj.command_handle,
+                                             c_config_name.as_ptr(),
+                                             null(),
+                                             rtn_obj.get_callback());
+
+        info!("indy_open_pool_ledger returned {}", indy_err);
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Returns the handle of the opened pool ledger, opening it on first use.
+pub fn get_pool_handle() -> Result<i32, u32> {
+    if settings::test_mode_enabled() { return Ok(1); }
+
+    unsafe {
+        if POOL_HANDLE > 0 { return Ok(POOL_HANDLE); }
+    }
+
+    let config_name = settings::get_config_value(settings::CONFIG_POOL_CONFIG_NAME)?;
+    let handle = open_pool_ledger(&config_name)?;
+    unsafe { POOL_HANDLE = handle; }
+    Ok(handle)
+}
+
 pub fn delete_pool_config(config: &str) {
     let command_handle: i32 = generate_command_handle();
     extern "C" fn f(_handle: i32, _err: i32) { }
@@ -66,5 +107,11 @@ mod tests {
         delete_pool_config("config1");
     }
 
+    #[test]
+    fn test_get_pool_handle_in_test_mode() {
+        settings::set_config_value(settings::CONFIG_ENABLE_TEST_MODE,"true");
+        assert_eq!(get_pool_handle().unwrap(), 1);
+    }
+
 
 }
\ No newline at end of file
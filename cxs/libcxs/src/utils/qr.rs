@@ -0,0 +1,85 @@
+extern crate qrcode;
+extern crate png;
+
+use self::qrcode::{QrCode, Color};
+use self::png::HasParameters;
+use utils::error;
+
+// light modules around the code that readers need to find it
+pub static QUIET_ZONE: usize = 4;
+pub static PNG_MODULE_PIXELS: usize = 8;
+
+// the modules of the code, row by row, with the quiet zone added
+fn modules(data: &str) -> Result<(usize, Vec<bool>), u32> {
+    let code = match QrCode::new(data.as_bytes()) {
+        Ok(x) => x,
+        Err(x) => {
+            warn!("could not encode {} bytes as a qr code: {:?}", data.len(), x);
+            return Err(error::QR_CODE_ERROR.code_num);
+        },
+    };
+
+    let width = code.width();
+    let size = width + 2 * QUIET_ZONE;
+    let mut dark = vec![false; size * size];
+    for (i, color) in code.to_colors().iter().enumerate() {
+        dark[(i / width + QUIET_ZONE) * size + i % width + QUIET_ZONE] = *color == Color::Dark;
+    }
+    Ok((size, dark))
+}
+
+/// Renders data as a qr code in an svg document, one unit per module.
+pub fn svg(data: &str) -> Result<String, u32> {
+    let (size, dark) = modules(data)?;
+
+    let mut path = String::new();
+    for (i, _) in dark.iter().enumerate().filter(|&(_, x)| *x) {
+        path.push_str(&format!("M{} {}h1v1h-1z", i % size, i / size));
+    }
+
+    Ok(format!("<?xml version=\"1.0\" standalone=\"yes\"?>\
+        <svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" viewBox=\"0 0 {0} {0}\" shape-rendering=\"crispEdges\">\
+        <rect width=\"{0}\" height=\"{0}\" fill=\"#fff\"/><path fill=\"#000\" d=\"{1}\"/></svg>", size, path))
+}
+
+/// Renders data as a qr code in a greyscale png, PNG_MODULE_PIXELS pixels per module.
+pub fn png(data: &str) -> Result<Vec<u8>, u32> {
+    let (size, dark) = modules(data)?;
+    let pixels = size * PNG_MODULE_PIXELS;
+
+    let mut image = Vec::with_capacity(pixels * pixels);
+    for y in 0..pixels {
+        for x in 0..pixels {
+            image.push(if dark[(y / PNG_MODULE_PIXELS) * size + x / PNG_MODULE_PIXELS] { 0 } else { 255 });
+        }
+    }
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, pixels as u32, pixels as u32);
+        encoder.set(png::ColorType::Grayscale).set(png::BitDepth::Eight);
+        let written = encoder.write_header().and_then(|mut x| x.write_image_data(&image));
+        if let Err(x) = written {
+            warn!("could not write qr code png: {}", x);
+            return Err(error::QR_CODE_ERROR.code_num);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_qr_code() {
+        let image = svg("http://127.0.0.1:8080/agency/invite?c=abc").unwrap();
+        assert!(image.starts_with("<?xml"));
+        assert!(image.ends_with("</svg>"));
+
+        let image = png("http://127.0.0.1:8080/agency/invite?c=abc").unwrap();
+        assert_eq!(&image[1..4], b"PNG");
+
+        assert_eq!(svg(&"x".repeat(8000)), Err(error::QR_CODE_ERROR.code_num));
+    }
+}
@@ -1,14 +1,17 @@
 extern crate libc;
+extern crate serde_json;
 
 use self::libc::c_char;
 use std::ffi::CString;
+use serde_json::{Map, Value};
 use connection;
 use settings;
 use utils::error;
 use utils::cstring::CStringUtils;
 use std::ptr::null;
-use utils::generate_command_handle;
+use utils::{generate_command_handle, now};
 use utils::init::indy_error_to_cxs_error_code;
+use utils::callback::{ReturnI32, ReturnI32Str, ReturnI32StrStrStr, check_indy_call};
 use api::CxsStateType;
 use std::thread;
 use rand::{thread_rng, Rng};
@@ -47,6 +50,18 @@ extern {
                                                          did: *const c_char,
                                                          verkey: *const c_char,
                                                          pk: *const c_char)>) -> i32;
+
+    fn indy_get_did_metadata(command_handle: i32,
+                             wallet_handle: i32,
+                             did: *const c_char,
+                             cb: Option<extern fn(xcommand_handle: i32, err: i32,
+                                                  metadata: *const c_char)>) -> i32;
+
+    fn indy_set_did_metadata(command_handle: i32,
+                             wallet_handle: i32,
+                             did: *const c_char,
+                             metadata: *const c_char,
+                             cb: Option<extern fn(xcommand_handle: i32, err: i32)>) -> i32;
 }
 
 
@@ -135,6 +150,85 @@ pub fn create_and_store_my_did(handle: u32, did_json: &str) -> Result<u32, Strin
     }
 }
 
+/// Creates a did in the wallet and waits for it, returning the did and its verkey.  Unlike
+/// create_and_store_my_did it leaves setting up the connection to the caller.
+pub fn create_my_did(did_json: &str) -> Result<(String, String), u32> {
+    if settings::test_mode_enabled() {
+        return Ok(("8XFh8yBzrpJQmNyZzgoTqB".to_string(), "EkVTa7SCJ5SntpYyX7CSb2pcBhiVGT9kWSagA8a9T69A".to_string()));
+    }
+
+    let rtn_obj = ReturnI32StrStrStr::new();
+
+    unsafe {
+        let indy_err = indy_create_and_store_my_did(rtn_obj.command_handle,
+                                                    get_wallet_handle(),
+                                                    CString::new(did_json).unwrap().as_ptr(),
+                                                    rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    let (did, verkey, _) = rtn_obj.receive()?;
+    Ok((did, verkey))
+}
+
+/// The metadata stored with one of our dids, failing with WALLET_RECORD_NOT_FOUND when it has none.
+pub fn get_my_did_metadata(did: &str) -> Result<String, u32> {
+    if settings::test_mode_enabled() { return Err(error::WALLET_RECORD_NOT_FOUND.code_num); }
+
+    let rtn_obj = ReturnI32Str::new();
+
+    unsafe {
+        let indy_err = indy_get_did_metadata(rtn_obj.command_handle,
+                                             get_wallet_handle(),
+                                             CString::new(did).unwrap().as_ptr(),
+                                             rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+/// Marks one of our dids as retired in its wallet metadata, so it is known to be no longer
+/// in use while its keys stay available for old messages.  Metadata the did already has is
+/// kept, metadata that isn't a json object under "metadata".
+pub fn retire_my_did(did: &str) -> Result<(), u32> {
+    if settings::test_mode_enabled() { return Ok(()); }
+
+    let existing = match get_my_did_metadata(did) {
+        Ok(x) => x,
+        Err(x) if x == error::WALLET_RECORD_NOT_FOUND.code_num => String::new(),
+        Err(x) => return Err(x),
+    };
+    let metadata = retired_metadata(&existing, now()).to_string();
+
+    let rtn_obj = ReturnI32::new();
+
+    unsafe {
+        let indy_err = indy_set_did_metadata(rtn_obj.command_handle,
+                                             get_wallet_handle(),
+                                             CString::new(did).unwrap().as_ptr(),
+                                             CString::new(metadata).unwrap().as_ptr(),
+                                             rtn_obj.get_callback());
+        check_indy_call(rtn_obj.command_handle, indy_err)?;
+    }
+
+    rtn_obj.receive()
+}
+
+fn retired_metadata(existing: &str, retired_at: u64) -> Value {
+    let mut metadata = match serde_json::from_str(existing) {
+        Ok(Value::Object(x)) => x,
+        _ if existing.is_empty() => Map::new(),
+        _ => {
+            let mut x = Map::new();
+            x.insert("metadata".to_string(), Value::String(existing.to_string()));
+            x
+        },
+    };
+    metadata.insert("retired".to_string(), Value::Bool(true));
+    metadata.insert("retired_at".to_string(), json!(retired_at));
+    Value::Object(metadata)
+}
 
 extern "C" fn store_new_did_info_cb(handle: i32,
                                     err: i32,
@@ -158,7 +252,9 @@ extern "C" fn store_new_did_info_cb(handle: i32,
         Ok(_) => info!("updated profile on agent"),
     };
 
-    connection::set_state(handle as u32, CxsStateType::CxsStateInitialized);
+    if let Err(x) = connection::set_state(handle as u32, CxsStateType::CxsStateInitialized) {
+        error!("could not initialize connection {}: {}", handle as u32, x);
+    }
 }
 
 #[cfg(test)]
@@ -228,4 +324,12 @@ pub mod tests {
         assert!(!connection::get_pw_verkey(handle).unwrap().is_empty());
     }
 
+    #[test]
+    fn test_retired_metadata_keeps_existing_metadata() {
+        assert_eq!(retired_metadata("", 1510000000), json!({"retired": true, "retired_at": 1510000000}));
+        assert_eq!(retired_metadata("{\"label\":\"alice\",\"retired\":false}", 1510000000),
+                   json!({"label": "alice", "retired": true, "retired_at": 1510000000}));
+        assert_eq!(retired_metadata("alice", 1510000000),
+                   json!({"metadata": "alice", "retired": true, "retired_at": 1510000000}));
+    }
 }